@@ -0,0 +1,31 @@
+use crate::lexical::span::Span;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+/// A construct of a checked program that cannot be translated into moon code
+#[derive(Debug, Clone, PartialEq)]
+pub struct CodeGenerationError {
+    pub(crate) span: Span,
+    pub(crate) message: String,
+}
+
+impl CodeGenerationError {
+    pub fn new(span: Span, message: &str) -> Self {
+        Self {
+            span,
+            message: message.to_string(),
+        }
+    }
+}
+
+impl Display for CodeGenerationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Code generation error at line {}, column {}: {}",
+            self.span.start.0, self.span.start.1, self.message
+        )
+    }
+}
+
+impl Error for CodeGenerationError {}
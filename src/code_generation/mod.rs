@@ -1,2 +1,5 @@
+pub mod code_generation_error;
+#[cfg(test)]
+mod moon;
 mod register;
-mod translation;
+pub mod translation;
@@ -0,0 +1,240 @@
+use std::collections::HashMap;
+
+/// Assemble and run moon code as the moon simulator would, reading `input` with `getc`.
+/// Return what it printed with `putc`, or why it could not run. Constants in instructions
+/// must fit in 16 bits.
+pub fn run(code: &str, input: &str) -> Result<String, String> {
+    let program = assemble(code)?;
+    Machine {
+        program: &program,
+        registers: [0; 16],
+        input: input.bytes().collect(),
+        output: String::new(),
+    }
+    .run()
+}
+
+struct Instruction {
+    line: usize,
+    opcode: String,
+    operands: Vec<String>,
+}
+
+struct Program {
+    /// instructions by address
+    instructions: HashMap<i32, Instruction>,
+    memory: HashMap<i32, i32>,
+    labels: HashMap<String, i32>,
+    entry: i32,
+}
+
+fn assemble(code: &str) -> Result<Program, String> {
+    let mut program = Program {
+        instructions: HashMap::new(),
+        memory: HashMap::new(),
+        labels: HashMap::new(),
+        entry: 0,
+    };
+    let mut address = 0;
+    let mut words = vec![];
+    for (line, text) in code.lines().enumerate() {
+        let text = text.split('%').next().unwrap();
+        if text.trim().is_empty() {
+            continue;
+        }
+        let mut fields = text.split_whitespace();
+        if !text.starts_with(char::is_whitespace) {
+            let label = fields.next().unwrap().to_string();
+            if program.labels.insert(label.clone(), address).is_some() {
+                return Err(format!(
+                    "line {}: label {} is defined twice",
+                    line + 1,
+                    label
+                ));
+            }
+        }
+        let opcode = match fields.next() {
+            Some(opcode) => opcode.to_string(),
+            None => continue,
+        };
+        let operands: Vec<String> = fields
+            .collect::<Vec<_>>()
+            .join("")
+            .split(',')
+            .filter(|operand| !operand.is_empty())
+            .map(str::to_string)
+            .collect();
+        match opcode.as_str() {
+            "entry" => program.entry = address,
+            "dw" => {
+                for operand in operands {
+                    words.push((address, operand, line));
+                    address += 4;
+                }
+            }
+            "res" => address += operands[0].parse::<i32>().map_err(|e| e.to_string())?,
+            _ => {
+                program.instructions.insert(
+                    address,
+                    Instruction {
+                        line: line + 1,
+                        opcode,
+                        operands,
+                    },
+                );
+                address += 4;
+            }
+        }
+    }
+    for (address, word, line) in words {
+        let value = program
+            .value(&word, i32::MIN as i64, i32::MAX as i64)
+            .map_err(|e| format!("line {}: {}", line + 1, e))?;
+        program.memory.insert(address, value);
+    }
+    Ok(program)
+}
+
+impl Program {
+    /// The value of a number or a label, which must fit between the bounds
+    fn value(&self, operand: &str, min: i64, max: i64) -> Result<i32, String> {
+        let value = match operand.parse::<i64>() {
+            Ok(value) => value,
+            Err(_) => *self
+                .labels
+                .get(operand)
+                .ok_or(format!("label {} is undefined", operand))? as i64,
+        };
+        if value < min || value > max {
+            return Err(format!("{} does not fit in the instruction", operand));
+        }
+        Ok(value as i32)
+    }
+}
+
+struct Machine<'a> {
+    program: &'a Program,
+    registers: [i32; 16],
+    input: std::collections::VecDeque<u8>,
+    output: String,
+}
+
+impl Machine<'_> {
+    fn run(mut self) -> Result<String, String> {
+        let mut memory = self.program.memory.clone();
+        let mut pc = self.program.entry;
+        for _ in 0..10_000_000 {
+            let instruction = self
+                .program
+                .instructions
+                .get(&pc)
+                .ok_or(format!("no instruction at address {}", pc))?;
+            let fail = |e: String| format!("line {}: {}", instruction.line, e);
+            let operands = &instruction.operands;
+            let register = |operand: &str| -> Result<usize, String> {
+                operand
+                    .strip_prefix('r')
+                    .and_then(|number| number.parse::<usize>().ok())
+                    .filter(|number| *number < 16)
+                    .ok_or(format!("{} is not a register", operand))
+            };
+            // `K(Rj)`
+            let address = |operand: &str, registers: &[i32; 16]| -> Result<i32, String> {
+                let (k, rj) = operand
+                    .strip_suffix(')')
+                    .and_then(|operand| operand.split_once('('))
+                    .ok_or(format!("{} is not an address", operand))?;
+                let k = self.program.value(k, i16::MIN as i64, i16::MAX as i64)?;
+                Ok(k.wrapping_add(registers[register(rj)?]))
+            };
+            let immediate = |operand: &str| {
+                self.program
+                    .value(operand, i16::MIN as i64, i16::MAX as i64)
+            };
+            let mut next = pc + 4;
+            let r = self.registers;
+            let mut set = |ri: &str, value: i32| -> Result<(), String> {
+                let ri = register(ri)?;
+                if ri != 0 {
+                    self.registers[ri] = value;
+                }
+                Ok(())
+            };
+            let arithmetic = |opcode: &str, a: i32, b: i32| -> Result<i32, String> {
+                Ok(match opcode {
+                    "add" => a.wrapping_add(b),
+                    "sub" => a.wrapping_sub(b),
+                    "mul" => a.wrapping_mul(b),
+                    "div" | "mod" if b == 0 => return Err("division by zero".to_string()),
+                    "div" => a.wrapping_div(b),
+                    "mod" => a.wrapping_rem(b),
+                    "and" => (a != 0 && b != 0) as i32,
+                    "or" => (a != 0 || b != 0) as i32,
+                    "ceq" => (a == b) as i32,
+                    "cne" => (a != b) as i32,
+                    "clt" => (a < b) as i32,
+                    "cle" => (a <= b) as i32,
+                    "cgt" => (a > b) as i32,
+                    "cge" => (a >= b) as i32,
+                    _ => return Err(format!("unknown instruction {}", opcode)),
+                })
+            };
+            let opcode = instruction.opcode.as_str();
+            (|| -> Result<(), String> {
+                match (opcode, operands.as_slice()) {
+                    ("lw", [ri, k_rj]) => {
+                        let address = address(k_rj, &r)?;
+                        set(ri, *memory.get(&address).unwrap_or(&0))?
+                    }
+                    ("sw", [k_rj, ri]) => {
+                        memory.insert(address(k_rj, &r)?, r[register(ri)?]);
+                    }
+                    ("not", [ri, rj]) => set(ri, (r[register(rj)?] == 0) as i32)?,
+                    ("bz", [ri, k]) => {
+                        if r[register(ri)?] == 0 {
+                            next = immediate(k)?
+                        }
+                    }
+                    ("bnz", [ri, k]) => {
+                        if r[register(ri)?] != 0 {
+                            next = immediate(k)?
+                        }
+                    }
+                    ("j", [k]) => next = immediate(k)?,
+                    ("jr", [ri]) => next = r[register(ri)?],
+                    ("jl", [ri, k]) => {
+                        set(ri, pc + 4)?;
+                        next = immediate(k)?
+                    }
+                    ("putc", [ri]) => self.output.push(r[register(ri)?] as u8 as char),
+                    ("getc", [ri]) => {
+                        let c = self.input.pop_front().map_or(-1, |c| c as i32);
+                        set(ri, c)?
+                    }
+                    ("nop", []) => {}
+                    ("hlt", []) => next = -1,
+                    (_, [ri, rj, rk]) if opcode.ends_with('i') => {
+                        let value = arithmetic(
+                            &opcode[..opcode.len() - 1],
+                            r[register(rj)?],
+                            immediate(rk)?,
+                        )?;
+                        set(ri, value)?
+                    }
+                    (_, [ri, rj, rk]) => {
+                        let value = arithmetic(opcode, r[register(rj)?], r[register(rk)?])?;
+                        set(ri, value)?
+                    }
+                    _ => return Err(format!("invalid instruction {}", opcode)),
+                }
+                Ok(())
+            })()
+            .map_err(fail)?;
+            if next == -1 {
+                return Ok(self.output);
+            }
+            pc = next;
+        }
+        Err("the program does not halt".to_string())
+    }
+}
@@ -1,3 +1,5 @@
+/// The registers left for expressions. r0 is always zero, r14 is kept for a stack
+/// pointer and r15 holds the return address of `jl`.
 pub struct RegisterPool {
     registers: Vec<i32>,
 }
@@ -5,18 +7,23 @@ pub struct RegisterPool {
 impl RegisterPool {
     pub fn new() -> RegisterPool {
         Self {
-            registers: vec![0, 1, 2, 3, 4, 5,6 7, 8, 9, 10, 11, 12, 13, 14, 15],
+            registers: (1..=13).rev().collect(),
         }
     }
-    pub fn get_register(&mut self) -> i32 {
-        let reg_option = self.registers.pop();
-        match reg_option {
-            None => panic!("Registers have been used up"),
-            Some(rid) => rid,
-        }
+
+    /// A free register, or `None` once they are all in use
+    pub fn get_register(&mut self) -> Option<i32> {
+        self.registers.pop()
     }
 
     pub fn give_back(&mut self, rid: i32) {
         self.registers.push(rid);
     }
+
+    /// The registers handed out and not given back yet, in increasing order
+    pub fn in_use(&self) -> Vec<i32> {
+        (1..=13)
+            .filter(|rid| !self.registers.contains(rid))
+            .collect()
+    }
 }
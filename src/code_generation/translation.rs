@@ -1,32 +1,1131 @@
+use crate::code_generation::code_generation_error::CodeGenerationError;
 use crate::code_generation::register::RegisterPool;
-use crate::semantic::concept::{CompositeConcept, Concept};
-use crate::semantic::symbol_table::SymbolTable;
-use crate::syntactic::tree::NodeId;
-use crate::AbstractSyntaxTree;
-use core::panicking::panic;
-use std::collections::HashMap;
+use crate::lexical::span::Span;
+use crate::semantic::constant_folding;
+use crate::semantic::constant_folding::ConstantValue;
+use crate::semantic::symbol_table::{SymbolKind, SymbolTable, SymbolTableEntry};
+use crate::semantic::typed_ast::{
+    BaseType, BinaryOp, Expr, ExprKind, FuncDef, Initializer, Item, Program, Stmt, StmtKind,
+    TypeSpec, UnaryOp, VarDecl,
+};
+use crate::semantic::visit::{walk_expr, walk_var_decl, Visitor};
+use std::collections::{HashMap, HashSet};
 
+/// Translate a program in the core language of `desugar`, with the symbol tables of a
+/// check that found no errors
 pub fn generate_moon_code(
-    ast: &AbstractSyntaxTree,
+    program: &Program,
     table_container: &HashMap<String, SymbolTable>,
-) {
-    let mut result_code = String::from("");
-    let mut register_pool = RegisterPool::new();
-    translate(ast.get_root(), ast, table_container, &mut register_pool);
+) -> Result<String> {
+    let mut generator = CodeGenerator::new(table_container);
+    generator.translate(program)?;
+    Ok(generator.finish())
 }
 
-pub fn translate(
-    node: NodeId,
-    ast: &AbstractSyntaxTree,
-    table_container: &HashMap<String, SymbolTable>,
-    register_pool: &mut RegisterPool,
-) {
-    let node_value = ast.get_node_value(node);
-    match node_value {
-        Concept::AtomicConcept(ac) => {}
-        Concept::CompositeConcept(cc) => match cc {
-            CompositeConcept::VarDecl => {}
-            _ => panic!("Unhandled composite concept"),
-        },
+type Result<T> = std::result::Result<T, CodeGenerationError>;
+
+/// Translates the typed AST into moon code. Variables are given memory of their own,
+/// labelled after the function and the variable name, and so are the parameters, the
+/// return value and the return address of each function. Functions therefore cannot be
+/// recursive. Registers in use are saved around calls.
+pub struct CodeGenerator<'a> {
+    table_container: &'a HashMap<String, SymbolTable>,
+    register_pool: RegisterPool,
+    code: String,
+    data: String,
+    label_count: usize,
+    /// the table of the function being translated
+    scope: String,
+    /// where `continue` and `break` jump to, for each enclosing loop
+    loop_labels: Vec<(String, String)>,
+    /// the functions that may be called, by name
+    funcs: HashMap<String, &'a FuncDef>,
+    /// the declared type of each variable and parameter, by label
+    var_types: HashMap<String, TypeSpec>,
+    /// the subroutines of `read` and `write` the program calls
+    routines: HashSet<&'static str>,
+}
+
+/// Where a word is in memory: at `label`, moved by the offset in a register if any. An
+/// address computed at run time has the label `0`.
+struct Location {
+    label: String,
+    offset: Option<i32>,
+}
+
+impl Location {
+    fn operand(&self) -> String {
+        format!("{}(r{})", self.label, self.offset.unwrap_or(0))
+    }
+}
+
+/// The variables declared and the functions called in the body of a function
+#[derive(Default)]
+struct FuncScan<'ast> {
+    var_decls: Vec<&'ast VarDecl>,
+    callees: Vec<(&'ast str, Span)>,
+}
+
+impl<'ast> Visitor<'ast> for FuncScan<'ast> {
+    fn visit_var_decl(&mut self, var_decl: &'ast VarDecl) {
+        self.var_decls.push(var_decl);
+        walk_var_decl(self, var_decl);
+    }
+
+    fn visit_expr(&mut self, expr: &'ast Expr) {
+        if let ExprKind::Call { callee, .. } = &expr.kind {
+            if let ExprKind::Id(id) = &callee.kind {
+                self.callees.push((&id.name, expr.span));
+            }
+        }
+        walk_expr(self, expr);
+    }
+}
+
+impl<'a> CodeGenerator<'a> {
+    pub fn new(table_container: &'a HashMap<String, SymbolTable>) -> Self {
+        Self {
+            table_container,
+            register_pool: RegisterPool::new(),
+            code: String::new(),
+            data: String::new(),
+            label_count: 0,
+            scope: "global".to_string(),
+            loop_labels: vec![],
+            funcs: HashMap::new(),
+            var_types: HashMap::new(),
+            routines: HashSet::new(),
+        }
+    }
+
+    /// The generated code followed by the subroutines it calls and the memory reserved
+    /// for variables
+    pub fn finish(mut self) -> String {
+        if self.routines.contains("putint") {
+            self.emit_putint();
+        }
+        if self.routines.contains("getint") {
+            self.emit_getint();
+        }
+        format!("{}{}", self.code, self.data)
+    }
+
+    /// Append the moon code of the program, in the core language of `desugar`
+    pub fn translate(&mut self, program: &'a Program) -> Result<()> {
+        self.declare(program)?;
+        for item in program.items.iter() {
+            match item {
+                Item::Func(func_def) => self.translate_func_def(func_def)?,
+                Item::Var(var_decl) => self.translate_var_decl(var_decl)?,
+                // structs are reported where they are used
+                Item::Struct(_) | Item::Impl(_) => {}
+                Item::Const(_) => {}
+                Item::Error(span) => {
+                    return Err(CodeGenerationError::new(
+                        *span,
+                        "declaration broken by a syntax error",
+                    ))
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Record the functions and the types of the variables, and reject the functions
+    /// that cannot have memory of their own
+    fn declare(&mut self, program: &'a Program) -> Result<()> {
+        let mut callees = HashMap::new();
+        for item in program.items.iter() {
+            match item {
+                Item::Var(var_decl) => {
+                    let label = self.label_in("global", &var_decl.name.name);
+                    self.var_types.insert(label, var_decl.var_type.clone());
+                }
+                Item::Func(func_def) => {
+                    let func_name = &func_def.name.name;
+                    if self.funcs.insert(func_name.clone(), func_def).is_some() {
+                        return Err(CodeGenerationError::new(
+                            func_def.name.span,
+                            &format!("function {} is overloaded", func_name),
+                        ));
+                    }
+                    let scope = format!("global:{}", func_name);
+                    for param in func_def.params.iter() {
+                        let label = self.label_in(&scope, &param.name.name);
+                        self.var_types.insert(label, param.param_type.clone());
+                    }
+                    let mut scan = FuncScan::default();
+                    for stmt in func_def.body.iter() {
+                        scan.visit_stmt(stmt);
+                    }
+                    for var_decl in scan.var_decls {
+                        let label = self.label_in(&scope, &var_decl.name.name);
+                        self.var_types.insert(label, var_decl.var_type.clone());
+                    }
+                    callees.insert(func_name.as_str(), scan.callees);
+                }
+                _ => {}
+            }
+        }
+        // a function calling itself, directly or not, would overwrite its own memory
+        for func_name in callees.keys() {
+            let mut reached = HashSet::new();
+            let mut calls = callees[func_name].clone();
+            while let Some((callee, span)) = calls.pop() {
+                if callee == *func_name {
+                    return Err(CodeGenerationError::new(
+                        span,
+                        &format!("function {} is recursive", func_name),
+                    ));
+                }
+                if reached.insert(callee) {
+                    calls.extend(callees.get(callee).cloned().unwrap_or_default());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn translate_func_def(&mut self, func_def: &FuncDef) -> Result<()> {
+        let func_name = &func_def.name.name;
+        let outer_scope = std::mem::replace(&mut self.scope, format!("global:{}", func_name));
+        for param in func_def.params.iter() {
+            // an array is passed as its address
+            self.check_storable(&param.param_type, param.span)?;
+            let label = self.variable_label(&param.name.name, param.span)?;
+            self.reserve(&label, 4);
+        }
+        if func_name == "main" {
+            self.emit("", "entry");
+            self.translate_stmts(&func_def.body)?;
+            self.emit("", "hlt");
+        } else {
+            self.reserve(&format!("{}_link", func_name), 4);
+            self.reserve(&format!("{}_return", func_name), 4);
+            self.emit(func_name, &format!("sw {}_link(r0),r15", func_name));
+            self.translate_stmts(&func_def.body)?;
+            self.emit_return();
+        }
+        self.scope = outer_scope;
+        Ok(())
+    }
+
+    fn translate_var_decl(&mut self, var_decl: &VarDecl) -> Result<()> {
+        let name = &var_decl.name.name;
+        self.check_storable(&var_decl.var_type, var_decl.span)?;
+        let label = self.variable_label(name, var_decl.name.span)?;
+        let size = self.size_of(&var_decl.var_type);
+        let mut elements = vec![];
+        if let Some(initializer) = &var_decl.initializer {
+            self.initializer_elements(initializer, &mut elements);
+        }
+        if self.scope == "global" {
+            // globals are initialized in static data
+            let data = if elements.is_empty() {
+                format!("res {}", size)
+            } else {
+                let global_table = &self.table_container["global"];
+                let mut words = vec![];
+                for element in elements {
+                    words.push(match constant_folding::fold(element, global_table) {
+                        Ok(ConstantValue::Integer(i)) => i.to_string(),
+                        Ok(ConstantValue::Bool(b)) => (b as i32).to_string(),
+                        _ => return Err(Self::unsupported_float(element.span)),
+                    });
+                }
+                format!("dw {}", words.join(","))
+            };
+            self.data.push_str(&format!("{:<9} {}\n", label, data));
+            return Ok(());
+        }
+        // a variable shared by several loops is reserved once
+        self.reserve(&label, size);
+        for (index, element) in elements.into_iter().enumerate() {
+            let value = self.translate_expr(element)?;
+            if index == 0 {
+                self.emit("", &format!("sw {}(r0),r{}", label, value));
+            } else {
+                let offset = self.get_register(element.span)?;
+                self.emit("", &format!("addi r{},r0,{}", offset, index * 4));
+                self.emit("", &format!("sw {}(r{}),r{}", label, offset, value));
+                self.register_pool.give_back(offset);
+            }
+            self.register_pool.give_back(value);
+        }
+        Ok(())
+    }
+
+    fn translate_stmts(&mut self, stmts: &[Stmt]) -> Result<()> {
+        for stmt in stmts {
+            self.translate_stmt(stmt)?;
+        }
+        Ok(())
+    }
+
+    fn translate_stmt(&mut self, stmt: &Stmt) -> Result<()> {
+        match &stmt.kind {
+            StmtKind::VarDecl(var_decl) => self.translate_var_decl(var_decl)?,
+            StmtKind::Assign { target, value } => {
+                let value = self.translate_expr(value)?;
+                let location = self.location(target)?;
+                self.emit("", &format!("sw {},r{}", location.operand(), value));
+                self.give_back(location);
+                self.register_pool.give_back(value);
+            }
+            StmtKind::Expr(expr) => {
+                if let Some(value) = self.translate_call(expr, false)? {
+                    self.register_pool.give_back(value);
+                }
+            }
+            StmtKind::If {
+                condition,
+                then_branch,
+                else_branch,
+            } => {
+                // an else-if chain is an If in the else branch
+                let label_id = self.new_label_id();
+                let else_label = format!("else{}", label_id);
+                let end_label = format!("endif{}", label_id);
+                self.branch(condition, false, &else_label)?;
+                self.translate_stmts(&then_branch.stmts)?;
+                match else_branch {
+                    Some(else_branch) => {
+                        self.emit("", &format!("j {}", end_label));
+                        self.emit(&else_label, "nop");
+                        self.translate_stmt(else_branch)?;
+                        self.emit(&end_label, "nop");
+                    }
+                    None => self.emit(&else_label, "nop"),
+                }
+            }
+            StmtKind::While { condition, body } => {
+                let label_id = self.new_label_id();
+                let start_label = format!("while{}", label_id);
+                let end_label = format!("endwhile{}", label_id);
+                self.emit(&start_label, "nop");
+                self.branch(condition, false, &end_label)?;
+                self.loop_labels
+                    .push((start_label.clone(), end_label.clone()));
+                self.translate_stmts(&body.stmts)?;
+                self.loop_labels.pop();
+                self.emit("", &format!("j {}", start_label));
+                self.emit(&end_label, "nop");
+            }
+            StmtKind::For {
+                variable,
+                initial_value,
+                condition,
+                step,
+                body,
+            } => {
+                let label_id = self.new_label_id();
+                let start_label = format!("for{}", label_id);
+                let step_label = format!("forstep{}", label_id);
+                let end_label = format!("endfor{}", label_id);
+                self.translate_var_decl(variable)?;
+                let initial_value = self.translate_expr(initial_value)?;
+                let label = self.variable_label(&variable.name.name, variable.span)?;
+                self.emit("", &format!("sw {}(r0),r{}", label, initial_value));
+                self.register_pool.give_back(initial_value);
+                self.emit(&start_label, "nop");
+                self.branch(condition, false, &end_label)?;
+                self.loop_labels
+                    .push((step_label.clone(), end_label.clone()));
+                self.translate_stmts(&body.stmts)?;
+                self.loop_labels.pop();
+                self.emit(&step_label, "nop");
+                self.translate_stmt(step)?;
+                self.emit("", &format!("j {}", start_label));
+                self.emit(&end_label, "nop");
+            }
+            StmtKind::Break | StmtKind::Continue => {
+                let (continue_label, break_label) = match self.loop_labels.last() {
+                    Some(labels) => labels.clone(),
+                    None => {
+                        return Err(CodeGenerationError::new(
+                            stmt.span,
+                            "break or continue outside of a loop",
+                        ))
+                    }
+                };
+                let target = if stmt.kind == StmtKind::Break {
+                    break_label
+                } else {
+                    continue_label
+                };
+                self.emit("", &format!("j {}", target));
+            }
+            StmtKind::Read(target) => {
+                let location = self.location(target)?;
+                self.call_routine("getint", stmt.span)?;
+                let value = self.get_register(stmt.span)?;
+                self.emit("", &format!("lw r{},getint_value(r0)", value));
+                self.emit("", &format!("sw {},r{}", location.operand(), value));
+                self.register_pool.give_back(value);
+                self.give_back(location);
+            }
+            StmtKind::Write(expr) => {
+                let value = self.translate_expr(expr)?;
+                self.emit("", &format!("sw putint_value(r0),r{}", value));
+                self.register_pool.give_back(value);
+                self.call_routine("putint", stmt.span)?;
+            }
+            StmtKind::Return(expr) => {
+                let value = self.translate_expr(expr)?;
+                let func_name = self.scope.replace("global:", "");
+                self.emit("", &format!("sw {}_return(r0),r{}", func_name, value));
+                self.register_pool.give_back(value);
+                if func_name == "main" {
+                    self.emit("", "hlt");
+                } else {
+                    self.emit_return();
+                }
+            }
+            StmtKind::Block(block) => self.translate_stmts(&block.stmts)?,
+            StmtKind::Error => {
+                return Err(CodeGenerationError::new(
+                    stmt.span,
+                    "statement broken by a syntax error",
+                ))
+            }
+        }
+        Ok(())
+    }
+
+    /// Append the moon code of the expression. Return the register holding its value,
+    /// which the caller gives back.
+    fn translate_expr(&mut self, expr: &Expr) -> Result<i32> {
+        match &expr.kind {
+            ExprKind::IntLit(value) => match value.parse::<i32>() {
+                Ok(value) => self.load_constant(value, expr.span),
+                Err(_) => Err(CodeGenerationError::new(
+                    expr.span,
+                    "integer does not fit in a word",
+                )),
+            },
+            ExprKind::BoolLit(value) => self.load_constant(*value as i32, expr.span),
+            ExprKind::Id(id) => {
+                if let Some((_, entry)) = self.lookup(&id.name) {
+                    if let SymbolKind::Constant(value) = entry.kind {
+                        // constants are folded into the code
+                        return match value {
+                            ConstantValue::Integer(i) => self.load_constant(i, expr.span),
+                            ConstantValue::Bool(b) => self.load_constant(b as i32, expr.span),
+                            ConstantValue::Float(_) => Err(Self::unsupported_float(expr.span)),
+                        };
+                    }
+                }
+                self.load(expr)
+            }
+            ExprKind::Var { .. } => self.load(expr),
+            ExprKind::Binary {
+                op: BinaryOp::And | BinaryOp::Or,
+                ..
+            } => {
+                // 1 or 0, without evaluating the right operand when not needed
+                let label_id = self.new_label_id();
+                let false_label = format!("false{}", label_id);
+                let end_label = format!("endbool{}", label_id);
+                self.branch(expr, false, &false_label)?;
+                let register = self.get_register(expr.span)?;
+                self.emit("", &format!("addi r{},r0,1", register));
+                self.emit("", &format!("j {}", end_label));
+                self.emit(&false_label, &format!("addi r{},r0,0", register));
+                self.emit(&end_label, "nop");
+                Ok(register)
+            }
+            ExprKind::Binary { op, lhs, rhs } => {
+                let left = self.translate_expr(lhs)?;
+                let right = self.translate_expr(rhs)?;
+                let instruction = match op {
+                    BinaryOp::Eq => "ceq",
+                    BinaryOp::NotEq => "cne",
+                    BinaryOp::Lt => "clt",
+                    BinaryOp::Gt => "cgt",
+                    BinaryOp::Leq => "cle",
+                    BinaryOp::Geq => "cge",
+                    BinaryOp::Add => "add",
+                    BinaryOp::Sub => "sub",
+                    BinaryOp::Mul => "mul",
+                    BinaryOp::Div => "div",
+                    BinaryOp::And | BinaryOp::Or => unreachable!(),
+                };
+                self.emit(
+                    "",
+                    &format!("{} r{},r{},r{}", instruction, left, left, right),
+                );
+                self.register_pool.give_back(right);
+                Ok(left)
+            }
+            ExprKind::Unary {
+                op: UnaryOp::Not,
+                operand,
+            } => {
+                let register = self.translate_expr(operand)?;
+                self.emit("", &format!("ceq r{},r{},r0", register, register));
+                Ok(register)
+            }
+            ExprKind::Unary {
+                op: UnaryOp::Minus,
+                operand,
+            } => {
+                let register = self.translate_expr(operand)?;
+                self.emit("", &format!("sub r{},r0,r{}", register, register));
+                Ok(register)
+            }
+            ExprKind::Unary {
+                op: UnaryOp::Plus,
+                operand,
+            } => self.translate_expr(operand),
+            ExprKind::Call { .. } => match self.translate_call(expr, true)? {
+                Some(value) => Ok(value),
+                None => Err(CodeGenerationError::new(
+                    expr.span,
+                    "function returning void used as a value",
+                )),
+            },
+            ExprKind::FloatLit(_) => Err(Self::unsupported_float(expr.span)),
+            ExprKind::Member { .. } => Err(Self::unsupported_struct(expr.span)),
+            ExprKind::Error => Err(CodeGenerationError::new(
+                expr.span,
+                "expression broken by a syntax error",
+            )),
+        }
+    }
+
+    /// Call a function, with its arguments stored in its parameters. Return the register
+    /// holding its return value if `needs_value`.
+    fn translate_call(&mut self, call: &Expr, needs_value: bool) -> Result<Option<i32>> {
+        let (callee, args) = match &call.kind {
+            ExprKind::Call { callee, args } => (callee, args),
+            _ => {
+                return Err(CodeGenerationError::new(
+                    call.span,
+                    "expression evaluated for nothing",
+                ))
+            }
+        };
+        let func_def = match &callee.kind {
+            ExprKind::Id(id) => match self.funcs.get(&id.name) {
+                Some(func_def) => *func_def,
+                None => {
+                    return Err(CodeGenerationError::new(
+                        call.span,
+                        &format!("{} is not a function", id.name),
+                    ))
+                }
+            },
+            _ => return Err(Self::unsupported_struct(call.span)),
+        };
+        let func_name = func_def.name.name.clone();
+        // every argument is evaluated before any parameter is written, as an argument may
+        // call the same function
+        let mut values = vec![];
+        for (arg, param) in args.iter().zip(func_def.params.iter()) {
+            let value = if param.param_type.dimensions.is_empty() {
+                self.translate_expr(arg)?
+            } else {
+                self.address_of(arg)?
+            };
+            values.push(value);
+        }
+        for (value, param) in values.into_iter().zip(func_def.params.iter()) {
+            let label = self.label_in(&format!("global:{}", func_name), &param.name.name);
+            self.emit("", &format!("sw {}(r0),r{}", label, value));
+            self.register_pool.give_back(value);
+        }
+        self.call(&func_name)?;
+        if !needs_value || func_def.return_type.base == BaseType::Void {
+            return Ok(None);
+        }
+        let value = self.get_register(call.span)?;
+        self.emit("", &format!("lw r{},{}_return(r0)", value, func_name));
+        Ok(Some(value))
+    }
+
+    /// Jump to `label` if the condition evaluates to `when`. The right operand of `&`
+    /// and `|` is skipped once the left one decides the result.
+    fn branch(&mut self, condition: &Expr, when: bool, label: &str) -> Result<()> {
+        match &condition.kind {
+            ExprKind::Binary {
+                op: BinaryOp::And,
+                lhs,
+                rhs,
+            } if !when => {
+                self.branch(lhs, false, label)?;
+                self.branch(rhs, false, label)?;
+            }
+            ExprKind::Binary {
+                op: BinaryOp::Or,
+                lhs,
+                rhs,
+            } if when => {
+                self.branch(lhs, true, label)?;
+                self.branch(rhs, true, label)?;
+            }
+            ExprKind::Binary {
+                op: op @ (BinaryOp::And | BinaryOp::Or),
+                lhs,
+                rhs,
+            } => {
+                // the left operand alone may decide the other way
+                let skip_label = format!("skip{}", self.new_label_id());
+                self.branch(lhs, *op == BinaryOp::Or, &skip_label)?;
+                self.branch(rhs, when, label)?;
+                self.emit(&skip_label, "nop");
+            }
+            ExprKind::Unary {
+                op: UnaryOp::Not,
+                operand,
+            } => {
+                self.branch(operand, !when, label)?;
+            }
+            _ => {
+                let register = self.translate_expr(condition)?;
+                let instruction = if when { "bnz" } else { "bz" };
+                self.emit("", &format!("{} r{},{}", instruction, register, label));
+                self.register_pool.give_back(register);
+            }
+        }
+        Ok(())
+    }
+
+    /// Load the word of a variable or of an element of an array
+    fn load(&mut self, expr: &Expr) -> Result<i32> {
+        let location = self.location(expr)?;
+        // the offset register is free once the address is used
+        let register = match location.offset {
+            Some(offset) => offset,
+            None => self.get_register(expr.span)?,
+        };
+        self.emit("", &format!("lw r{},{}", register, location.operand()));
+        Ok(register)
+    }
+
+    /// Where the variable or the element of an array is. In the core language `a[i][j]`
+    /// is `(a[i])[j]`, so the indices are gathered from the nested `Var` nodes.
+    fn location(&mut self, expr: &Expr) -> Result<Location> {
+        let mut base = expr;
+        let mut indices = vec![];
+        while let ExprKind::Var {
+            base: inner,
+            indices: inner_indices,
+        } = &base.kind
+        {
+            indices.splice(0..0, inner_indices.iter());
+            base = inner;
+        }
+        let id = match &base.kind {
+            ExprKind::Id(id) => id,
+            ExprKind::Member { .. } => return Err(Self::unsupported_struct(base.span)),
+            _ => {
+                return Err(CodeGenerationError::new(
+                    base.span,
+                    "expression is not a variable",
+                ))
+            }
+        };
+        let label = self.variable_label(&id.name, id.span)?;
+        let var_type = self.var_types.get(&label).cloned().ok_or_else(|| {
+            CodeGenerationError::new(id.span, &format!("{} is not a variable", id.name))
+        })?;
+        if indices.is_empty() {
+            return Ok(Location {
+                label,
+                offset: None,
+            });
+        }
+        if indices.len() != var_type.dimensions.len() {
+            return Err(CodeGenerationError::new(
+                expr.span,
+                "array is indexed in some of its dimensions only",
+            ));
+        }
+        // row-major: each index steps over the elements of the dimensions after it
+        let mut offset = None;
+        for (position, index) in indices.into_iter().enumerate() {
+            let mut stride = 4;
+            for size in var_type.dimensions[position + 1..].iter() {
+                match size {
+                    Some(size) => stride *= *size as i64,
+                    None => {
+                        return Err(CodeGenerationError::new(
+                            id.span,
+                            "array of unknown size in a dimension other than the first",
+                        ))
+                    }
+                }
+            }
+            let register = self.translate_expr(index)?;
+            self.emit("", &format!("muli r{},r{},{}", register, register, stride));
+            match offset {
+                Some(offset) => {
+                    self.emit("", &format!("add r{},r{},r{}", offset, offset, register));
+                    self.register_pool.give_back(register);
+                }
+                None => offset = Some(register),
+            }
+        }
+        if self.is_param(&id.name) {
+            // the parameter holds the address of the array
+            let offset = offset.unwrap();
+            let address = self.get_register(id.span)?;
+            self.emit("", &format!("lw r{},{}(r0)", address, label));
+            self.emit("", &format!("add r{},r{},r{}", offset, offset, address));
+            self.register_pool.give_back(address);
+            return Ok(Location {
+                label: "0".to_string(),
+                offset: Some(offset),
+            });
+        }
+        Ok(Location { label, offset })
+    }
+
+    /// Load the address of an array passed as an argument
+    fn address_of(&mut self, expr: &Expr) -> Result<i32> {
+        let id = match &expr.kind {
+            ExprKind::Id(id) => id,
+            ExprKind::Member { .. } => return Err(Self::unsupported_struct(expr.span)),
+            _ => {
+                return Err(CodeGenerationError::new(
+                    expr.span,
+                    "array argument is not a variable",
+                ))
+            }
+        };
+        let label = self.variable_label(&id.name, id.span)?;
+        let register = self.get_register(expr.span)?;
+        if self.is_param(&id.name) {
+            self.emit("", &format!("lw r{},{}(r0)", register, label));
+        } else {
+            self.emit("", &format!("addi r{},r0,{}", register, label));
+        }
+        Ok(register)
+    }
+
+    fn give_back(&mut self, location: Location) {
+        if let Some(offset) = location.offset {
+            self.register_pool.give_back(offset);
+        }
+    }
+
+    /// Load a constant. `addi` takes 16 bits, larger values are kept in memory.
+    fn load_constant(&mut self, value: i32, span: Span) -> Result<i32> {
+        let register = self.get_register(span)?;
+        if i16::try_from(value).is_ok() {
+            self.emit("", &format!("addi r{},r0,{}", register, value));
+        } else {
+            let label = format!("const{}", self.new_label_id());
+            self.data.push_str(&format!("{:<9} dw {}\n", label, value));
+            self.emit("", &format!("lw r{},{}(r0)", register, label));
+        }
+        Ok(register)
+    }
+
+    /// Jump to a function or a subroutine, saving the registers in use around the call
+    fn call(&mut self, label: &str) -> Result<()> {
+        let saved = self.register_pool.in_use();
+        let save_id = self.new_label_id();
+        for register in saved.iter() {
+            let save_label = format!("save{}r{}", save_id, register);
+            self.reserve(&save_label, 4);
+            self.emit("", &format!("sw {}(r0),r{}", save_label, register));
+        }
+        self.emit("", &format!("jl r15,{}", label));
+        for register in saved.iter() {
+            self.emit(
+                "",
+                &format!("lw r{},save{}r{}(r0)", register, save_id, register),
+            );
+        }
+        Ok(())
+    }
+
+    fn call_routine(&mut self, routine: &'static str, _span: Span) -> Result<()> {
+        self.routines.insert(routine);
+        self.call(routine)
+    }
+
+    /// Go back to the caller of the function being translated
+    fn emit_return(&mut self) {
+        let func_name = self.scope.replace("global:", "");
+        self.emit("", &format!("lw r15,{}_link(r0)", func_name));
+        self.emit("", "jr r15");
+    }
+
+    /// Print the integer at `putint_value` in decimal, then a new line
+    fn emit_putint(&mut self) {
+        self.reserve("putint_value", 4);
+        for (label, instruction) in [
+            ("putint", "lw r1,putint_value(r0)"),
+            ("", "cge r2,r1,r0"),
+            ("", "bnz r2,putint1"),
+            ("", "addi r3,r0,45"),
+            ("", "putc r3"),
+            ("", "sub r1,r0,r1"),
+            // the largest power of ten not above the value, which is 10^9 at most
+            ("putint1", "addi r2,r0,1"),
+            ("putint2", "lw r4,putint_max(r0)"),
+            ("", "ceq r4,r2,r4"),
+            ("", "bnz r4,putint3"),
+            ("", "muli r3,r2,10"),
+            ("", "cgt r4,r3,r1"),
+            ("", "bnz r4,putint3"),
+            ("", "addi r2,r3,0"),
+            ("", "j putint2"),
+            // a digit for each power of ten down to 1
+            ("putint3", "div r3,r1,r2"),
+            ("", "addi r4,r3,48"),
+            ("", "putc r4"),
+            ("", "mul r4,r3,r2"),
+            ("", "sub r1,r1,r4"),
+            ("", "divi r2,r2,10"),
+            ("", "bnz r2,putint3"),
+            ("", "addi r3,r0,10"),
+            ("", "putc r3"),
+            ("", "jr r15"),
+        ] {
+            self.emit(label, instruction);
+        }
+        self.data
+            .push_str(&format!("{:<9} dw {}\n", "putint_max", 1_000_000_000));
+    }
+
+    /// Read an integer in decimal up to the end of its line into `getint_value`
+    fn emit_getint(&mut self) {
+        self.reserve("getint_value", 4);
+        for (label, instruction) in [
+            ("getint", "addi r1,r0,0"),
+            ("", "addi r2,r0,0"),
+            ("", "getc r3"),
+            ("", "cnei r4,r3,45"),
+            ("", "bnz r4,getint2"),
+            ("", "addi r2,r0,1"),
+            ("getint1", "getc r3"),
+            ("getint2", "clti r4,r3,48"),
+            ("", "bnz r4,getint3"),
+            ("", "cgti r4,r3,57"),
+            ("", "bnz r4,getint3"),
+            ("", "muli r1,r1,10"),
+            ("", "add r1,r1,r3"),
+            ("", "subi r1,r1,48"),
+            ("", "j getint1"),
+            ("getint3", "bz r2,getint4"),
+            ("", "sub r1,r0,r1"),
+            ("getint4", "sw getint_value(r0),r1"),
+            ("", "jr r15"),
+        ] {
+            self.emit(label, instruction);
+        }
+    }
+
+    /// The expressions of an initializer, with nested lists flattened in row-major order
+    fn initializer_elements<'b>(&self, initializer: &'b Initializer, elements: &mut Vec<&'b Expr>) {
+        match initializer {
+            Initializer::Expr(expr) => elements.push(expr),
+            Initializer::List(list, _) => {
+                for element in list {
+                    self.initializer_elements(element, elements);
+                }
+            }
+        }
+    }
+
+    fn new_label_id(&mut self) -> usize {
+        self.label_count += 1;
+        self.label_count
+    }
+
+    fn get_register(&mut self, span: Span) -> Result<i32> {
+        self.register_pool.get_register().ok_or_else(|| {
+            CodeGenerationError::new(span, "expression needs more registers than there are")
+        })
+    }
+
+    /// Reserve a word or more of memory under the label, unless it already is
+    fn reserve(&mut self, label: &str, size: usize) {
+        let is_reserved = self
+            .data
+            .lines()
+            .any(|line| line.split_whitespace().next() == Some(label));
+        if !is_reserved {
+            self.data.push_str(&format!("{:<9} res {}\n", label, size));
+        }
+    }
+
+    /// The entry of a name visible from the function being translated, with the scope
+    /// declaring it
+    fn lookup(&self, name: &str) -> Option<(&str, SymbolTableEntry)> {
+        [self.scope.as_str(), "global"]
+            .into_iter()
+            .find_map(|scope| {
+                self.table_container
+                    .get(scope)
+                    .and_then(|table| table.get_all_entries_by_name(name).into_iter().next())
+                    .map(|entry| (scope, entry))
+            })
+    }
+
+    fn is_param(&self, name: &str) -> bool {
+        matches!(
+            self.lookup(name),
+            Some((_, entry)) if entry.kind == SymbolKind::Parameter
+        )
+    }
+
+    /// The memory label of a variable of the function being translated, or of a global one
+    fn variable_label(&self, name: &str, span: Span) -> Result<String> {
+        match self.lookup(name) {
+            Some((scope, _)) => Ok(self.label_in(scope, name)),
+            None => Err(CodeGenerationError::new(
+                span,
+                &format!("{} is not declared", name),
+            )),
+        }
+    }
+
+    fn label_in(&self, scope: &str, name: &str) -> String {
+        format!(
+            "{}_{}",
+            scope.replace("global:", "").replace(':', "_"),
+            name
+        )
+    }
+
+    /// Report the types whose values do not fit in words of integers
+    fn check_storable(&self, var_type: &TypeSpec, span: Span) -> Result<()> {
+        match var_type.base {
+            BaseType::Float => Err(Self::unsupported_float(span)),
+            BaseType::Class(_) => Err(Self::unsupported_struct(span)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Bytes taken by a variable of the type, such as `integer[2][3]`
+    fn size_of(&self, var_type: &TypeSpec) -> usize {
+        var_type
+            .dimensions
+            .iter()
+            .map(|size| size.unwrap_or(1))
+            .product::<usize>()
+            * 4
+    }
+
+    fn unsupported_float(span: Span) -> CodeGenerationError {
+        CodeGenerationError::new(span, "floats are not supported by the moon target")
+    }
+
+    fn unsupported_struct(span: Span) -> CodeGenerationError {
+        CodeGenerationError::new(span, "structs are not supported by the moon target")
+    }
+
+    /// Append an instruction, with its label if any
+    fn emit(&mut self, label: &str, instruction: &str) {
+        self.code
+            .push_str(&format!("{:<9} {}\n", label, instruction));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::code_generation::moon;
+    use crate::semantic::ast::generate_symbol_tables;
+    use crate::semantic::desugar::desugar;
+    use crate::semantic::typed_ast;
+    use crate::syntactic::parser::tests::{sample_tokens, tokens_of};
+    use crate::syntactic::parser::Parser;
+    use std::path::Path;
+
+    /// Desugar, check and translate a program free of errors
+    fn compile(source: &str) -> Result<String> {
+        let (ast, spans) = match Parser::new().parse(tokens_of(source)) {
+            Ok((_, ast, spans, errors)) if errors.is_empty() => (ast, spans),
+            _ => panic!("syntax errors in {}", source),
+        };
+        let program = desugar(&typed_ast::lower(&ast, &spans));
+        let (tables, semantic_errors) = generate_symbol_tables(&program);
+        assert!(
+            !semantic_errors.iter().any(|e| e.is_error()),
+            "semantic errors in {}: {:?}",
+            source,
+            semantic_errors
+        );
+        generate_moon_code(&program, &tables)
+    }
+
+    /// What the program prints
+    fn run(source: &str, input: &str) -> String {
+        let code = compile(source).unwrap_or_else(|e| panic!("{}", e));
+        moon::run(&code, input).unwrap_or_else(|e| panic!("{}\n{}", e, code))
+    }
+
+    #[test]
+    fn runs_the_samples_without_errors() {
+        let mut samples = vec![];
+        sample_tokens(Path::new("resource"), &mut samples);
+        let mut outputs = HashMap::new();
+        for (path, tokens) in samples {
+            let (ast, spans) = match Parser::new().parse(tokens) {
+                Ok((_, ast, spans, errors)) if errors.is_empty() => (ast, spans),
+                _ => continue,
+            };
+            let program = desugar(&typed_ast::lower(&ast, &spans));
+            let (tables, semantic_errors) = generate_symbol_tables(&program);
+            // constructs of the moon target are errors, not panics
+            let code = generate_moon_code(&program, &tables);
+            if !semantic_errors.iter().any(|e| e.is_error()) {
+                let code = code.unwrap_or_else(|e| panic!("{}: {}", path, e));
+                let output = moon::run(&code, "").unwrap_or_else(|e| panic!("{}: {}", path, e));
+                outputs.insert(path.replace('\\', "/"), output);
+            }
+        }
+        // the sample does not reset `j`, so it makes a single pass
+        assert_eq!(
+            outputs["resource/semantics/bubblesort.src"],
+            "64\n34\n25\n12\n22\n11\n90\n34\n25\n12\n22\n11\n64\n90\n"
+        );
+    }
+
+    #[test]
+    fn translates_calls_read_write_and_return() {
+        let source = "
+            func add(a: integer, b: integer) -> integer { return(a + b); }
+            func twice(x: integer) -> integer { return(add(x, x)); }
+            func main() -> void {
+              let x: integer;
+              read(x);
+              write(add(twice(x), add(1, 2)) * 10);
+              write(-x);
+              write(70000 + 0);
+              write(2147483647);
+            }";
+        assert_eq!(run(source, "-3\n"), "-30\n3\n70000\n2147483647\n");
+    }
+
+    #[test]
+    fn indexes_arrays_in_every_dimension() {
+        let source = "
+            func set(a: integer[][3], i: integer, v: integer) -> void { a[i][i + 1] = v; }
+            func main() -> void {
+              let m: integer[2][3];
+              let i: integer;
+              i = 0;
+              while (i < 2) { m[i][0] = i; i = i + 1; };
+              set(m, 1, 9);
+              write(m[1][2] + m[1][0]);
+              write(m[0][0]);
+            }";
+        assert_eq!(run(source, ""), "10\n0\n");
+    }
+
+    #[test]
+    fn takes_one_branch_of_else_if_chains() {
+        let source = "
+            func sign(x: integer) -> integer {
+              if (x < 0) then return(-1); else if (x == 0) then return(0); else return(1); ;
+              return(2);
+            }
+            func main() -> void {
+              write(sign(-5));
+              write(sign(0));
+              write(sign(5));
+              if (sign(3) == 1) then { write(7); };
+              if (sign(3) == 0) then { write(8); };
+            }";
+        assert_eq!(run(source, ""), "-1\n0\n1\n7\n");
+    }
+
+    #[test]
+    fn breaks_and_continues_for_loops() {
+        let source = "
+            func main() -> void {
+              let sum: integer = 0;
+              for (let i: integer = 0; i < 10; i = i + 1) {
+                if (i == 7) then { break; };
+                if (i == 2 | i == 4) then { continue; };
+                for (let j: integer = 0; j < 100; j = j + 1) {
+                  if (j == 1) then break;;
+                  sum = sum + i;
+                };
+              };
+              write(sum);
+              while (sum > 0) { sum = sum - 4; if (sum < 10) then break;; };
+              write(sum);
+            }";
+        // 0 + 1 + 3 + 5 + 6
+        assert_eq!(run(source, ""), "15\n7\n");
+    }
+
+    #[test]
+    fn skips_the_right_operand_of_decided_booleans() {
+        // `seen` prints its argument, so the output shows which operands are evaluated
+        let source = "
+            func seen(x: integer) -> bool { write(x); return(x > 0); }
+            func main() -> void {
+              let b: bool;
+              if (seen(0) & seen(1)) then write(10); else write(20); ;
+              if (seen(2) | seen(3)) then write(30); ;
+              if (!(seen(0) | seen(4))) then write(40); ;
+              b = seen(5) & !seen(0);
+              write(b);
+              b = seen(0) | false;
+              write(b);
+            }";
+        assert_eq!(run(source, ""), "0\n20\n2\n30\n0\n4\n5\n0\n1\n0\n0\n");
+    }
+
+    #[test]
+    fn keeps_globals_in_static_data() {
+        let source = "
+            const LIMIT: integer = 40000 * 2;
+            const STEP: integer = 3;
+            let total: integer = LIMIT + 1;
+            let counter: integer;
+            func count() -> void { counter = counter + STEP; total = total - 1; }
+            func main() -> void { count(); count(); write(counter); write(total); write(LIMIT); }";
+        let code = compile(source).unwrap();
+        assert!(code.contains("global_total dw 80001"), "{}", code);
+        assert!(code.contains("global_counter res 4"), "{}", code);
+        assert_eq!(run(source, ""), "6\n79999\n80000\n");
+    }
+
+    #[test]
+    fn initializes_variables_and_arrays() {
+        let source = "
+            let grid: integer[2][2] = {{1, 2}, {3, 4}};
+            func main() -> void {
+              let i: integer = 1;
+              let row: integer[3] = {i, i + 1, grid[1][1]};
+              let flag: bool = row[2] == 4;
+              let j = row[0] + row[1];
+              write(row[0]);
+              write(row[1]);
+              write(row[2]);
+              write(flag);
+              write(j * grid[0][1]);
+            }";
+        assert_eq!(run(source, ""), "1\n2\n4\n1\n6\n");
+    }
+
+    #[test]
+    fn reports_what_the_moon_target_cannot_run() {
+        let cases = [
+            (
+                "func main() -> void { let x: float; x = 1.5; write(x); }",
+                "floats are not supported by the moon target",
+            ),
+            (
+                "struct P { public let x: integer; }; func main() -> void { let p: P; p.x = 1; }",
+                "structs are not supported by the moon target",
+            ),
+            (
+                "func f(n: integer) -> integer { return(f(n)); } func main() -> void { write(f(1)); }",
+                "function f is recursive",
+            ),
+        ];
+        for (source, message) in cases {
+            assert_eq!(compile(source).unwrap_err().message, message, "{}", source);
+        }
     }
 }
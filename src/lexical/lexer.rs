@@ -17,6 +17,12 @@ pub struct Lexer {
     possibly_exiting_block: bool,  // encounter a '*', waiting for '/'
 }
 
+impl Default for Lexer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Lexer {
     pub fn new() -> Lexer {
         Lexer {
@@ -47,32 +53,26 @@ impl Lexer {
                 if self.possibly_entering_block {
                     // we just consumed a '/'
                     self.possibly_entering_block = false;
-                    match c {
-                        '*' => {
-                            // enter a block
-                            self.block_depth += 1
-                        }
-                        _ => {}
+                    if c == '*' {
+                        // enter a block
+                        self.block_depth += 1
                     }
                 } else if self.possibly_exiting_block {
                     self.possibly_exiting_block = false;
-                    match c {
-                        '/' => {
-                            // exit a block
-                            self.block_depth -= 1;
-                            if self.block_depth == 0 {
-                                // going out of block comment
-                                let token = Token {
-                                    token_type: TokenType::ValidTokenType(ValidTokenType::BlockCmt),
-                                    lexeme: self.buffer.clone(),
-                                    location: self.start_loc,
-                                };
-                                self.buffer.clear();
-                                self.state_machine = StateMachine::new();
-                                self.handle_finalized_token(token);
-                            }
+                    if c == '/' {
+                        // exit a block
+                        self.block_depth -= 1;
+                        if self.block_depth == 0 {
+                            // going out of block comment
+                            let token = Token {
+                                token_type: TokenType::ValidTokenType(ValidTokenType::BlockCmt),
+                                lexeme: self.buffer.clone(),
+                                location: self.start_loc,
+                            };
+                            self.buffer.clear();
+                            self.state_machine = StateMachine::new();
+                            self.handle_finalized_token(token);
                         }
-                        _ => {}
                     }
                 } else {
                     match c {
@@ -166,13 +166,15 @@ impl Lexer {
         match consumed_result {
             Ok(_output) => {
                 // transition success
-                self.buffer.push(input.clone());
+                self.buffer.push(*input);
             }
             Err(_e) => {
                 // if transition error happens,
                 // 1. finalize the last token
                 let token_result = self.finalize_token(Some(input));
                 let some_error = self.handle_finalized_token(token_result);
+                // the current char starts the next token
+                self.start_loc = (self.current_loc.0, self.current_loc.1 - 1);
                 // 2. if it is not the first character causing the error, consume the current char
                 match some_error {
                     Some(e) if e.error_type == InvalidTokenType::InvalidChar => {}
@@ -195,7 +197,7 @@ impl Lexer {
             self.state_machine = StateMachine::new();
             return token;
         }
-        match LexerStateMachineImpl::state_to_token_type(&self.state_machine.state()) {
+        match LexerStateMachineImpl::state_to_token_type(self.state_machine.state()) {
             TokenType::ValidTokenType(valid_token_type) => {
                 let result = Token {
                     token_type: TokenType::ValidTokenType(valid_token_type),
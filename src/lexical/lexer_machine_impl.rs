@@ -78,19 +78,23 @@ pub enum State {
     KwImp,
     KwImpl,
 
-    // then
+    // then, true
     KwT,
     KwTh,
     KwThe,
     KwThen,
 
+    KwTr,
+    KwTru,
+    KwTrue,
+
     // else
     KwE,
     KwEl,
     KwEls,
     KwElse,
 
-    // float, func
+    // float, func, for, false
     KwF,
     KwFl,
     KwFlo,
@@ -101,6 +105,14 @@ pub enum State {
     KwFun,
     KwFunc,
 
+    KwFo,
+    KwFor,
+
+    KwFa,
+    KwFal,
+    KwFals,
+    KwFalse,
+
     // void, var
     KwV,
     KwVo,
@@ -162,6 +174,30 @@ pub enum State {
     KwL,
     KwLe,
     KwLet,
+
+    // break, bool
+    KwB,
+    KwBr,
+    KwBre,
+    KwBrea,
+    KwBreak,
+
+    KwBo,
+    KwBoo,
+    KwBool,
+
+    // continue, const
+    KwC,
+    KwCo,
+    KwCon,
+    KwCont,
+    KwConti,
+    KwContin,
+    KwContinu,
+    KwContinue,
+
+    KwCons,
+    KwConst,
 }
 pub struct LexerStateMachineImpl {}
 
@@ -219,6 +255,13 @@ impl LexerStateMachineImpl {
             State::KwInherits => Some(ValidTokenType::KwInherits),
             State::KwLet => Some(ValidTokenType::KwLet),
             State::KwImpl => Some(ValidTokenType::KwImpl),
+            State::KwFor => Some(ValidTokenType::KwFor),
+            State::KwBreak => Some(ValidTokenType::KwBreak),
+            State::KwContinue => Some(ValidTokenType::KwContinue),
+            State::KwBool => Some(ValidTokenType::KwBool),
+            State::KwTrue => Some(ValidTokenType::KwTrue),
+            State::KwFalse => Some(ValidTokenType::KwFalse),
+            State::KwConst => Some(ValidTokenType::KwConst),
 
             // atomic elements
             State::Id2 => Some(ValidTokenType::Id),
@@ -247,11 +290,11 @@ impl LexerStateMachineImpl {
             _ => None,
         };
 
-        return if invalid_token_type.is_some() {
-            TokenType::InvalidTokenType(invalid_token_type.expect("Invalid token type not found!"))
+        if let Some(invalid_token_type) = invalid_token_type {
+            TokenType::InvalidTokenType(invalid_token_type)
         } else {
             TokenType::ValidTokenType(valid_token_type.expect("Valid token type not exist!"))
-        };
+        }
     }
 }
 
@@ -288,19 +331,23 @@ impl StateMachineImpl for LexerStateMachineImpl {
             (State::KwIm, 'p') => Some(State::KwImp),
             (State::KwImp, 'l') => Some(State::KwImpl),
 
-            // then
+            // then, true
             (State::Start, 't') => Some(State::KwT),
             (State::KwT, 'h') => Some(State::KwTh),
             (State::KwTh, 'e') => Some(State::KwThe),
             (State::KwThe, 'n') => Some(State::KwThen),
 
+            (State::KwT, 'r') => Some(State::KwTr),
+            (State::KwTr, 'u') => Some(State::KwTru),
+            (State::KwTru, 'e') => Some(State::KwTrue),
+
             // else
             (State::Start, 'e') => Some(State::KwE),
             (State::KwE, 'l') => Some(State::KwEl),
             (State::KwEl, 's') => Some(State::KwEls),
             (State::KwEls, 'e') => Some(State::KwElse),
 
-            // float, func
+            // float, func, for, false
             (State::Start, 'f') => Some(State::KwF),
             (State::KwF, 'l') => Some(State::KwFl),
             (State::KwFl, 'o') => Some(State::KwFlo),
@@ -311,6 +358,14 @@ impl StateMachineImpl for LexerStateMachineImpl {
             (State::KwFu, 'n') => Some(State::KwFun),
             (State::KwFun, 'c') => Some(State::KwFunc),
 
+            (State::KwF, 'o') => Some(State::KwFo),
+            (State::KwFo, 'r') => Some(State::KwFor),
+
+            (State::KwF, 'a') => Some(State::KwFa),
+            (State::KwFa, 'l') => Some(State::KwFal),
+            (State::KwFal, 's') => Some(State::KwFals),
+            (State::KwFals, 'e') => Some(State::KwFalse),
+
             // void, var
             (State::Start, 'v') => Some(State::KwV),
             (State::KwV, 'o') => Some(State::KwVo),
@@ -375,6 +430,30 @@ impl StateMachineImpl for LexerStateMachineImpl {
             (State::KwL, 'e') => Some(State::KwLe),
             (State::KwLe, 't') => Some(State::KwLet),
 
+            // break, bool
+            (State::Start, 'b') => Some(State::KwB),
+            (State::KwB, 'r') => Some(State::KwBr),
+            (State::KwBr, 'e') => Some(State::KwBre),
+            (State::KwBre, 'a') => Some(State::KwBrea),
+            (State::KwBrea, 'k') => Some(State::KwBreak),
+
+            (State::KwB, 'o') => Some(State::KwBo),
+            (State::KwBo, 'o') => Some(State::KwBoo),
+            (State::KwBoo, 'l') => Some(State::KwBool),
+
+            // continue, const
+            (State::Start, 'c') => Some(State::KwC),
+            (State::KwC, 'o') => Some(State::KwCo),
+            (State::KwCo, 'n') => Some(State::KwCon),
+            (State::KwCon, 't') => Some(State::KwCont),
+            (State::KwCont, 'i') => Some(State::KwConti),
+            (State::KwConti, 'n') => Some(State::KwContin),
+            (State::KwContin, 'u') => Some(State::KwContinu),
+            (State::KwContinu, 'e') => Some(State::KwContinue),
+
+            (State::KwCon, 's') => Some(State::KwCons),
+            (State::KwCons, 't') => Some(State::KwConst),
+
             // keywords to id
             (
                 State::KwI
@@ -398,6 +477,9 @@ impl StateMachineImpl for LexerStateMachineImpl {
                 | State::KwTh
                 | State::KwThe
                 | State::KwThen
+                | State::KwTr
+                | State::KwTru
+                | State::KwTrue
                 | State::KwE
                 | State::KwEl
                 | State::KwEls
@@ -410,6 +492,12 @@ impl StateMachineImpl for LexerStateMachineImpl {
                 | State::KwFu
                 | State::KwFun
                 | State::KwFunc
+                | State::KwFo
+                | State::KwFor
+                | State::KwFa
+                | State::KwFal
+                | State::KwFals
+                | State::KwFalse
                 | State::KwV
                 | State::KwVo
                 | State::KwVoi
@@ -456,7 +544,25 @@ impl StateMachineImpl for LexerStateMachineImpl {
                 | State::KwReturn
                 | State::KwL
                 | State::KwLe
-                | State::KwLet,
+                | State::KwLet
+                | State::KwB
+                | State::KwBr
+                | State::KwBre
+                | State::KwBrea
+                | State::KwBreak
+                | State::KwBo
+                | State::KwBoo
+                | State::KwBool
+                | State::KwC
+                | State::KwCo
+                | State::KwCon
+                | State::KwCont
+                | State::KwConti
+                | State::KwContin
+                | State::KwContinu
+                | State::KwContinue
+                | State::KwCons
+                | State::KwConst,
                 'A'..='Z' | 'a'..='z' | '0'..='9' | '_',
             ) => Some(State::Id2),
 
@@ -1,4 +1,5 @@
 pub mod lexer;
 pub mod lexer_machine_impl;
 pub mod lexical_error;
+pub mod span;
 pub mod token;
@@ -0,0 +1,37 @@
+use std::fmt::{Display, Formatter};
+
+/// A region of the source code, from `start` to `end` (exclusive), as (line, column)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Span {
+    pub start: (u32, u32),
+    pub end: (u32, u32),
+}
+
+impl Span {
+    pub fn new(start: (u32, u32), end: (u32, u32)) -> Self {
+        Self { start, end }
+    }
+
+    /// The smallest span covering both spans
+    pub fn merge(&self, other: &Span) -> Span {
+        Span {
+            start: self.start.min(other.start),
+            end: self.end.max(other.end),
+        }
+    }
+
+    /// Whether the (line, column) position is in the span
+    pub fn contains(&self, position: (u32, u32)) -> bool {
+        self.start <= position && position < self.end
+    }
+}
+
+impl Display for Span {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}:{}-{}:{}",
+            self.start.0, self.start.1, self.end.0, self.end.1
+        )
+    }
+}
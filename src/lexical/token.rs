@@ -1,3 +1,4 @@
+use crate::lexical::span::Span;
 use std::fmt;
 use std::fmt::{Debug, Display, Formatter};
 
@@ -7,7 +8,7 @@ pub enum TokenType {
     InvalidTokenType(InvalidTokenType),
 }
 
-#[derive(Debug, Clone, Hash, PartialEq, Eq, Copy)]
+#[derive(Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord, Copy)]
 pub enum ValidTokenType {
     Id,
     Integer,
@@ -64,6 +65,83 @@ pub enum ValidTokenType {
     KwInherits,
     KwLet,
     KwImpl,
+    KwFor,
+    KwBreak,
+    KwContinue,
+    KwBool,
+    KwTrue,
+    KwFalse,
+    KwConst,
+
+    // end of input sentinel appended by the parser
+    Eof,
+}
+
+impl ValidTokenType {
+    /// How the token is written in the source code
+    pub fn spelling(&self) -> &'static str {
+        match self {
+            ValidTokenType::Id => "identifier",
+            ValidTokenType::Integer => "integer literal",
+            ValidTokenType::Float => "float literal",
+            ValidTokenType::Str => "string literal",
+            ValidTokenType::InlineCmt => "inline comment",
+            ValidTokenType::BlockCmt => "block comment",
+            ValidTokenType::Eq => "==",
+            ValidTokenType::NotEq => "<>",
+            ValidTokenType::Lt => "<",
+            ValidTokenType::Gt => ">",
+            ValidTokenType::Leq => "<=",
+            ValidTokenType::Geq => ">=",
+            ValidTokenType::Plus => "+",
+            ValidTokenType::Minus => "-",
+            ValidTokenType::Mult => "*",
+            ValidTokenType::Div => "/",
+            ValidTokenType::Assign => "=",
+            ValidTokenType::Or => "|",
+            ValidTokenType::And => "&",
+            ValidTokenType::Not => "!",
+            ValidTokenType::OpenPar => "(",
+            ValidTokenType::ClosePar => ")",
+            ValidTokenType::OpenCuBr => "{",
+            ValidTokenType::CloseCuBr => "}",
+            ValidTokenType::OpenSqBr => "[",
+            ValidTokenType::CloseSqBr => "]",
+            ValidTokenType::Semi => ";",
+            ValidTokenType::Comma => ",",
+            ValidTokenType::Dot => ".",
+            ValidTokenType::Colon => ":",
+            ValidTokenType::ColonColon => "::",
+            ValidTokenType::Arrow => "->",
+            ValidTokenType::KwIf => "if",
+            ValidTokenType::KwThen => "then",
+            ValidTokenType::KwElse => "else",
+            ValidTokenType::KwInteger => "integer",
+            ValidTokenType::KwFloat => "float",
+            ValidTokenType::KwVoid => "void",
+            ValidTokenType::KwPublic => "public",
+            ValidTokenType::KwPrivate => "private",
+            ValidTokenType::KwFunc => "func",
+            ValidTokenType::KwVar => "var",
+            ValidTokenType::KwStruct => "struct",
+            ValidTokenType::KwWhile => "while",
+            ValidTokenType::KwRead => "read",
+            ValidTokenType::KwWrite => "write",
+            ValidTokenType::KwReturn => "return",
+            ValidTokenType::KwSelf => "self",
+            ValidTokenType::KwInherits => "inherits",
+            ValidTokenType::KwLet => "let",
+            ValidTokenType::KwImpl => "impl",
+            ValidTokenType::KwFor => "for",
+            ValidTokenType::KwBreak => "break",
+            ValidTokenType::KwContinue => "continue",
+            ValidTokenType::KwBool => "bool",
+            ValidTokenType::KwTrue => "true",
+            ValidTokenType::KwFalse => "false",
+            ValidTokenType::KwConst => "const",
+            ValidTokenType::Eof => "end of file",
+        }
+    }
 }
 
 impl fmt::Display for ValidTokenType {
@@ -114,6 +192,20 @@ impl Token {
             TokenType::InvalidTokenType(_) => None,
         }
     }
+
+    /// The source region of the lexeme. Block comments may span several lines.
+    pub fn get_span(&self) -> Span {
+        let (mut line, mut column) = self.location;
+        for c in self.lexeme.chars() {
+            if c == '\n' {
+                line += 1;
+                column = 0;
+            } else {
+                column += 1;
+            }
+        }
+        Span::new(self.location, (line, column))
+    }
 }
 
 impl Debug for Token {
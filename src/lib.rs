@@ -0,0 +1,4 @@
+pub mod code_generation;
+pub mod lexical;
+pub mod semantic;
+pub mod syntactic;
@@ -1,36 +1,433 @@
 extern crate core;
-extern crate core;
-
-mod code_generation;
-mod lexical;
-mod semantic;
-mod syntactic;
 
-use crate::semantic::ast::{generate_symbol_tables, AbstractSyntaxTree};
-use crate::syntactic::parser::Parser;
-use lexical::lexer::Lexer;
+use compiler_442::code_generation::translation::generate_moon_code;
+use compiler_442::lexical::lexer::Lexer;
+use compiler_442::lexical::span::Span;
+use compiler_442::semantic::ast::{generate_symbol_tables, AbstractSyntaxTree};
+use compiler_442::semantic::concept::Concept;
+use compiler_442::semantic::desugar::desugar;
+use compiler_442::semantic::pretty_printer;
+use compiler_442::semantic::pretty_printer::{BraceStyle, FormatOptions};
+use compiler_442::semantic::serialization;
+use compiler_442::semantic::source_map::SourceMap;
+use compiler_442::semantic::typed_ast;
+use compiler_442::syntactic::lalr_parser::LalrParser;
+use compiler_442::syntactic::parser::Parser;
+use compiler_442::syntactic::syntax_error::write_syntax_errors;
+use compiler_442::syntactic::trace::{Trace, TraceFormat};
+use compiler_442::syntactic::tree::NodeId;
+use std::collections::HashMap;
 use std::fs;
 use std::fs::File;
 use std::io::Write;
+use std::path::Path;
+
+enum FmtMode {
+    /// rewrite the source file
+    Write,
+    /// only tell whether the source file is formatted
+    Check,
+}
+
+const USAGE: &str = "usage: compiler-442 [OPTIONS] FILE
+
+
+  --backend=ll|lalr                      parse with the LL(1) or the LALR(1) parser
+  --trace=sentential|productions|json    write the derivations to FILE.outderivation,
+                                         with the LL(1) parser only
+  --cst=text|dot|json                    write the parse tree to FILE.outcst, with the
+                                         LL(1) parser only
+  --ast=text|json|sexpr|dot              write the AST to FILE.outast, syntax errors
+                                         highlighted in dot
+  --print                                write the program back as canonical source
+                                         to FILE.outsrc
+  --fmt                                  rewrite FILE.src in formatted style, keeping
+                                         comments
+  --check                                only tell whether FILE.src is formatted, and
+                                         exit with 1 if not
+  --indent=N                             indent width of --fmt and --check
+  --brace-style=same-line|next-line      brace style of --fmt and --check
+  --node-at=LINE:COL                     print the innermost AST node at the position
+                                         and the nodes enclosing it
+  --from-ast=json|sexpr                  read FILE as an AST written by --ast instead
+                                         of parsing it as source
+  --moon                                 write the moon code of a program without
+                                         errors to FILE.moon";
 
-fn main() {
+/// What the command line asks for
+struct Options {
+    /// the source file
+    path: String,
+    use_lalr: bool,
+    trace_format: Option<TraceFormat>,
+    cst_format: Option<String>,
+    ast_format: Option<String>,
+    print_source: bool,
+    fmt_mode: Option<FmtMode>,
+    format_options: FormatOptions,
+    node_position: Option<(u32, u32)>,
+    /// the form of the AST in the file, if it holds one rather than source
+    ast_input: Option<String>,
+    generate_code: bool,
+}
+
+impl Options {
+    /// Read the arguments, without the program name. Each option is `--name` or
+    /// `--name=value`, and the one argument that is not an option is the source file.
+    fn parse(args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut path = None;
+        let mut options = Self {
+            path: String::new(),
+            use_lalr: false,
+            trace_format: None,
+            cst_format: None,
+            ast_format: None,
+            print_source: false,
+            fmt_mode: None,
+            format_options: FormatOptions::default(),
+            node_position: None,
+            ast_input: None,
+            generate_code: false,
+        };
+        for arg in args {
+            if !arg.starts_with("--") {
+                if path.replace(arg.clone()).is_some() {
+                    return Err(format!(
+                        "unexpected argument `{}`, only one file is read",
+                        arg
+                    ));
+                }
+                continue;
+            }
+            let (name, value) = match arg.split_once('=') {
+                Some((name, value)) => (name, Some(value)),
+                None => (arg.as_str(), None),
+            };
+            match (name, value) {
+                ("--backend", Some("ll")) => options.use_lalr = false,
+                ("--backend", Some("lalr")) => options.use_lalr = true,
+                ("--trace", Some(format)) => {
+                    options.trace_format = Some(format.parse::<TraceFormat>()?)
+                }
+                ("--cst", Some(format @ ("text" | "dot" | "json"))) => {
+                    options.cst_format = Some(format.to_string())
+                }
+                ("--ast", Some(format @ ("text" | "json" | "sexpr" | "dot"))) => {
+                    options.ast_format = Some(format.to_string())
+                }
+                ("--print", None) => options.print_source = true,
+                ("--fmt", None) => {
+                    options.fmt_mode.get_or_insert(FmtMode::Write);
+                }
+                ("--check", None) => options.fmt_mode = Some(FmtMode::Check),
+                ("--indent", Some(width)) => {
+                    options.format_options.indent_width = width
+                        .parse()
+                        .map_err(|_| format!("invalid indent width `{}`", width))?
+                }
+                ("--brace-style", Some(style)) => {
+                    options.format_options.brace_style = style.parse::<BraceStyle>()?
+                }
+                ("--node-at", Some(position)) => {
+                    options.node_position = Some(
+                        position
+                            .split_once(':')
+                            .and_then(|(line, column)| {
+                                Some((line.parse().ok()?, column.parse().ok()?))
+                            })
+                            .ok_or(format!(
+                                "invalid position `{}`, expected LINE:COL",
+                                position
+                            ))?,
+                    )
+                }
+                ("--from-ast", Some(format @ ("json" | "sexpr"))) => {
+                    options.ast_input = Some(format.to_string())
+                }
+                ("--moon", None) => options.generate_code = true,
+                (
+                    "--backend" | "--trace" | "--cst" | "--ast" | "--print" | "--fmt" | "--check"
+                    | "--indent" | "--brace-style" | "--node-at" | "--from-ast" | "--moon",
+                    _,
+                ) => return Err(format!("invalid option `{}`", arg)),
+                _ => return Err(format!("unknown option `{}`", arg)),
+            }
+        }
+        options.path = path.ok_or("no source file given")?;
+        // these need the source and its tokens, which an AST does not keep
+        if options.ast_input.is_some()
+            && (options.use_lalr
+                || options.trace_format.is_some()
+                || options.cst_format.is_some()
+                || options.fmt_mode.is_some())
+        {
+            return Err(
+                "--from-ast cannot be used with --backend, --trace, --cst, --fmt or --check"
+                    .to_string(),
+            );
+        }
+        // the LALR(1) parser neither derives the leftmost derivation nor builds a CST
+        if options.use_lalr && (options.trace_format.is_some() || options.cst_format.is_some()) {
+            return Err("--backend=lalr cannot be used with --trace or --cst".to_string());
+        }
+        Ok(options)
+    }
+}
+
+fn main() -> std::io::Result<()> {
+    let options = match Options::parse(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            std::process::exit(2);
+        }
+    };
+    // the outputs are named after the input file
+    let path = Path::new(&options.path)
+        .with_extension("")
+        .display()
+        .to_string();
+    let text = match fs::read_to_string(&options.path) {
+        Ok(text) => text,
+        Err(_) => panic!("Cannot find source code {}", options.path),
+    };
+    let read_ast = match options.ast_input.as_deref() {
+        Some("json") => serialization::from_json(&text),
+        Some(_) => serialization::from_sexpr(&text),
+        None => return compile(&options, &text, &path),
+    };
+    match read_ast {
+        Ok((ast, ast_spans)) => write_outputs(&options, &path, &ast, &ast_spans),
+        Err(e) => {
+            println!("Cannot read the AST of {}: {}", options.path, e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Parse the source, then format it or write the outputs of its AST
+fn compile(options: &Options, src: &str, path: &str) -> std::io::Result<()> {
     let mut lexer: Lexer = Lexer::new();
     let mut parser = Parser::new();
-    let path = "resource/semantics/test";
-    if let Ok(src) = fs::read_to_string(path.to_string() + ".src") {
-        lexer.read_source(&src);
-        let (_, ast) = parser.parse(lexer.get_tokens()).unwrap();
-        println!("{}", ast);
-        let tables = generate_symbol_tables(&ast as &AbstractSyntaxTree);
-        let mut outsymboltables = File::create(path.to_string() + ".outsymboltables").unwrap();
-        for (_, table) in tables.iter() {
-            outsymboltables.write_all(format!("{}\n", table).as_bytes());
-            // println!("{}", table);
-            // println!()
-        }
-        // let mut outast_file = File::create(path.to_string() + ".outast").unwrap();
-        // outast_file.write_all(format!("{}", ast).as_bytes());
+    lexer.read_source(src);
+    if let Some(format) = options.trace_format.clone() {
+        let file = File::create(path.to_string() + ".outderivation")?;
+        parser.set_trace(Some(Trace::new(format, Box::new(file))));
+    }
+    let raw_tokens = lexer.get_tokens();
+    let parse_result = if options.use_lalr {
+        let lalr_parser = LalrParser::new();
+        for conflict in lalr_parser.get_conflicts() {
+            println!("{}", conflict);
+        }
+        lalr_parser
+            .parse(raw_tokens.clone())
+            .map(|(ast, ast_spans, syntax_errors)| (None, ast, ast_spans, syntax_errors))
     } else {
-        panic!("Cannot find source code");
+        parser
+            .parse(raw_tokens.clone())
+            .map(|(cst, ast, ast_spans, syntax_errors)| (Some(cst), ast, ast_spans, syntax_errors))
+    };
+    let syntax_errors = match &parse_result {
+        Ok((_, _, _, syntax_errors)) | Err(syntax_errors) => syntax_errors,
+    };
+    let mut outsyntaxerrors = File::create(path.to_string() + ".outsyntaxerrors")?;
+    write_syntax_errors(&mut outsyntaxerrors, &raw_tokens, syntax_errors)?;
+    let (ast, ast_spans) = match parse_result {
+        Ok((cst, ast, ast_spans, syntax_errors)) => {
+            for e in syntax_errors.iter() {
+                println!("{}", e);
+            }
+            if options.fmt_mode.is_some() && !syntax_errors.is_empty() {
+                // the parts skipped by error recovery would be lost
+                println!("Cannot format a source with syntax errors");
+                std::process::exit(1);
+            }
+            if let (Some(cst), Some(format)) = (cst, &options.cst_format) {
+                let output = match format.as_str() {
+                    "text" => cst.to_text(),
+                    "dot" => cst.to_dot(),
+                    _ => cst.to_json(),
+                };
+                let mut outcst = File::create(path.to_string() + ".outcst")?;
+                outcst.write_all(output.as_bytes())?;
+            }
+            (ast, ast_spans)
+        }
+        Err(syntax_errors) => {
+            for e in syntax_errors.iter() {
+                println!("{}", e);
+            }
+            if options.fmt_mode.is_some() {
+                std::process::exit(1);
+            }
+            return Ok(());
+        }
+    };
+    if let Some(mode) = &options.fmt_mode {
+        let tokens = raw_tokens;
+        if tokens
+            .iter()
+            .any(|token| token.get_valid_token_type().is_none())
+        {
+            println!("Cannot format a source with lexical errors");
+            std::process::exit(1);
+        }
+        let formatted = pretty_printer::format(&ast, &ast_spans, &tokens, options.format_options);
+        match mode {
+            FmtMode::Write => fs::write(&options.path, formatted)?,
+            FmtMode::Check if formatted != src => {
+                println!("{} is not formatted", options.path);
+                std::process::exit(1);
+            }
+            FmtMode::Check => {}
+        }
+        return Ok(());
+    }
+    write_outputs(options, path, &ast, &ast_spans)
+}
+
+/// Write what the options ask for about the AST, and its symbol tables
+fn write_outputs(
+    options: &Options,
+    path: &str,
+    ast: &AbstractSyntaxTree,
+    ast_spans: &HashMap<NodeId, Span>,
+) -> std::io::Result<()> {
+    // an AST read back from an empty program has no root, nor anything to write
+    if !ast.has_root() {
+        println!("The AST is empty, no output is written");
+        return Ok(());
+    }
+    println!("{}", ast);
+    if let Some(format) = &options.ast_format {
+        let output = match format.as_str() {
+            "text" => ast.to_string(),
+            "json" => serialization::to_json(ast, ast_spans),
+            "sexpr" => serialization::to_sexpr(ast, ast_spans),
+            _ => {
+                let root = ast.get_root();
+                let errors = ast.find_all(root, Concept::is_error).into_iter().collect();
+                ast.to_dot(root, Concept::label, &errors)
+            }
+        };
+        let mut outast = File::create(path.to_string() + ".outast")?;
+        outast.write_all(output.as_bytes())?;
+    }
+    if let Some(position) = options.node_position {
+        let source_map = SourceMap::new(ast, ast_spans);
+        for (node, span) in source_map.selection_ranges(position) {
+            println!("{} {}", span, ast.get_node_value(node).label());
+        }
+    }
+    if options.print_source {
+        let mut outsrc = File::create(path.to_string() + ".outsrc")?;
+        outsrc.write_all(pretty_printer::unparse(ast).as_bytes())?;
+    }
+    let program = desugar(&typed_ast::lower(ast, ast_spans));
+    let (tables, semantic_errors) = generate_symbol_tables(&program);
+    let mut outsemanticerrors = File::create(path.to_string() + ".outsemanticerrors")?;
+    for e in semantic_errors.iter() {
+        println!("{}", e);
+        writeln!(outsemanticerrors, "{}", e)?;
+    }
+    let mut outsymboltables = File::create(path.to_string() + ".outsymboltables")?;
+    for (_, table) in tables.iter() {
+        outsymboltables.write_all(format!("{}\n", table).as_bytes())?;
+        // println!("{}", table);
+        // println!()
+    }
+    if options.generate_code {
+        // syntax errors leave error nodes, which code generation reports
+        if semantic_errors.iter().any(|e| e.is_error()) {
+            println!("Cannot generate code for a program with semantic errors");
+            std::process::exit(1);
+        }
+        match generate_moon_code(&program, &tables) {
+            Ok(code) => {
+                let mut outmoon = File::create(path.to_string() + ".moon")?;
+                outmoon.write_all(code.as_bytes())?;
+            }
+            Err(e) => {
+                println!("{}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Options, String> {
+        Options::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn reads_the_file_and_the_options() {
+        let options = parse(&["--backend=lalr", "a.src", "--indent=2", "--node-at=3:4"]).unwrap();
+        assert_eq!(options.path, "a.src");
+        assert!(options.use_lalr);
+        assert_eq!(options.format_options.indent_width, 2);
+        assert_eq!(options.node_position, Some((3, 4)));
+        assert!(options.fmt_mode.is_none());
+        assert!(!options.generate_code);
+        assert!(parse(&["--moon", "a.src"]).unwrap().generate_code);
+    }
+
+    #[test]
+    fn reads_the_format_options() {
+        let options = parse(&["--fmt", "--check", "--brace-style=next-line", "a.src"]).unwrap();
+        assert!(matches!(options.fmt_mode, Some(FmtMode::Check)));
+        assert_eq!(options.format_options.brace_style, BraceStyle::NextLine);
+        assert!(matches!(
+            parse(&["--check", "--fmt", "a.src"]).unwrap().fmt_mode,
+            Some(FmtMode::Check)
+        ));
+        assert!(parse(&["--brace-style=k&r", "a.src"]).is_err());
+    }
+
+    #[test]
+    fn rejects_unknown_options_and_invalid_values() {
+        assert_eq!(
+            parse(&["--bogus", "a.src"]).err().unwrap(),
+            "unknown option `--bogus`"
+        );
+        assert_eq!(
+            parse(&["--cst=svg", "a.src"]).err().unwrap(),
+            "invalid option `--cst=svg`"
+        );
+        assert_eq!(
+            parse(&["--print=yes", "a.src"]).err().unwrap(),
+            "invalid option `--print=yes`"
+        );
+        assert!(parse(&["--moon=yes", "a.src"]).is_err());
+        assert!(parse(&["--node-at=3", "a.src"]).is_err());
+        assert!(parse(&["--indent=two", "a.src"]).is_err());
+    }
+
+    #[test]
+    fn reads_an_ast_without_its_source() {
+        let options = parse(&["--from-ast=sexpr", "a.outast", "--print"]).unwrap();
+        assert_eq!(options.ast_input.as_deref(), Some("sexpr"));
+        assert!(parse(&["--from-ast=dot", "a.outast"]).is_err());
+        assert!(parse(&["--from-ast=json", "--fmt", "a.outast"]).is_err());
+        assert!(parse(&["--from-ast=json", "--cst=text", "a.outast"]).is_err());
+    }
+
+    #[test]
+    fn rejects_outputs_the_lalr_parser_does_not_make() {
+        assert!(parse(&["--backend=lalr", "--trace=json", "a.src"]).is_err());
+        assert!(parse(&["--backend=lalr", "--cst=text", "a.src"]).is_err());
+        assert!(parse(&["--backend=ll", "--trace=json", "--cst=text", "a.src"]).is_ok());
+        assert!(parse(&["--backend=lalr", "--ast=dot", "a.src"]).is_ok());
+    }
+
+    #[test]
+    fn needs_exactly_one_file() {
+        assert_eq!(parse(&["--print"]).err().unwrap(), "no source file given");
+        assert!(parse(&["a.src", "b.src"]).is_err());
     }
 }
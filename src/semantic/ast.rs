@@ -1,781 +1,1029 @@
-use crate::semantic::concept::{AtomicConceptType, CompositeConcept, Concept};
-use crate::semantic::semantic_error::{SemanticErrType, SemanticError};
+use crate::semantic::concept::Concept;
+use crate::semantic::constant_folding;
+use crate::semantic::desugar::is_negation;
+use crate::semantic::semantic_error::SemanticError;
 use crate::semantic::symbol_table::{SymbolKind, SymbolTable, SymbolTableEntry, SymbolType};
+use crate::semantic::typed_ast::{
+    BaseType, BinaryOp, Block, ConstDecl, Expr, ExprKind, FuncDef, ImplDef, Initializer, Item,
+    Member, Program, Stmt, StmtKind, StructDecl, TypeSpec, UnaryOp, VarDecl,
+};
+use crate::semantic::visit::{walk_stmt, Visitor};
 use crate::syntactic::tree::{NodeId, Tree};
 use std::collections::HashMap;
 
 pub type AbstractSyntaxTree = Tree<Concept>;
 
-pub fn generate_symbol_tables(ast: &AbstractSyntaxTree) -> HashMap<String, SymbolTable> {
+/// Build the symbol tables of the program and check its types. The type check stops at
+/// the first error of a declaration. Return the tables, and the errors and warnings in
+/// the order they are found.
+pub fn generate_symbol_tables(
+    program: &Program,
+) -> (HashMap<String, SymbolTable>, Vec<SemanticError>) {
+    let mut errors = vec![];
     let mut table_container = HashMap::new();
-    let root = ast.get_root();
-    create_table(ast, root, &mut table_container, "".to_string());
-    check_func_def(&table_container);
-    refer_type_on_node(root, ast, "global", &table_container);
-    table_container
+    create_global_table(program, &mut table_container, &mut errors);
+    infer_var_types(program, &mut table_container, &mut errors);
+    check_func_def(&table_container, &mut errors);
+    if let Err(e) = refer_type_on_program(program, &table_container, &mut errors) {
+        errors.push(e);
+    }
+    (table_container, errors)
+}
+
+fn create_global_table(
+    program: &Program,
+    table_container: &mut HashMap<String, SymbolTable>,
+    errors: &mut Vec<SemanticError>,
+) {
+    let mut this_table = SymbolTable::new("global".to_string());
+    for item in program.items.iter() {
+        let entry = match item {
+            // broken by a syntax error
+            Item::Error(_) => None,
+            // constants may use the ones declared before them
+            Item::Const(const_decl) => constant_entry(const_decl, &this_table, errors),
+            Item::Func(func_def) => Some(SymbolTableEntry::from_func_def(
+                func_def,
+                table_container,
+                "global",
+                errors,
+            )),
+            Item::Struct(struct_decl) => Some(SymbolTableEntry::from_struct_decl(
+                struct_decl,
+                table_container,
+                "global",
+                errors,
+            )),
+            Item::Var(var_decl) => Some(SymbolTableEntry::from_var_decl(var_decl)),
+            Item::Impl(impl_def) => {
+                add_impl_def_entries(impl_def, table_container, "global", errors);
+                None
+            }
+        };
+        if let Some(entry) = entry {
+            this_table.insert(entry, errors);
+        }
+    }
+    table_container.insert("global".to_string(), this_table);
 }
 
-pub fn create_table(
-    ast: &AbstractSyntaxTree,
-    node: NodeId,
+/// Create the table of a function with its parameters and local variables, and return
+/// its name
+pub fn create_func_table(
+    func_def: &FuncDef,
     table_container: &mut HashMap<String, SymbolTable>,
-    name_prefix: String,
+    name_prefix: &str,
+    errors: &mut Vec<SemanticError>,
 ) -> String {
-    let concept = ast.get_node_value(node);
-    match concept {
-        Concept::AtomicConcept(_) => "".to_string(),
-        Concept::CompositeConcept(cc) => match cc {
-            CompositeConcept::FuncDef => {
-                let func_def_children = ast.get_children(node);
-                let func_name = ast
-                    .get_node_value(func_def_children[0])
-                    .get_atomic_concept()
-                    .get_value();
-                let table_name = format!("{}:{}", name_prefix, func_name);
-                let mut this_table = SymbolTable::new(table_name.clone());
-
-                // insert entries of params
-                for f_param in ast.get_children(func_def_children[1]) {
-                    if let Some(entry) = SymbolTableEntry::from_node(
-                        f_param,
-                        ast,
-                        table_container,
-                        table_name.clone(),
-                    ) {
-                        this_table.insert(entry);
-                    }
-                }
+    let table_name = format!("{}:{}", name_prefix, func_def.name.name);
+    let mut this_table = SymbolTable::new(table_name.clone());
 
-                for body_stmt_node in ast.get_children(func_def_children[3]) {
-                    if let Some(entry) = SymbolTableEntry::from_node(
-                        body_stmt_node,
-                        ast,
-                        table_container,
-                        table_name.clone(),
-                    ) {
-                        this_table.insert(entry);
-                    }
-                }
-                table_container.insert(table_name.clone(), this_table);
-                table_name
-            }
-            CompositeConcept::StructDecl => {
-                let struct_decl_children = ast.get_children(node);
-                let struct_name = ast
-                    .get_node_value(struct_decl_children[0])
-                    .get_atomic_concept()
-                    .get_value();
-                let table_name = format!("{}:{}", name_prefix, struct_name);
-                let mut this_table = SymbolTable::new(table_name.clone());
-
-                // inherits as entries
-                let inherit_nodes = ast.get_children(struct_decl_children[1]);
-                for inherit_node in inherit_nodes {
-                    let inherited_table_name = format!(
-                        "{}:{}",
-                        "global",
-                        ast.get_node_value(inherit_node)
-                            .get_atomic_concept()
-                            .get_value()
-                    );
-                    if table_container.contains_key(&inherited_table_name) {
-                        this_table.insert(SymbolTableEntry {
-                            name: "".to_string(),
-                            kind: SymbolKind::Inherits,
-                            symbol_type: SymbolType::from_node(inherit_node, ast),
-                            link: Some(inherited_table_name.clone()),
-                        });
-                    } else {
-                        SemanticError::report_error(&format!(
-                            "inherited class {} doesn't exist",
-                            inherited_table_name
-                        ));
+    // insert entries of params
+    for param in func_def.params.iter() {
+        this_table.insert(SymbolTableEntry::from_param(param), errors);
+    }
+
+    for stmt in func_def.body.iter() {
+        if let StmtKind::VarDecl(var_decl) = &stmt.kind {
+            this_table.insert(SymbolTableEntry::from_var_decl(var_decl), errors);
+        }
+    }
+
+    // loop variables of for loops anywhere in the body
+    let mut loop_vars = LoopVarCollector::default();
+    for stmt in func_def.body.iter() {
+        loop_vars.visit_stmt(stmt);
+    }
+    for loop_var_decl in loop_vars.var_decls {
+        let entry = SymbolTableEntry::from_var_decl(loop_var_decl);
+        // loops reusing a variable of the same type share its entry
+        let is_shared = this_table
+            .get_all_entries_by_name(&entry.name)
+            .iter()
+            .any(|existing| existing.symbol_type == entry.symbol_type);
+        if !is_shared {
+            this_table.insert(entry, errors);
+        }
+    }
+    table_container.insert(table_name.clone(), this_table);
+    table_name
+}
+
+/// Create the table of a struct with its inherited structs and its members, and return
+/// its name
+pub fn create_struct_table(
+    struct_decl: &StructDecl,
+    table_container: &mut HashMap<String, SymbolTable>,
+    name_prefix: &str,
+    errors: &mut Vec<SemanticError>,
+) -> String {
+    let table_name = format!("{}:{}", name_prefix, struct_decl.name.name);
+    let mut this_table = SymbolTable::new(table_name.clone());
+
+    // inherits as entries
+    for inherit in struct_decl.inherits.iter() {
+        let inherited_table_name = format!("{}:{}", "global", inherit.name);
+        if table_container.contains_key(&inherited_table_name) {
+            this_table.insert(
+                SymbolTableEntry {
+                    name: "".to_string(),
+                    kind: SymbolKind::Inherits,
+                    symbol_type: SymbolType::new(inherit.name.clone()),
+                    link: Some(inherited_table_name.clone()),
+                },
+                errors,
+            );
+        } else {
+            errors.push(SemanticError::error(&format!(
+                "inherited class {} doesn't exist",
+                inherited_table_name
+            )));
+        }
+    }
+
+    // members
+    for member_decl in struct_decl.members.iter() {
+        let entry = match &member_decl.member {
+            Member::Var(var_decl) => SymbolTableEntry::from_var_decl(var_decl),
+            Member::Func(func_decl) => SymbolTableEntry::from_func_decl(func_decl),
+        };
+        let inherit_entries = this_table.get_all_entries_by_kind(SymbolKind::Inherits);
+        let inherit_table_names = inherit_entries
+            .iter()
+            .map(|entry| entry.link.as_ref().unwrap())
+            .collect::<Vec<&String>>();
+        for inherit_table_name in inherit_table_names {
+            if search_inherited_class_from_member(
+                &entry.name,
+                match entry.kind {
+                    SymbolKind::Function => {
+                        let func_type = entry.symbol_type.get_name();
+                        Some(func_type.split(':').next_back().unwrap().to_string())
                     }
-                }
+                    _ => None,
+                },
+                inherit_table_name,
+                table_container,
+            )
+            .is_some()
+            {
+                errors.push(SemanticError::warning(&format!(
+                    "Overriding member {}",
+                    &entry.name
+                )));
+            }
+        }
+        this_table.insert(entry, errors);
+    }
+    table_container.insert(table_name.clone(), this_table);
+    table_name
+}
 
-                // members
-                let struct_member_decl_nodes = ast.get_children(struct_decl_children[2]);
-                for struct_member_decl_node in struct_member_decl_nodes {
-                    match SymbolTableEntry::from_node(
-                        struct_member_decl_node,
-                        ast,
-                        table_container,
-                        table_name.clone(),
-                    ) {
-                        None => {}
-                        Some(entry) => {
-                            let inherit_entries =
-                                this_table.get_all_entries_by_kind(SymbolKind::Inherits);
-                            let inherit_table_names = inherit_entries
-                                .iter()
-                                .map(|entry| entry.link.as_ref().unwrap())
-                                .collect::<Vec<&String>>();
-                            for inherit_table_name in inherit_table_names {
-                                if search_inherited_class_from_member(
-                                    &entry.name,
-                                    match entry.kind {
-                                        SymbolKind::Function => {
-                                            let func_type = entry.symbol_type.get_name();
-                                            Some(func_type.split(":").last().unwrap().to_string())
-                                        }
-                                        _ => None,
-                                    },
-                                    inherit_table_name,
-                                    table_container,
-                                )
-                                .is_some()
-                                {
-                                    SemanticError::report(
-                                        SemanticErrType::Warning,
-                                        &format!("Overriding member {}", &entry.name),
-                                    );
-                                }
-                            }
-                            this_table.insert(entry);
-                        }
+/// Add the functions of an `impl` block to the table of its struct, which has declared
+/// them
+fn add_impl_def_entries(
+    impl_def: &ImplDef,
+    table_container: &mut HashMap<String, SymbolTable>,
+    name_prefix: &str,
+    errors: &mut Vec<SemanticError>,
+) {
+    let table_name = format!("{}:{}", name_prefix, impl_def.name.name);
+    let mut new_entry_set = vec![];
+    for func_def in impl_def.funcs.iter() {
+        new_entry_set.push(SymbolTableEntry::from_func_def(
+            func_def,
+            table_container,
+            &table_name,
+            errors,
+        ));
+    }
+
+    match table_container.get_mut(&table_name) {
+        None => {
+            errors.push(SemanticError::error(&format!(
+                "struct {} is undefined",
+                impl_def.name.name
+            )));
+        }
+        Some(table) => {
+            for new_entry in new_entry_set {
+                table.insert(new_entry, errors);
+            }
+        }
+    }
+}
+
+fn refer_type_on_program(
+    program: &Program,
+    table_container: &HashMap<String, SymbolTable>,
+    errors: &mut Vec<SemanticError>,
+) -> Result<String, SemanticError> {
+    // for each funcDef, implDef and structDecl, check type
+    for item in program.items.iter() {
+        match item {
+            Item::Func(func_def) => {
+                refer_type_on_func_def(func_def, "global", table_container, errors)?;
+            }
+            Item::Var(var_decl) => {
+                refer_type_on_var_decl(var_decl, "global", false, table_container, errors)?;
+            }
+            Item::Struct(struct_decl) => {
+                let scope = format!("global:{}", struct_decl.name.name);
+                for member_decl in struct_decl.members.iter() {
+                    if let Member::Var(var_decl) = &member_decl.member {
+                        refer_type_on_var_decl(var_decl, &scope, true, table_container, errors)?;
                     }
                 }
-                table_container.insert(table_name.clone(), this_table);
-                table_name
-            }
-            // CompositeConcept::InheritsList => {}
-            // CompositeConcept::StructMemberDeclList => {}
-            // CompositeConcept::StructMemberDecl => {}
-            CompositeConcept::Prog => {
-                let mut this_table = SymbolTable::new("global".to_string());
-                for child in ast.get_children(node) {
-                    match SymbolTableEntry::from_node(
-                        child,
-                        &ast,
-                        table_container,
-                        "global".to_string(),
-                    ) {
-                        None => {}
-                        Some(entry) => {
-                            this_table.insert(entry);
-                        }
-                    }
+            }
+            Item::Impl(impl_def) => {
+                let struct_table_name = format!("global:{}", impl_def.name.name);
+                if !table_container.contains_key(&struct_table_name) {
+                    // the struct was dropped after a syntax error
+                    continue;
+                }
+                for func_def in impl_def.funcs.iter() {
+                    refer_type_on_func_def(func_def, &struct_table_name, table_container, errors)?;
                 }
-                table_container.insert("global".to_string(), this_table);
-                "global".to_string()
             }
-            _ => panic!(""),
-        },
+            Item::Const(_) | Item::Error(_) => {}
+        }
     }
+    Ok("".to_string())
 }
 
-fn refer_type_on_node(
-    node: NodeId,
-    ast: &AbstractSyntaxTree,
+/// Check the statements of a function, and that it returns its declared type
+fn refer_type_on_func_def(
+    func_def: &FuncDef,
     scope: &str,
     table_container: &HashMap<String, SymbolTable>,
+    errors: &mut Vec<SemanticError>,
 ) -> Result<String, SemanticError> {
-    let concept = ast.get_node_value(node);
-    let table = table_container.get(scope).unwrap();
-
-    match concept {
-        Concept::AtomicConcept(ac) => match ac.atomic_concept_type {
-            AtomicConceptType::Id => {
-                let entries = table.get_all_entries_by_name(&ac.get_value());
-                if !entries.is_empty() {
-                    // id is found in this table
-                    Ok(entries[0].symbol_type.get_name())
-                } else {
-                    if scope.eq("global") {
-                        // already searching in the global scope and still not found. Error
-                        return Err(SemanticError::report_error(&format!(
-                            "{} referred is undeclared",
-                            ac.get_value()
-                        )));
+    // get the table belong to this funcDef
+    let table_name = format!("{}:{}", scope, func_def.name.name);
+    let this_table = table_container
+        .get(&table_name)
+        .unwrap_or_else(|| panic!("cannot get table {}", table_name));
+
+    // check statements in func body and return type matches
+    let mut body_return_type = String::from("");
+    for stmt in func_def.body.iter() {
+        let stmt_type = refer_type_on_stmt(stmt, &table_name, false, table_container, errors)?;
+        if matches!(stmt.kind, StmtKind::Return(_)) {
+            body_return_type = stmt_type;
+        }
+    }
+    let defined_return_type =
+        refer_type_on_return_type(&func_def.return_type, &table_name, table_container)?;
+    if body_return_type.is_empty() && !defined_return_type.eq("void") {
+        Err(SemanticError::error(&format!(
+            "function {} doesn't contain return statement of type {}",
+            &this_table.get_table_name(),
+            &defined_return_type
+        )))
+    } else if body_return_type.eq("integer") && defined_return_type.eq("float") {
+        // auto-cast integer to float
+        Ok("float".to_string())
+    } else if !body_return_type.is_empty() && !body_return_type.eq(&defined_return_type) {
+        Err(SemanticError::error(&format!(
+            "function {} should return {} but {} is returned",
+            &this_table.get_table_name(),
+            &defined_return_type,
+            &body_return_type
+        )))
+    } else {
+        Ok(body_return_type)
+    }
+}
+
+fn refer_type_on_return_type(
+    return_type: &TypeSpec,
+    scope: &str,
+    table_container: &HashMap<String, SymbolTable>,
+) -> Result<String, SemanticError> {
+    match &return_type.base {
+        BaseType::Void => Ok("void".to_string()),
+        BaseType::Class(name) => refer_type_on_id(name, scope, table_container),
+        base => Ok(SymbolType::of_base(base).get_name()),
+    }
+}
+
+/// Check the initializer of a variable, if any, against its type
+fn refer_type_on_var_decl(
+    var_decl: &VarDecl,
+    scope: &str,
+    is_member: bool,
+    table_container: &HashMap<String, SymbolTable>,
+    errors: &mut Vec<SemanticError>,
+) -> Result<String, SemanticError> {
+    let initializer = match &var_decl.initializer {
+        Some(initializer) => initializer,
+        None => return Ok("".to_string()),
+    };
+    let name = &var_decl.name.name;
+    if is_member {
+        return Err(SemanticError::error(&format!(
+            "member {} of a struct cannot be initialized",
+            name
+        )));
+    }
+    let element_type = if var_decl.var_type.base == BaseType::Inferred {
+        let table = table_container.get(scope).unwrap();
+        match table.get_all_entries_by_name(name).first() {
+            // inference failed and was reported
+            Some(entry) if entry.symbol_type.get_name().is_empty() => return Ok("".to_string()),
+            Some(entry) => entry.symbol_type.get_name(),
+            None => return Ok("".to_string()),
+        }
+    } else {
+        SymbolType::of_base(&var_decl.var_type.base).get_name()
+    };
+    check_initializer(
+        name,
+        &element_type,
+        &var_decl.var_type.dimensions,
+        initializer,
+        scope,
+        table_container,
+        errors,
+    )?;
+    Ok("".to_string())
+}
+
+/// The type of a statement: the type returned by a `return`, the one of the last
+/// statement of a block, or empty
+fn refer_type_on_stmt(
+    stmt: &Stmt,
+    scope: &str,
+    is_in_loop: bool,
+    table_container: &HashMap<String, SymbolTable>,
+    errors: &mut Vec<SemanticError>,
+) -> Result<String, SemanticError> {
+    match &stmt.kind {
+        StmtKind::VarDecl(var_decl) => {
+            refer_type_on_var_decl(var_decl, scope, false, table_container, errors)
+        }
+        StmtKind::Assign { target, value } => {
+            check_not_constant(target, scope, table_container)?;
+            let left_type = refer_type_on_expr(target, scope, table_container)?;
+            let right_type = refer_type_on_expr(value, scope, table_container)?;
+            if !left_type.eq(&right_type) {
+                errors.push(SemanticError::error(&format!(
+                    "Left and right hand side of assignment operator have different types: {} vs. {}",
+                    left_type, right_type
+                )));
+            }
+            Ok("".to_string())
+        }
+        StmtKind::Expr(expr) => refer_type_on_expr(expr, scope, table_container),
+        StmtKind::If {
+            condition,
+            then_branch,
+            else_branch,
+        } => {
+            let condition_type = refer_type_on_expr(condition, scope, table_container)?;
+            if !condition_type.eq("bool") {
+                return Err(SemanticError::error(&format!(
+                    "If condition should be bool, but {} is found",
+                    condition_type
+                )));
+            }
+            let then_type =
+                refer_type_on_block(then_branch, scope, is_in_loop, table_container, errors)?;
+            let else_type = match else_branch {
+                Some(else_branch) => {
+                    refer_type_on_stmt(else_branch, scope, is_in_loop, table_container, errors)?
+                }
+                None => "".to_string(),
+            };
+            if then_type.eq(&else_type) {
+                Ok(then_type)
+            } else {
+                Ok("".to_string())
+            }
+        }
+        StmtKind::While { condition, body } => {
+            let condition_type = refer_type_on_expr(condition, scope, table_container)?;
+            if !condition_type.eq("bool") {
+                return Err(SemanticError::error(&format!(
+                    "while condition should be bool, but {} is found",
+                    condition_type
+                )));
+            }
+            refer_type_on_block(body, scope, true, table_container, errors)
+        }
+        StmtKind::For {
+            variable,
+            initial_value,
+            condition,
+            step,
+            body,
+        } => {
+            let loop_var_type = SymbolType::from_type_spec(&variable.var_type).get_name();
+            let init_type = refer_type_on_expr(initial_value, scope, table_container)?;
+            if !loop_var_type.eq(&init_type) {
+                errors.push(SemanticError::error(&format!(
+                    "for loop variable of type {} is initialized with {}",
+                    loop_var_type, init_type
+                )));
+            }
+            let condition_type = refer_type_on_expr(condition, scope, table_container)?;
+            if !condition_type.eq("bool") {
+                return Err(SemanticError::error(&format!(
+                    "for condition should be bool, but {} is found",
+                    condition_type
+                )));
+            }
+            refer_type_on_stmt(step, scope, true, table_container, errors)?;
+            refer_type_on_block(body, scope, true, table_container, errors)
+        }
+        StmtKind::Break | StmtKind::Continue => {
+            if !is_in_loop {
+                return Err(SemanticError::error(&format!(
+                    "{} statement outside of a loop",
+                    if stmt.kind == StmtKind::Break {
+                        "break"
                     } else {
-                        let mut scope_split_vec = scope.split(":").collect::<Vec<&str>>();
-                        if let Some((_, parent_scope)) = scope_split_vec.split_last() {
-                            return refer_type_on_node(
-                                node,
-                                ast,
-                                &parent_scope.join(":"),
-                                table_container,
-                            );
-                        } else {
-                            panic!("Unexpected scope string");
-                        }
+                        "continue"
                     }
-                }
+                )));
             }
-            AtomicConceptType::FloatLit => Ok("float".to_string()),
-            AtomicConceptType::Float => Ok("float".to_string()),
-            AtomicConceptType::IntLit => Ok("integer".to_string()),
-            AtomicConceptType::Integer => Ok("integer".to_string()),
-            AtomicConceptType::Void => Ok("void".to_string()),
-            //AtomicConceptType::RelOp => {}
-            // AtomicConceptType::MultiOp => {}
-            // AtomicConceptType::AddOp => {}
-            // AtomicConceptType::Sign => {}
-            // AtomicConceptType::Visibility => {}
-            // AtomicConceptType::Epsilon => {}
-            _ => Ok("".to_string()),
-        },
-        Concept::CompositeConcept(cc) => match cc {
-            CompositeConcept::Dot => {
-                let dot_children = ast.get_children(node);
-                let left_side_type =
-                    refer_type_on_node(dot_children[0], ast, scope, table_container)?;
-                let global_table = table_container.get("global").unwrap();
-                let entries_of_left_side = global_table.get_all_entries_by_name(&left_side_type);
-                // check caller is a defined class
-                if entries_of_left_side.is_empty() {
-                    return Err(SemanticError::report_error(&format!(
-                        "Type of caller of a \".\" operator should be a class. {} is found",
-                        &left_side_type
+            Ok("".to_string())
+        }
+        StmtKind::Return(expr) => refer_type_on_expr(expr, scope, table_container),
+        StmtKind::Block(block) => {
+            refer_type_on_block(block, scope, is_in_loop, table_container, errors)
+        }
+        StmtKind::Read(target) => {
+            check_not_constant(target, scope, table_container)?;
+            Ok("".to_string())
+        }
+        StmtKind::Write(_) | StmtKind::Error => Ok("".to_string()),
+    }
+}
+
+fn refer_type_on_block(
+    block: &Block,
+    scope: &str,
+    is_in_loop: bool,
+    table_container: &HashMap<String, SymbolTable>,
+    errors: &mut Vec<SemanticError>,
+) -> Result<String, SemanticError> {
+    let mut stmt_type: String = "".to_string();
+    for stmt in block.stmts.iter() {
+        stmt_type = refer_type_on_stmt(stmt, scope, is_in_loop, table_container, errors)?;
+    }
+    Ok(stmt_type)
+}
+
+fn refer_type_on_expr(
+    expr: &Expr,
+    scope: &str,
+    table_container: &HashMap<String, SymbolTable>,
+) -> Result<String, SemanticError> {
+    match &expr.kind {
+        ExprKind::IntLit(_) => Ok("integer".to_string()),
+        ExprKind::FloatLit(_) => Ok("float".to_string()),
+        ExprKind::BoolLit(_) => Ok("bool".to_string()),
+        ExprKind::Id(id) => refer_type_on_id(&id.name, scope, table_container),
+        ExprKind::Var { .. } => {
+            // `(a[i])[j]` of the core language is `a[i][j]`
+            let (base, indices) = var_parts(expr);
+            let left_type = refer_type_on_expr(base, scope, table_container)?;
+            for index in indices.iter() {
+                let index_type = refer_type_on_expr(index, scope, table_container)?;
+                if !index_type.eq("integer") {
+                    return Err(SemanticError::error(&format!(
+                        "array index should be integer, but {} is found",
+                        index_type
                     )));
-                } else {
-                    let class_entry = &entries_of_left_side[0];
-                    let left_side_table = table_container
-                        .get(&class_entry.link.clone().unwrap())
-                        .unwrap();
-                    let right_side_name = ast
-                        .get_node_value(dot_children[1])
-                        .get_atomic_concept()
-                        .get_value();
-
-                    // check callee is in a table
-                    let target_table_option = search_inherited_class_from_member(
-                        &right_side_name,
-                        None,
-                        &left_side_table.get_table_name(),
-                        table_container,
-                    );
-                    match target_table_option {
-                        None => Err(SemanticError::report_error(&format!(
-                            "{} is not a member of {} or its super classes",
-                            right_side_name, left_side_type
-                        ))),
-                        Some(target_table_name) => {
-                            let entries = table_container
-                                .get(&target_table_name)
-                                .unwrap()
-                                .get_all_entries_by_name(&right_side_name);
-                            return Ok(entries[0].symbol_type.get_name());
-                        }
-                    }
                 }
             }
-            CompositeConcept::IndexList => {
-                let indices = ast.get_children(node);
-                if indices.len() == 0 {
-                    return Ok("".to_string());
-                }
-                let mut return_type = String::from("");
-                for index in indices {
-                    let index_type = refer_type_on_node(index, ast, scope, table_container)?;
-                    if !index_type.eq("integer") {
-                        return Err(SemanticError::report_error(&format!(
-                            "array index should be integer, but {} is found",
-                            index_type
-                        )));
-                    }
-                    return_type.push_str("[");
-                    return_type.push_str(&index_type);
-                    return_type.push_str("]");
-                }
-                Ok(return_type)
-            }
-            CompositeConcept::Var => {
-                let var_children = ast.get_children(node);
-                let left_type = refer_type_on_node(var_children[0], ast, scope, table_container)?;
-                let right_type = refer_type_on_node(var_children[1], ast, scope, table_container)?;
-                if right_type.eq("") {
-                    return Ok(left_type);
-                }
 
-                // check index number should match
-                let left_index_split = left_type.split("[").collect::<Vec<&str>>();
-                let right_index_split = right_type.split("[").collect::<Vec<&str>>();
-                return if left_index_split.len() == right_index_split.len() {
-                    Ok(left_index_split[0].to_string())
-                } else {
-                    Err(SemanticError::report_error(&format!(
-                        "array index call on variable of type {} does not match",
-                        left_type
-                    )))
-                };
-            }
-            CompositeConcept::Assign => {
-                let assign_children = ast.get_children(node);
-                let left_type =
-                    refer_type_on_node(assign_children[0], ast, scope, table_container)?;
-                let right_type =
-                    refer_type_on_node(assign_children[1], ast, scope, table_container)?;
-                if !left_type.eq(&right_type) {
-                    SemanticError::report_error(
-                        &format!("Left and right hand side of assignment operator have different types: {} vs. {}",left_type, right_type)
-                    );
+            // check index number should match
+            let left_index_split = left_type.split('[').collect::<Vec<&str>>();
+            if left_index_split.len() == indices.len() + 1 {
+                Ok(left_index_split[0].to_string())
+            } else {
+                Err(SemanticError::error(&format!(
+                    "array index call on variable of type {} does not match",
+                    left_type
+                )))
+            }
+        }
+        ExprKind::Member { object, member } => {
+            let left_side_type = refer_type_on_expr(object, scope, table_container)?;
+            let left_side_table_name = class_table_name(&left_side_type, table_container)?;
+            let right_side_name = &member.name;
+
+            // check callee is in a table
+            match search_inherited_class_from_member(
+                right_side_name,
+                None,
+                &left_side_table_name,
+                table_container,
+            ) {
+                None => Err(SemanticError::error(&format!(
+                    "{} is not a member of {} or its super classes",
+                    right_side_name, left_side_type
+                ))),
+                Some(target_table_name) => {
+                    let entries = table_container
+                        .get(&target_table_name)
+                        .unwrap()
+                        .get_all_entries_by_name(right_side_name);
+                    Ok(entries[0].symbol_type.get_name())
                 }
-                Ok("".to_string())
             }
-            CompositeConcept::FuncCall => {
-                // get entry from callee and verify params
-                let func_call_children = ast.get_children(node);
-                if matches!(
-                    ast.get_node_value(func_call_children[0]),
-                    Concept::CompositeConcept(CompositeConcept::Dot)
+        }
+        ExprKind::Call { callee, args } => match &callee.kind {
+            ExprKind::Member { object, member } => {
+                // left hand side is a dot. must check all types of this dot
+                let dot_callee_name = &member.name;
+                let dot_caller_type = refer_type_on_expr(object, scope, table_container)?;
+                let dot_caller_table_name = class_table_name(&dot_caller_type, table_container)?;
+                let params_type = refer_type_on_args(args, scope, table_container)?;
+                match search_inherited_class_from_member(
+                    dot_callee_name,
+                    Some(params_type.clone()),
+                    &dot_caller_table_name,
+                    table_container,
                 ) {
-                    // left hand side is a dot. must check all types of this dot
-                    let dot_children = ast.get_children(func_call_children[0]);
-                    let dot_callee_name = ast
-                        .get_node_value(dot_children[1])
-                        .get_atomic_concept()
-                        .get_value();
-                    let dot_caller_type =
-                        refer_type_on_node(dot_children[0], ast, scope, table_container)?;
-                    let global_table = table_container.get("global").unwrap();
-                    let entries_by_dot_caller_name =
-                        global_table.get_all_entries_by_name(&dot_caller_type);
-                    if entries_by_dot_caller_name.is_empty() {
-                        return Err(SemanticError::report_error(&format!(
-                            "Type of caller of a \".\" operator should be a class. {} is found",
-                            dot_caller_type
-                        )));
-                    }
-                    let dot_caller_class_entry = &entries_by_dot_caller_name[0];
-                    let dot_caller_table = table_container
-                        .get(&dot_caller_class_entry.link.clone().unwrap())
-                        .unwrap();
-                    let params_type =
-                        refer_type_on_node(func_call_children[1], ast, scope, table_container)?;
-                    let target_table_name_option = search_inherited_class_from_member(
-                        &dot_callee_name,
-                        Some(params_type.clone()),
-                        &dot_caller_table.get_table_name(),
-                        table_container,
-                    );
-                    match target_table_name_option {
-                        None => Err(SemanticError::report_error(&format!(
-                            "function {} of parameter type ({}) is not found as a member of {}",
-                            dot_callee_name, params_type, dot_caller_type
-                        ))),
-                        Some(target_table_name) => {
-                            let func_entry_symbol_type = table_container
-                                .get(&target_table_name)
-                                .unwrap()
-                                .get_all_entries_by_name(&dot_callee_name)
-                                .iter()
-                                .find(|entry| {
-                                    entry
-                                        .symbol_type
-                                        .get_name()
-                                        .split(":")
-                                        .last()
-                                        .unwrap()
-                                        .eq(&params_type)
-                                })
-                                .unwrap()
-                                .symbol_type
-                                .get_name();
-                            Ok(func_entry_symbol_type
-                                .split(":")
-                                .collect::<Vec<&str>>()
-                                .get(0)
-                                .unwrap()
-                                .to_string())
-                        }
-                    }
-                } else {
-                    // left hand side is an id
-                    let caller_name = ast
-                        .get_node_value(func_call_children[0])
-                        .get_atomic_concept()
-                        .get_value();
-                    let caller_type =
-                        refer_type_on_node(func_call_children[0], ast, scope, table_container)?;
-                    println!("scope = {}", scope);
-                    if !caller_type.contains(":") {
-                        return Err(SemanticError::report_error(&format!(
-                            "{} is not a function",
-                            caller_name
-                        )));
-                    }
-                    let caller_type_vec: Vec<&str> = caller_type.split(":").collect();
-                    let params_type =
-                        refer_type_on_node(func_call_children[1], ast, scope, table_container)?;
-                    if params_type.eq(caller_type_vec[1]) {
-                        Ok(caller_type_vec[0].to_string())
-                    } else {
-                        Err(SemanticError::report_error(&format!(
-                            "function {} should be called on parameter {}. Parameter {} is found",
-                            caller_name, caller_type_vec[1], params_type
-                        )))
+                    None => Err(SemanticError::error(&format!(
+                        "function {} of parameter type ({}) is not found as a member of {}",
+                        dot_callee_name, params_type, dot_caller_type
+                    ))),
+                    Some(target_table_name) => {
+                        let func_entry_symbol_type = table_container
+                            .get(&target_table_name)
+                            .unwrap()
+                            .get_all_entries_by_name(dot_callee_name)
+                            .iter()
+                            .find(|entry| {
+                                entry
+                                    .symbol_type
+                                    .get_name()
+                                    .split(':')
+                                    .next_back()
+                                    .unwrap()
+                                    .eq(&params_type)
+                            })
+                            .unwrap()
+                            .symbol_type
+                            .get_name();
+                        Ok(func_entry_symbol_type
+                            .split(':')
+                            .next()
+                            .unwrap()
+                            .to_string())
                     }
                 }
             }
-            CompositeConcept::RelExpr => {
-                let rel_expr_children = ast.get_children(node);
-                let operator = ast
-                    .get_node_value(rel_expr_children[1])
-                    .get_atomic_concept()
-                    .get_value();
-                let left_operand_type =
-                    refer_type_on_node(rel_expr_children[0], ast, scope, table_container)?;
-                let right_operand_type =
-                    refer_type_on_node(rel_expr_children[2], ast, scope, table_container)?;
-                if !left_operand_type.eq("float") && !left_operand_type.eq("integer") {
-                    return Err(SemanticError::report_error(&format!(
-                        "real operator applied on {left_operand_type}, which is not a number"
-                    )));
-                }
-                if !right_operand_type.eq("float") && !right_operand_type.eq("integer") {
-                    return Err(SemanticError::report_error(&format!(
-                        "real operator applied on {right_operand_type}, which is not a number"
+            ExprKind::Id(id) => {
+                // left hand side is an id
+                let caller_name = &id.name;
+                let caller_type = refer_type_on_expr(callee, scope, table_container)?;
+                if !caller_type.contains(':') {
+                    return Err(SemanticError::error(&format!(
+                        "{} is not a function",
+                        caller_name
                     )));
                 }
-                if !left_operand_type.eq(&right_operand_type) {
-                    return Err(SemanticError::report_error(&format!(
-                        "two operands of operator {} have different types",
-                        operator
-                    )));
+                let caller_type_vec: Vec<&str> = caller_type.split(':').collect();
+                let params_type = refer_type_on_args(args, scope, table_container)?;
+                if params_type.eq(caller_type_vec[1]) {
+                    Ok(caller_type_vec[0].to_string())
+                } else {
+                    Err(SemanticError::error(&format!(
+                        "function {} should be called on parameter {}. Parameter {} is found",
+                        caller_name, caller_type_vec[1], params_type
+                    )))
                 }
-                Ok("bool".to_string())
             }
-            CompositeConcept::AddExpr => {
-                let add_expr_children = ast.get_children(node);
-                let operator = ast
-                    .get_node_value(add_expr_children[1])
-                    .get_atomic_concept()
-                    .get_value();
-                let left_operand_type =
-                    refer_type_on_node(add_expr_children[0], ast, scope, table_container)?;
-                let right_operand_type =
-                    refer_type_on_node(add_expr_children[2], ast, scope, table_container)?;
-                match operator.as_str() {
-                    "+" | "-" => {
-                        if !left_operand_type.eq("float") && !left_operand_type.eq("integer") {
-                            return Err(SemanticError::report_error(&format!(
-                                "add operator applied on {left_operand_type}, which is not a number"
-                            )));
-                        }
-                        if !right_operand_type.eq("float") && !right_operand_type.eq("integer") {
-                            return Err(SemanticError::report_error(&format!(
-                                "add operator applied on {right_operand_type}, which is not a number"
+            _ => Ok("".to_string()),
+        },
+        ExprKind::Binary { op, lhs, rhs } => {
+            let left_operand_type = refer_type_on_expr(lhs, scope, table_container)?;
+            let right_operand_type = refer_type_on_expr(rhs, scope, table_container)?;
+            let is_number =
+                |operand_type: &str| operand_type.eq("float") || operand_type.eq("integer");
+            match op {
+                BinaryOp::Eq
+                | BinaryOp::NotEq
+                | BinaryOp::Lt
+                | BinaryOp::Gt
+                | BinaryOp::Leq
+                | BinaryOp::Geq => {
+                    for operand_type in [&left_operand_type, &right_operand_type] {
+                        if !is_number(operand_type) {
+                            return Err(SemanticError::error(&format!(
+                                "real operator applied on {operand_type}, which is not a number"
                             )));
                         }
-                        // if either one operand is float, return float
-                        return if left_operand_type.eq("float") && right_operand_type.eq("float") {
-                            Ok("float".to_string())
-                        } else if left_operand_type.eq("integer")
-                            && right_operand_type.eq("integer")
-                        {
-                            Ok("integer".to_string())
-                        } else {
-                            Err(SemanticError::report_error(&format!(
-                                "Two operands of operator {} have different types",
-                                operator
-                            )))
-                        };
                     }
-                    "or" => {
-                        return if left_operand_type.eq("bool")
-                            && left_operand_type.eq(&right_operand_type)
-                        {
-                            Ok("bool".to_string())
-                        } else {
-                            Err(SemanticError::report_error(
-                                "\"or\" can only be applied on bool",
-                            ))
-                        }
+                    if !left_operand_type.eq(&right_operand_type) {
+                        return Err(SemanticError::error(&format!(
+                            "two operands of operator {} have different types",
+                            op
+                        )));
                     }
-                    _ => panic!("Unexpected add operator {operator}"),
+                    Ok("bool".to_string())
                 }
-            }
-            CompositeConcept::MultExpr => {
-                let mult_expr_children = ast.get_children(node);
-                let operator = ast
-                    .get_node_value(mult_expr_children[1])
-                    .get_atomic_concept()
-                    .get_value();
-                let left_operand_type =
-                    refer_type_on_node(mult_expr_children[0], ast, scope, table_container)?;
-                let right_operand_type =
-                    refer_type_on_node(mult_expr_children[2], ast, scope, table_container)?;
-                match operator.as_str() {
-                    "*" | "/" => {
-                        if !left_operand_type.eq("float") && !left_operand_type.eq("integer") {
-                            return Err(SemanticError::report_error(&format!(
-                                "mult operator applied on {left_operand_type}, which is not a number"
-                            )));
-                        }
-                        if !right_operand_type.eq("float") && !right_operand_type.eq("integer") {
-                            return Err(SemanticError::report_error(&format!(
-                                "mult operator applied on {right_operand_type}, which is not a number"
+                BinaryOp::Add | BinaryOp::Sub | BinaryOp::Mul | BinaryOp::Div => {
+                    if is_negation(expr) && !is_number(&right_operand_type) {
+                        return Err(SemanticError::error(&format!(
+                            "sign can only be applied on numbers, but {right_operand_type} is found."
+                        )));
+                    }
+                    let operator_kind = if matches!(op, BinaryOp::Add | BinaryOp::Sub) {
+                        "add"
+                    } else {
+                        "mult"
+                    };
+                    for operand_type in [&left_operand_type, &right_operand_type] {
+                        if !is_number(operand_type) {
+                            return Err(SemanticError::error(&format!(
+                                "{operator_kind} operator applied on {operand_type}, which is not a number"
                             )));
                         }
-                        // if either one operand is float, return float
-                        return if left_operand_type.eq("float") && right_operand_type.eq("float") {
-                            Ok("float".to_string())
-                        } else if left_operand_type.eq("integer")
-                            && right_operand_type.eq("integer")
-                        {
-                            Ok("integer".to_string())
-                        } else {
-                            Err(SemanticError::report_error(&format!(
-                                "Two operands of operator {} have different types",
-                                operator
-                            )))
-                        };
                     }
-                    "and" => {
-                        return if left_operand_type.eq("bool")
-                            && left_operand_type.eq(&right_operand_type)
-                        {
-                            Ok("bool".to_string())
-                        } else {
-                            Err(SemanticError::report_error(
-                                "\"and\" can only be applied on bool",
-                            ))
-                        }
+                    if left_operand_type.eq(&right_operand_type) {
+                        Ok(left_operand_type)
+                    } else if is_negation(expr) {
+                        Ok(right_operand_type)
+                    } else {
+                        Err(SemanticError::error(&format!(
+                            "Two operands of operator {} have different types",
+                            op
+                        )))
                     }
-                    _ => panic!("Unexpected mult operator {operator}"),
                 }
-            }
-            CompositeConcept::NotExpr => {
-                let not_expr_children = ast.get_children(node);
-                let not_expr_type =
-                    refer_type_on_node(not_expr_children[0], ast, scope, table_container)?;
-                if not_expr_type.eq("bool") {
-                    Ok("bool".to_string())
-                } else {
-                    Err(SemanticError::report_error(&format!(
-                        "\"not\" can only be applied on bool, but {not_expr_type} is found."
-                    )))
+                BinaryOp::And | BinaryOp::Or => {
+                    if left_operand_type.eq("bool") && right_operand_type.eq("bool") {
+                        Ok("bool".to_string())
+                    } else {
+                        Err(SemanticError::error(&format!(
+                            "\"{}\" can only be applied on bool, but {} and {} are found",
+                            op, left_operand_type, right_operand_type
+                        )))
+                    }
                 }
             }
-            CompositeConcept::SignedExpr => {
-                let signed_expr_children = ast.get_children(node);
-                let signed_expr_type =
-                    refer_type_on_node(signed_expr_children[1], ast, scope, table_container)?;
-                if signed_expr_type.eq("integer") || signed_expr_type.eq("float") {
-                    Ok(signed_expr_type)
-                } else {
-                    Err(SemanticError::report_error(&format!(
-                        "sign can only be applied on bool, but {signed_expr_type} is found."
-                    )))
-                }
+        }
+        ExprKind::Unary {
+            op: UnaryOp::Not,
+            operand,
+        } => {
+            let not_expr_type = refer_type_on_expr(operand, scope, table_container)?;
+            if not_expr_type.eq("bool") {
+                Ok("bool".to_string())
+            } else {
+                Err(SemanticError::error(&format!(
+                    "\"not\" can only be applied on bool, but {not_expr_type} is found."
+                )))
             }
-            CompositeConcept::IfThenElse => {
-                let if_then_else_children = ast.get_children(node);
-                let condition_type =
-                    refer_type_on_node(if_then_else_children[0], ast, scope, table_container)?;
-                if !condition_type.eq("bool") {
-                    return Err(SemanticError::report_error(&format!(
-                        "If condition should be bool, but {} is found",
-                        condition_type
-                    )));
-                }
-                let then_type =
-                    refer_type_on_node(if_then_else_children[1], ast, scope, table_container)?;
-                let else_type =
-                    refer_type_on_node(if_then_else_children[2], ast, scope, table_container)?;
-                return if then_type.eq(&else_type) {
-                    Ok(then_type)
-                } else {
-                    Ok("".to_string())
-                };
-            }
-            CompositeConcept::While => {
-                let while_children = ast.get_children(node);
-                let condition_type =
-                    refer_type_on_node(while_children[0], ast, scope, table_container)?;
-                if !condition_type.eq("bool") {
-                    return Err(SemanticError::report_error(&format!(
-                        "while condition should be bool, but {} is found",
-                        condition_type
-                    )));
-                }
-                refer_type_on_node(while_children[1], ast, scope, table_container)
-            }
-            CompositeConcept::Return => {
-                let return_children = ast.get_children(node);
-                let return_type =
-                    refer_type_on_node(return_children[0], ast, scope, table_container)?;
-                Ok(return_type)
-            }
-            CompositeConcept::StmtBlock => {
-                let statements = ast.get_children(node);
-                let mut stmt_type: String = "".to_string();
-                for statement in statements {
-                    stmt_type = refer_type_on_node(statement.clone(), ast, scope, table_container)?;
-                }
-                Ok(stmt_type)
-            }
-            CompositeConcept::AParams => {
-                let a_params = ast.get_children(node);
-                let mut result = String::from("");
-                for node in a_params {
-                    let node_type = refer_type_on_node(node.clone(), ast, scope, table_container)?;
-                    result.push_str(&node_type);
-                    result.push_str(",");
-                }
-                if result.len() > 0 {
-                    return Ok(result[0..result.len() - 1].to_string());
-                } else {
-                    return Ok("".to_string());
-                }
+        }
+        ExprKind::Unary { operand, .. } => {
+            let signed_expr_type = refer_type_on_expr(operand, scope, table_container)?;
+            if signed_expr_type.eq("integer") || signed_expr_type.eq("float") {
+                Ok(signed_expr_type)
+            } else {
+                Err(SemanticError::error(&format!(
+                    "sign can only be applied on bool, but {signed_expr_type} is found."
+                )))
             }
-            CompositeConcept::FuncDef => {
-                // get the table belong to this funcDef
-                let func_def_children = ast.get_children(node);
-                let func_name = ast
-                    .get_node_value(func_def_children[0])
-                    .get_atomic_concept()
-                    .get_value();
-                let this_table = table_container
-                    .get(&*format!("{}:{}", &scope, &func_name))
-                    .expect(&format!("cannot get table {}:{}", &scope, &func_name));
-
-                // check statements in func body and return type matches
-
-                let body_return_type = refer_type_on_node(
-                    func_def_children[3], // funcBody
-                    ast,
-                    &this_table.get_table_name(),
-                    table_container,
-                )?;
-                let defined_return_type = refer_type_on_node(
-                    func_def_children[2],
-                    ast,
-                    &this_table.get_table_name(),
-                    table_container,
-                )?;
-                return if body_return_type.eq("") && !defined_return_type.eq("void") {
-                    Err(SemanticError::report_error(&format!(
-                        "function {} doesn't contain return statement of type {}",
-                        &this_table.get_table_name(),
-                        &defined_return_type
-                    )))
-                } else if body_return_type.eq("integer") && defined_return_type.eq("float") {
-                    // auto-cast integer to float
-                    Ok("float".to_string())
-                } else if !body_return_type.eq("") && !body_return_type.eq(&defined_return_type) {
-                    Err(SemanticError::report_error(&format!(
-                        "function {} should return {} but {} is returned",
-                        &this_table.get_table_name(),
-                        &defined_return_type,
-                        &body_return_type
-                    )))
-                } else {
-                    Ok(body_return_type)
-                };
-            }
-            // CompositeConcept::VarDecl => Ok("".to_string)
-            CompositeConcept::FuncBody => {
-                let func_body_children = ast.get_children(node);
-                let mut return_type = String::from("");
-                for stmt in func_body_children {
-                    let stmt_concept = ast.get_node_value(stmt.clone());
-                    if matches!(
-                        stmt_concept,
-                        Concept::CompositeConcept(CompositeConcept::Return)
-                    ) {
-                        match refer_type_on_node(stmt, ast, scope, table_container) {
-                            Ok(t) => return_type = t,
-                            Err(e) => return Err(e),
-                        }
-                    } else {
-                        if let Err(e) = refer_type_on_node(stmt, ast, scope, table_container) {
-                            return Err(e);
-                        }
-                    }
-                }
-                Ok(return_type)
+        }
+        ExprKind::Error => Ok("".to_string()),
+    }
+}
+
+/// The types of the arguments of a call, separated by commas
+fn refer_type_on_args(
+    args: &[Expr],
+    scope: &str,
+    table_container: &HashMap<String, SymbolTable>,
+) -> Result<String, SemanticError> {
+    let mut arg_types = vec![];
+    for arg in args {
+        arg_types.push(refer_type_on_expr(arg, scope, table_container)?);
+    }
+    Ok(arg_types.join(","))
+}
+
+fn refer_type_on_id(
+    name: &str,
+    scope: &str,
+    table_container: &HashMap<String, SymbolTable>,
+) -> Result<String, SemanticError> {
+    match find_entry(name, scope, table_container) {
+        Some(entry) => Ok(entry.symbol_type.get_name()),
+        // still not found in the global scope
+        None => Err(SemanticError::error(&format!(
+            "{} referred is undeclared",
+            name
+        ))),
+    }
+}
+
+/// The table of the struct a "." operator is applied on
+fn class_table_name(
+    class_type: &str,
+    table_container: &HashMap<String, SymbolTable>,
+) -> Result<String, SemanticError> {
+    let global_table = table_container.get("global").unwrap();
+    match global_table.get_all_entries_by_name(class_type).first() {
+        Some(class_entry) => Ok(class_entry.link.clone().unwrap()),
+        // check caller is a defined class
+        None => Err(SemanticError::error(&format!(
+            "Type of caller of a \".\" operator should be a class. {} is found",
+            class_type
+        ))),
+    }
+}
+
+/// The variable an indexed one is based on, and its indices from the outermost
+fn var_parts(expr: &Expr) -> (&Expr, Vec<&Expr>) {
+    let mut base = expr;
+    let mut indices = vec![];
+    while let ExprKind::Var {
+        base: inner,
+        indices: inner_indices,
+    } = &base.kind
+    {
+        indices.splice(0..0, inner_indices.iter());
+        base = inner;
+    }
+    (base, indices)
+}
+
+/// The entry of a `const` declaration, with its initializer folded
+fn constant_entry(
+    const_decl: &ConstDecl,
+    table: &SymbolTable,
+    errors: &mut Vec<SemanticError>,
+) -> Option<SymbolTableEntry> {
+    let name = const_decl.name.name.clone();
+    let symbol_type = SymbolType::from_type_spec(&const_decl.const_type);
+    let value = match constant_folding::fold(&const_decl.value, table) {
+        Ok(value) => value,
+        Err(e) => {
+            errors.push(e);
+            return None;
+        }
+    };
+    let value_type = value.type_name();
+    match value.convert_to(&symbol_type.get_name()) {
+        Some(value) => Some(SymbolTableEntry {
+            name,
+            kind: SymbolKind::Constant(value),
+            symbol_type,
+            link: None,
+        }),
+        None => {
+            errors.push(SemanticError::error(&format!(
+                "constant {} of type {} is initialized with {}",
+                name,
+                symbol_type.get_name(),
+                value_type
+            )));
+            None
+        }
+    }
+}
+
+/// Type the variables declared without one after their initializers, in the order they
+/// are declared so that an initializer can use the variables inferred before it
+fn infer_var_types(
+    program: &Program,
+    table_container: &mut HashMap<String, SymbolTable>,
+    errors: &mut Vec<SemanticError>,
+) {
+    for item in program.items.iter() {
+        match item {
+            Item::Var(var_decl) => infer_var_type(var_decl, "global", table_container, errors),
+            Item::Func(func_def) => {
+                let scope = format!("global:{}", func_def.name.name);
+                infer_func_var_types(func_def, &scope, table_container, errors);
             }
-            CompositeConcept::Prog => {
-                // for each child (funcDef, implDef, structDecl), check type
-                for child in ast.get_children(node) {
-                    refer_type_on_node(child, ast, "global", table_container)?;
+            Item::Impl(impl_def) => {
+                let struct_scope = format!("global:{}", impl_def.name.name);
+                if !table_container.contains_key(&struct_scope) {
+                    continue;
+                }
+                for func_def in impl_def.funcs.iter() {
+                    let scope = format!("{}:{}", struct_scope, func_def.name.name);
+                    infer_func_var_types(func_def, &scope, table_container, errors);
                 }
-                Ok("".to_string())
             }
-            CompositeConcept::Write => Ok("".to_string()),
-            CompositeConcept::Read => Ok("".to_string()),
-            CompositeConcept::ArraySizes => {
-                let mut array_sizes_type = String::from("");
-                for child in ast.get_children(node) {
-                    let int_lit_type =
-                        refer_type_on_node(child.clone(), ast, "global", table_container)?;
-                    if !int_lit_type.eq("integer") {
-                        return Err(SemanticError::report_error("array size must be integer"));
+            Item::Struct(struct_decl) => {
+                let scope = format!("global:{}", struct_decl.name.name);
+                for member_decl in struct_decl.members.iter() {
+                    if let Member::Var(var_decl) = &member_decl.member {
+                        infer_var_type(var_decl, &scope, table_container, errors);
                     }
-                    let int_lit_value = ast.get_node_value(child).get_atomic_concept().get_value();
-                    array_sizes_type.push('[');
-                    array_sizes_type.push_str(&int_lit_value);
-                    array_sizes_type.push(']');
                 }
-                Ok(array_sizes_type)
-            }
-            CompositeConcept::StructDecl => {
-                let struct_children = ast.get_children(node);
-                let struct_name = ast
-                    .get_node_value(struct_children[0])
-                    .get_atomic_concept()
-                    .get_value();
-                refer_type_on_node(
-                    struct_children[2],
-                    ast,
-                    &format!("{}:{}", scope, struct_name),
-                    table_container,
-                )
-            }
-
-            CompositeConcept::ImplDef => {
-                let impl_children = ast.get_children(node);
-                let impl_name = ast
-                    .get_node_value(impl_children[0])
-                    .get_atomic_concept()
-                    .get_value();
-                refer_type_on_node(
-                    impl_children[1],
-                    ast,
-                    &format!("{}:{}", scope, impl_name),
-                    table_container,
-                )
             }
+            Item::Const(_) | Item::Error(_) => {}
+        }
+    }
+}
+
+fn infer_func_var_types(
+    func_def: &FuncDef,
+    scope: &str,
+    table_container: &mut HashMap<String, SymbolTable>,
+    errors: &mut Vec<SemanticError>,
+) {
+    for stmt in func_def.body.iter() {
+        if let StmtKind::VarDecl(var_decl) = &stmt.kind {
+            infer_var_type(var_decl, scope, table_container, errors);
+        }
+    }
+}
+
+fn infer_var_type(
+    var_decl: &VarDecl,
+    scope: &str,
+    table_container: &mut HashMap<String, SymbolTable>,
+    errors: &mut Vec<SemanticError>,
+) {
+    if var_decl.var_type.base != BaseType::Inferred || !table_container.contains_key(scope) {
+        return;
+    }
+    let name = &var_decl.name.name;
+    let initializer = match &var_decl.initializer {
+        Some(Initializer::Expr(initializer)) => initializer,
+        Some(Initializer::List(..)) => {
+            errors.push(SemanticError::error(&format!(
+                "type of {} cannot be inferred from an initializer list",
+                name
+            )));
+            return;
+        }
+        None => return,
+    };
+    let inferred_type = match refer_type_on_expr(initializer, scope, table_container) {
+        Ok(inferred_type) => inferred_type,
+        Err(e) => {
+            errors.push(e);
+            return;
+        }
+    };
+    if inferred_type.is_empty() || inferred_type.eq("void") {
+        errors.push(SemanticError::error(&format!(
+            "type of {} cannot be inferred from an initializer of type void",
+            name
+        )));
+    } else if inferred_type.contains(':') {
+        errors.push(SemanticError::error(&format!(
+            "type of {} cannot be inferred from function {}, which may be overloaded",
+            name, inferred_type
+        )));
+    } else {
+        table_container
+            .get_mut(scope)
+            .unwrap()
+            .set_inferred_type(name, inferred_type);
+    }
+}
 
-            CompositeConcept::FuncDefList
-            | CompositeConcept::StructMemberDecl
-            | CompositeConcept::StructMemberDeclList => {
-                for child in ast.get_children(node) {
-                    refer_type_on_node(child, ast, scope, table_container)?;
+/// Check the initializer of a variable, or an element of its initializer list, against
+/// its type and the array sizes left. Globals are initialized in static data, so their
+/// initializers must be constant expressions.
+fn check_initializer(
+    name: &str,
+    element_type: &str,
+    dimensions: &[Option<usize>],
+    initializer: &Initializer,
+    scope: &str,
+    table_container: &HashMap<String, SymbolTable>,
+    errors: &mut Vec<SemanticError>,
+) -> Result<(), SemanticError> {
+    match initializer {
+        Initializer::List(elements, _) => {
+            // integer[3][2] is initialized with 3 lists of 2 integers
+            let (size, dimensions) = match dimensions.split_first() {
+                Some(split) => split,
+                None => {
+                    return Err(SemanticError::error(&format!(
+                        "{} of type {} is initialized with a list",
+                        name, element_type
+                    )))
                 }
-                Ok("".to_string())
+            };
+            if *size != Some(elements.len()) {
+                errors.push(SemanticError::error(&format!(
+                    "array {} of size [{}] is initialized with {} elements",
+                    name,
+                    size.map(|size| size.to_string()).unwrap_or_default(),
+                    elements.len()
+                )));
             }
-            CompositeConcept::FParam => Ok("".to_string()),
-            CompositeConcept::Type => {
-                let type_children = ast.get_children(node);
-                refer_type_on_node(type_children[0], ast, scope, table_container)
+            for element in elements {
+                check_initializer(
+                    name,
+                    element_type,
+                    dimensions,
+                    element,
+                    scope,
+                    table_container,
+                    errors,
+                )?;
             }
-            CompositeConcept::FParams => Ok("".to_string()),
-            CompositeConcept::VarDecl => Ok("".to_string()),
-            CompositeConcept::FuncDecl => Ok("".to_string()),
-            CompositeConcept::InheritsList => Ok("".to_string()),
-        },
+        }
+        Initializer::Expr(initializer) => {
+            let var_type = format!("{}{}", element_type, "[integer]".repeat(dimensions.len()));
+            let initializer_type = refer_type_on_expr(initializer, scope, table_container)?;
+            if !var_type.eq(&initializer_type) {
+                errors.push(SemanticError::error(&format!(
+                    "{} of type {} is initialized with {}",
+                    name, var_type, initializer_type
+                )));
+            } else if scope.eq("global") {
+                constant_folding::fold(initializer, table_container.get(scope).unwrap())?;
+            }
+        }
     }
+    Ok(())
 }
 
-fn check_func_def(table_container: &HashMap<String, SymbolTable>) {
+/// The entry a name refers to in the scope or its enclosing ones
+fn find_entry(
+    name: &str,
+    scope: &str,
+    table_container: &HashMap<String, SymbolTable>,
+) -> Option<SymbolTableEntry> {
+    let mut scope = scope.to_string();
+    loop {
+        if let Some(entry) = table_container
+            .get(&scope)
+            .and_then(|table| table.get_all_entries_by_name(name).into_iter().next())
+        {
+            return Some(entry);
+        }
+        match scope.rfind(':') {
+            Some(position) => scope.truncate(position),
+            None => return None,
+        }
+    }
+}
+
+/// Report a constant written by an assignment or a `read`
+fn check_not_constant(
+    target: &Expr,
+    scope: &str,
+    table_container: &HashMap<String, SymbolTable>,
+) -> Result<(), SemanticError> {
+    let (id, _) = var_parts(target);
+    if let ExprKind::Id(id) = &id.kind {
+        if let Some(SymbolTableEntry {
+            kind: SymbolKind::Constant(_),
+            ..
+        }) = find_entry(&id.name, scope, table_container)
+        {
+            return Err(SemanticError::error(&format!(
+                "constant {} cannot be assigned",
+                id.name
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Whether the subtree has an `Error` placeholder left by a syntax error
+pub(crate) fn contains_error(ast: &AbstractSyntaxTree, node: NodeId) -> bool {
+    ast.pre_order(node)
+        .any(|id| ast.get_node_value(id).is_error())
+}
+
+/// The variable declarations of the `for` loops in a function body, nested ones included
+#[derive(Default)]
+struct LoopVarCollector<'ast> {
+    var_decls: Vec<&'ast VarDecl>,
+}
+
+impl<'ast> Visitor<'ast> for LoopVarCollector<'ast> {
+    fn visit_stmt(&mut self, stmt: &'ast Stmt) {
+        if let StmtKind::For { variable, .. } = &stmt.kind {
+            self.var_decls.push(variable);
+        }
+        walk_stmt(self, stmt);
+    }
+}
+
+fn check_func_def(table_container: &HashMap<String, SymbolTable>, errors: &mut Vec<SemanticError>) {
     // check main function is defined
     let global_table = table_container.get("global").unwrap();
     if global_table.get_all_entries_by_name("main").is_empty() {
-        SemanticError::report_error(&format!("main function is not defined"));
+        errors.push(SemanticError::error("main function is not defined"));
     }
     // for each table, each function should have link
     for (table_name, table) in table_container {
         for entry in table.get_all_entries() {
-            if matches!(entry.kind, SymbolKind::Function) {
-                if entry.link.is_none() {
-                    SemanticError::report_error(&format!(
-                        "function {}:{} is declared but not defined",
-                        table_name, entry.name
-                    ));
-                }
+            if matches!(entry.kind, SymbolKind::Function) && entry.link.is_none() {
+                errors.push(SemanticError::error(&format!(
+                    "function {}:{} is declared but not defined",
+                    table_name, entry.name
+                )));
             }
         }
     }
@@ -839,3 +1087,181 @@ fn search_inherited_class_from_member(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::semantic::constant_folding::ConstantValue;
+    use crate::semantic::desugar::desugar;
+    use crate::semantic::typed_ast::lower;
+    use crate::syntactic::parser::tests::tokens_of;
+    use crate::syntactic::parser::Parser;
+
+    /// The symbol tables of a program free of syntax errors, and its semantic errors
+    fn check(source: &str) -> (HashMap<String, SymbolTable>, Vec<String>) {
+        let (ast, spans) = match Parser::new().parse(tokens_of(source)) {
+            Ok((_, ast, spans, errors)) if errors.is_empty() => (ast, spans),
+            _ => panic!("syntax errors in {}", source),
+        };
+        let (tables, errors) = generate_symbol_tables(&desugar(&lower(&ast, &spans)));
+        (tables, errors.iter().map(|e| e.to_string()).collect())
+    }
+
+    fn errors_of(source: &str) -> Vec<String> {
+        check(source).1
+    }
+
+    #[test]
+    fn reports_break_and_continue_outside_of_loops() {
+        assert_eq!(
+            errors_of("func main() -> void { break; }"),
+            ["semantic error: break statement outside of a loop"]
+        );
+        assert_eq!(
+            errors_of("func main() -> void { if (true) then { continue; } else ; }"),
+            ["semantic error: continue statement outside of a loop"]
+        );
+        assert!(errors_of(
+            "func main() -> void {
+               while (true) { if (true) then { break; } else { continue; }; };
+               for (let i: integer = 0; i < 3; i = i + 1) { continue; break; };
+             }"
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn reports_conditions_that_are_not_bool() {
+        assert_eq!(
+            errors_of("func main() -> void { if (1) then { } else ; }"),
+            ["semantic error: If condition should be bool, but integer is found"]
+        );
+        assert_eq!(
+            errors_of("func main() -> void { let x: float; while (x) { }; }"),
+            ["semantic error: while condition should be bool, but float is found"]
+        );
+        assert_eq!(
+            errors_of("func main() -> void { for (let i: integer = 0; i; i = i + 1) ; }"),
+            ["semantic error: for condition should be bool, but integer is found"]
+        );
+        assert!(errors_of(
+            "func main() -> void {
+               let i: integer;
+               if (i < 2 & !(i == 0)) then { } else ;
+               while (i <> 3 | false) { i = i + 1; };
+             }"
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn reports_constants_written_to() {
+        assert_eq!(
+            errors_of("const C: integer = 1; func main() -> void { C = 2; }"),
+            ["semantic error: constant C cannot be assigned"]
+        );
+        assert_eq!(
+            errors_of("const C: integer = 1; func main() -> void { read(C); }"),
+            ["semantic error: constant C cannot be assigned"]
+        );
+    }
+
+    #[test]
+    fn folds_constants_in_words_of_32_bits() {
+        let (tables, errors) = check(
+            "const N: integer = 2 * 3 + 1;
+             const MIN: integer = -2147483647 - 1;
+             const F: float = N;
+             func main() -> void { }",
+        );
+        assert!(errors.is_empty(), "{:?}", errors);
+        let value = |name: &str| {
+            tables["global"].get_all_entries_by_name(name)[0]
+                .kind
+                .clone()
+        };
+        assert_eq!(value("N"), SymbolKind::Constant(ConstantValue::Integer(7)));
+        assert_eq!(
+            value("MIN"),
+            SymbolKind::Constant(ConstantValue::Integer(i32::MIN))
+        );
+        assert_eq!(value("F"), SymbolKind::Constant(ConstantValue::Float(7.0)));
+
+        assert_eq!(
+            errors_of(
+                "const BIG: integer = 2147483647 + 1;
+                 const TOO_LARGE: integer = 2147483648;
+                 func main() -> void { }"
+            ),
+            [
+                "semantic error: overflow in a constant expression",
+                "semantic error: 2147483648 is too large"
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_initializers_of_another_type_or_size() {
+        assert_eq!(
+            errors_of(
+                "let g: integer[2] = {1, 2, 3};
+                 let h: integer = {1};
+                 func main() -> void { }"
+            ),
+            [
+                "semantic error: array g of size [2] is initialized with 3 elements",
+                "semantic error: h of type integer is initialized with a list"
+            ]
+        );
+        assert_eq!(
+            errors_of("let k: integer[2][2] = {{1, 2}, {3, 4.0}}; func main() -> void { }"),
+            ["semantic error: k of type integer is initialized with float"]
+        );
+        assert_eq!(
+            errors_of(
+                "func main() -> void {
+                   let x: integer[2] = {1, 2};
+                   let y: float = x[0];
+                   let z: integer[2] = x;
+                 }"
+            ),
+            ["semantic error: y of type float is initialized with integer"]
+        );
+    }
+
+    #[test]
+    fn infers_types_from_initializers() {
+        let (tables, errors) = check(
+            "let n = 3;
+             func main() -> void { let x = 1.5 * 2.0; let y = x; let z = n < 4; }",
+        );
+        assert!(errors.is_empty(), "{:?}", errors);
+        let type_of = |table: &str, name: &str| {
+            tables[table].get_all_entries_by_name(name)[0]
+                .symbol_type
+                .get_name()
+        };
+        assert_eq!(type_of("global", "n"), "integer");
+        assert_eq!(type_of("global:main", "x"), "float");
+        assert_eq!(type_of("global:main", "y"), "float");
+        assert_eq!(type_of("global:main", "z"), "bool");
+    }
+
+    #[test]
+    fn reports_types_that_cannot_be_inferred() {
+        assert_eq!(
+            errors_of(
+                "func f() -> void { }
+                 func g(a: integer) -> integer { return (a); }
+                 let a = {1, 2};
+                 func main() -> void { let b = f(); let c = g; }"
+            ),
+            [
+                "semantic error: type of a cannot be inferred from an initializer list",
+                "semantic error: type of b cannot be inferred from an initializer of type void",
+                "semantic error: type of c cannot be inferred from function integer:integer, \
+                 which may be overloaded"
+            ]
+        );
+    }
+}
@@ -1,5 +1,6 @@
 use crate::lexical::token::{Token, TokenType, ValidTokenType};
 use std::fmt::{Display, Formatter};
+use std::str::FromStr;
 
 #[derive(PartialEq)]
 pub enum Concept {
@@ -32,18 +33,30 @@ impl Concept {
                     atomic_concept_type: AtomicConceptType::RelOp,
                     value: token.lexeme,
                 })),
-                ValidTokenType::Plus | ValidTokenType::Minus | ValidTokenType::Or => {
+                ValidTokenType::Plus | ValidTokenType::Minus => {
                     Ok(Concept::AtomicConcept(AtomicConcept {
                         atomic_concept_type: AtomicConceptType::AddOp,
                         value: token.lexeme,
                     }))
                 }
-                ValidTokenType::Mult | ValidTokenType::Div | ValidTokenType::And => {
+                ValidTokenType::Mult | ValidTokenType::Div => {
                     Ok(Concept::AtomicConcept(AtomicConcept {
                         atomic_concept_type: AtomicConceptType::MultiOp,
                         value: token.lexeme,
                     }))
                 }
+                ValidTokenType::And | ValidTokenType::Or => {
+                    Ok(Concept::AtomicConcept(AtomicConcept {
+                        atomic_concept_type: AtomicConceptType::LogicOp,
+                        value: token.lexeme,
+                    }))
+                }
+                ValidTokenType::KwTrue | ValidTokenType::KwFalse => {
+                    Ok(Concept::AtomicConcept(AtomicConcept {
+                        atomic_concept_type: AtomicConceptType::BoolLit,
+                        value: token.lexeme,
+                    }))
+                }
                 ValidTokenType::KwVoid => Ok(Concept::AtomicConcept(AtomicConcept {
                     atomic_concept_type: AtomicConceptType::Void,
                     value: token.lexeme,
@@ -56,6 +69,10 @@ impl Concept {
                     atomic_concept_type: AtomicConceptType::Integer,
                     value: token.lexeme,
                 })),
+                ValidTokenType::KwBool => Ok(Concept::AtomicConcept(AtomicConcept {
+                    atomic_concept_type: AtomicConceptType::Bool,
+                    value: token.lexeme,
+                })),
                 ValidTokenType::KwPublic | ValidTokenType::KwPrivate => {
                     Ok(Concept::AtomicConcept(AtomicConcept {
                         atomic_concept_type: AtomicConceptType::Visibility,
@@ -68,17 +85,14 @@ impl Concept {
         }
     }
 
-    pub fn create_sign(token: Token) -> Result<Self, ()> {
+    pub(crate) fn create_sign(token: Token) -> Result<Self, ()> {
         match token.token_type {
-            TokenType::ValidTokenType(valid_token_type) => match valid_token_type {
-                ValidTokenType::Plus | ValidTokenType::Minus => {
-                    Ok(Concept::AtomicConcept(AtomicConcept {
-                        atomic_concept_type: AtomicConceptType::Sign,
-                        value: token.lexeme,
-                    }))
-                }
-                _ => Err(()),
-            },
+            TokenType::ValidTokenType(ValidTokenType::Plus | ValidTokenType::Minus) => {
+                Ok(Concept::AtomicConcept(AtomicConcept {
+                    atomic_concept_type: AtomicConceptType::Sign,
+                    value: token.lexeme,
+                }))
+            }
             _ => Err(()),
         }
     }
@@ -90,6 +104,23 @@ impl Concept {
         })
     }
 
+    /// Placeholder for a concept the parser could not build, with what was expected
+    pub fn create_error(expected: &str) -> Self {
+        Concept::AtomicConcept(AtomicConcept {
+            atomic_concept_type: AtomicConceptType::Error,
+            value: expected.to_string(),
+        })
+    }
+
+    pub fn is_error(&self) -> bool {
+        match self {
+            Concept::AtomicConcept(atomic_concept) => {
+                matches!(atomic_concept.atomic_concept_type, AtomicConceptType::Error)
+            }
+            Concept::CompositeConcept(_) => false,
+        }
+    }
+
     pub fn is_epsilon(&self) -> bool {
         match self {
             Concept::AtomicConcept(atomic_concept) => matches!(
@@ -100,6 +131,16 @@ impl Concept {
         }
     }
 
+    /// The concept type, followed by the value of an atom having one, as in `Id: counter`
+    pub fn label(&self) -> String {
+        match self {
+            Concept::AtomicConcept(ac) if !ac.value.is_empty() => {
+                format!("{:?}: {}", ac.atomic_concept_type, ac.value)
+            }
+            concept => concept.to_string(),
+        }
+    }
+
     pub fn get_composite_concept(&self) -> CompositeConcept {
         match self {
             Concept::AtomicConcept(_) => {
@@ -156,14 +197,53 @@ pub enum AtomicConceptType {
     Float,
     IntLit,
     Integer,
+    BoolLit,
+    Bool,
     Void,
     RelOp,
     MultiOp,
     AddOp,
+    /// `&` or `|`
+    LogicOp,
     Sign,
     Visibility,
     Epsilon,
     EmptyArraySize,
+    /// the type of a `let` without one, inferred from its initializer
+    InferredType,
+    /// the else branch of an `if` without one
+    NoElse,
+    /// placeholder for a concept missing because of a syntax error
+    Error,
+}
+
+impl FromStr for AtomicConceptType {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Id" => Ok(AtomicConceptType::Id),
+            "FloatLit" => Ok(AtomicConceptType::FloatLit),
+            "Float" => Ok(AtomicConceptType::Float),
+            "IntLit" => Ok(AtomicConceptType::IntLit),
+            "Integer" => Ok(AtomicConceptType::Integer),
+            "BoolLit" => Ok(AtomicConceptType::BoolLit),
+            "Bool" => Ok(AtomicConceptType::Bool),
+            "Void" => Ok(AtomicConceptType::Void),
+            "RelOp" => Ok(AtomicConceptType::RelOp),
+            "MultiOp" => Ok(AtomicConceptType::MultiOp),
+            "AddOp" => Ok(AtomicConceptType::AddOp),
+            "LogicOp" => Ok(AtomicConceptType::LogicOp),
+            "Sign" => Ok(AtomicConceptType::Sign),
+            "Visibility" => Ok(AtomicConceptType::Visibility),
+            "Epsilon" => Ok(AtomicConceptType::Epsilon),
+            "EmptyArraySize" => Ok(AtomicConceptType::EmptyArraySize),
+            "InferredType" => Ok(AtomicConceptType::InferredType),
+            "NoElse" => Ok(AtomicConceptType::NoElse),
+            "Error" => Ok(AtomicConceptType::Error),
+            _ => Err(()),
+        }
+    }
 }
 
 #[derive(PartialEq, Debug, Clone)]
@@ -176,12 +256,17 @@ pub enum CompositeConcept {
     RelExpr,
     AddExpr,
     MultExpr,
+    AndExpr,
+    OrExpr,
     NotExpr,
     SignedExpr,
     IfThenElse,
     Read,
     Return,
     While,
+    For,
+    Break,
+    Continue,
     StmtBlock,
     Write,
     AParams,
@@ -191,6 +276,8 @@ pub enum CompositeConcept {
     FParams,
     FuncDef,
     VarDecl,
+    InitList,
+    ConstDecl,
     FuncBody,
     FuncDecl,
     FuncDefList,
@@ -202,6 +289,55 @@ pub enum CompositeConcept {
     Prog,
 }
 
+impl FromStr for CompositeConcept {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Dot" => Ok(CompositeConcept::Dot),
+            "IndexList" => Ok(CompositeConcept::IndexList),
+            "Var" => Ok(CompositeConcept::Var),
+            "Assign" => Ok(CompositeConcept::Assign),
+            "FuncCall" => Ok(CompositeConcept::FuncCall),
+            "RelExpr" => Ok(CompositeConcept::RelExpr),
+            "AddExpr" => Ok(CompositeConcept::AddExpr),
+            "MultExpr" => Ok(CompositeConcept::MultExpr),
+            "AndExpr" => Ok(CompositeConcept::AndExpr),
+            "OrExpr" => Ok(CompositeConcept::OrExpr),
+            "NotExpr" => Ok(CompositeConcept::NotExpr),
+            "SignedExpr" => Ok(CompositeConcept::SignedExpr),
+            "IfThenElse" => Ok(CompositeConcept::IfThenElse),
+            "Read" => Ok(CompositeConcept::Read),
+            "Return" => Ok(CompositeConcept::Return),
+            "While" => Ok(CompositeConcept::While),
+            "For" => Ok(CompositeConcept::For),
+            "Break" => Ok(CompositeConcept::Break),
+            "Continue" => Ok(CompositeConcept::Continue),
+            "StmtBlock" => Ok(CompositeConcept::StmtBlock),
+            "Write" => Ok(CompositeConcept::Write),
+            "AParams" => Ok(CompositeConcept::AParams),
+            "ArraySizes" => Ok(CompositeConcept::ArraySizes),
+            "FParam" => Ok(CompositeConcept::FParam),
+            "Type" => Ok(CompositeConcept::Type),
+            "FParams" => Ok(CompositeConcept::FParams),
+            "FuncDef" => Ok(CompositeConcept::FuncDef),
+            "VarDecl" => Ok(CompositeConcept::VarDecl),
+            "InitList" => Ok(CompositeConcept::InitList),
+            "ConstDecl" => Ok(CompositeConcept::ConstDecl),
+            "FuncBody" => Ok(CompositeConcept::FuncBody),
+            "FuncDecl" => Ok(CompositeConcept::FuncDecl),
+            "FuncDefList" => Ok(CompositeConcept::FuncDefList),
+            "ImplDef" => Ok(CompositeConcept::ImplDef),
+            "StructDecl" => Ok(CompositeConcept::StructDecl),
+            "InheritsList" => Ok(CompositeConcept::InheritsList),
+            "StructMemberDeclList" => Ok(CompositeConcept::StructMemberDeclList),
+            "StructMemberDecl" => Ok(CompositeConcept::StructMemberDecl),
+            "Prog" => Ok(CompositeConcept::Prog),
+            _ => Err(()),
+        }
+    }
+}
+
 impl Display for CompositeConcept {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(f, "{:?}", self)
@@ -0,0 +1,144 @@
+use crate::semantic::semantic_error::SemanticError;
+use crate::semantic::symbol_table::{SymbolKind, SymbolTable};
+use crate::semantic::typed_ast::{Expr, ExprKind, UnaryOp};
+use std::fmt::{Display, Formatter};
+
+/// Value of a constant expression, computed at compile time
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConstantValue {
+    Integer(i32),
+    Float(f64),
+    Bool(bool),
+}
+
+impl ConstantValue {
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            ConstantValue::Integer(_) => "integer",
+            ConstantValue::Float(_) => "float",
+            ConstantValue::Bool(_) => "bool",
+        }
+    }
+
+    /// Convert the value to a constant of the declared type, if it is compatible
+    pub fn convert_to(self, type_name: &str) -> Option<Self> {
+        match (self, type_name) {
+            (ConstantValue::Integer(i), "float") => Some(ConstantValue::Float(i as f64)),
+            (value, type_name) if value.type_name() == type_name => Some(value),
+            _ => None,
+        }
+    }
+}
+
+impl Display for ConstantValue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConstantValue::Integer(i) => write!(f, "{}", i),
+            ConstantValue::Float(x) => write!(f, "{:?}", x),
+            ConstantValue::Bool(b) => write!(f, "{}", b),
+        }
+    }
+}
+
+/// Evaluate a constant expression. It may only use literals, operators and the constants
+/// already in `table`.
+pub fn fold(expr: &Expr, table: &SymbolTable) -> Result<ConstantValue, SemanticError> {
+    match &expr.kind {
+        ExprKind::IntLit(value) => value
+            .parse()
+            .map(ConstantValue::Integer)
+            .map_err(|_| SemanticError::error(&format!("{} is too large", value))),
+        ExprKind::FloatLit(value) => Ok(ConstantValue::Float(value.parse().unwrap())),
+        ExprKind::BoolLit(value) => Ok(ConstantValue::Bool(*value)),
+        ExprKind::Id(id) => {
+            let entries = table.get_all_entries_by_name(&id.name);
+            match entries.first().map(|entry| &entry.kind) {
+                Some(SymbolKind::Constant(value)) => Ok(value.clone()),
+                _ => Err(SemanticError::error(&format!(
+                    "{} is not a constant declared before",
+                    id.name
+                ))),
+            }
+        }
+        ExprKind::Unary { op, operand } => match (op, fold(operand, table)?) {
+            (UnaryOp::Not, ConstantValue::Bool(b)) => Ok(ConstantValue::Bool(!b)),
+            (UnaryOp::Not, value) => Err(SemanticError::error(&format!(
+                "\"not\" can only be applied on bool, but {} is found.",
+                value.type_name()
+            ))),
+            (_, ConstantValue::Bool(_)) => {
+                Err(SemanticError::error("sign can only be applied on numbers"))
+            }
+            (UnaryOp::Minus, ConstantValue::Integer(i)) => i
+                .checked_neg()
+                .map(ConstantValue::Integer)
+                .ok_or_else(overflow),
+            (UnaryOp::Minus, ConstantValue::Float(x)) => Ok(ConstantValue::Float(-x)),
+            (_, value) => Ok(value),
+        },
+        ExprKind::Binary { op, lhs, rhs } => {
+            let left = fold(lhs, table)?;
+            let right = fold(rhs, table)?;
+            fold_binary(op.lexeme(), left, right)
+        }
+        _ => Err(not_constant()),
+    }
+}
+
+fn fold_binary(
+    operator: &str,
+    left: ConstantValue,
+    right: ConstantValue,
+) -> Result<ConstantValue, SemanticError> {
+    use ConstantValue::*;
+    let value = match (operator, &left, &right) {
+        (_, Integer(_), Integer(0)) if operator == "/" => {
+            return Err(SemanticError::error(
+                "division by zero in a constant expression",
+            ))
+        }
+        ("+", Integer(l), Integer(r)) => l.checked_add(*r).map(Integer),
+        ("-", Integer(l), Integer(r)) => l.checked_sub(*r).map(Integer),
+        ("*", Integer(l), Integer(r)) => l.checked_mul(*r).map(Integer),
+        ("/", Integer(l), Integer(r)) => l.checked_div(*r).map(Integer),
+        ("+", Float(l), Float(r)) => Some(Float(l + r)),
+        ("-", Float(l), Float(r)) => Some(Float(l - r)),
+        ("*", Float(l), Float(r)) => Some(Float(l * r)),
+        ("/", Float(l), Float(r)) => Some(Float(l / r)),
+        // `-x` of the core language
+        ("-", Integer(0), Float(r)) => Some(Float(-r)),
+        ("==", l, r) if l.type_name() == r.type_name() => Some(Bool(l == r)),
+        ("<>", l, r) if l.type_name() == r.type_name() => Some(Bool(l != r)),
+        ("<" | ">" | "<=" | ">=", Integer(l), Integer(r)) => Some(Bool(compare(operator, l, r))),
+        ("<" | ">" | "<=" | ">=", Float(l), Float(r)) => Some(Bool(compare(operator, l, r))),
+        ("&", Bool(l), Bool(r)) => Some(Bool(*l && *r)),
+        ("|", Bool(l), Bool(r)) => Some(Bool(*l || *r)),
+        _ => {
+            return Err(SemanticError::error(&format!(
+                "operator {} cannot be applied on {} and {}",
+                operator,
+                left.type_name(),
+                right.type_name()
+            )))
+        }
+    };
+    value.ok_or_else(overflow)
+}
+
+/// Integers are words of the target machine, which has 32 bits
+fn overflow() -> SemanticError {
+    SemanticError::error("overflow in a constant expression")
+}
+
+fn compare<T: PartialOrd>(operator: &str, left: T, right: T) -> bool {
+    match operator {
+        "<" => left < right,
+        ">" => left > right,
+        "<=" => left <= right,
+        _ => left >= right,
+    }
+}
+
+fn not_constant() -> SemanticError {
+    SemanticError::error("initializer of a constant or a global must be a constant expression")
+}
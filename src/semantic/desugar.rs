@@ -0,0 +1,120 @@
+use crate::semantic::typed_ast::{BinaryOp, Expr, ExprKind, Program, UnaryOp};
+use crate::semantic::visit::{fold_expr_children, Fold};
+
+/// Lower the typed AST to a smaller core language:
+/// - `+x` is `x` and `-x` is `0 - x`, so `not` is the only unary operator
+/// - member access chains are `Member`, `Call` and `Var` nodes without empty index lists
+/// - `a[i][j]` is `(a[i])[j]`, every `Var` has exactly one index
+///
+/// The semantic checker and code generation run on the core program. Its nodes keep
+/// the spans of the source they come from, so diagnostics point at the source.
+pub fn desugar(program: &Program) -> Program {
+    Desugar.fold_program(program.clone())
+}
+
+/// Whether the expression is `0 - x`, a negation of the core language, which has the
+/// type of `x` whether it is an integer or a float
+pub fn is_negation(expr: &Expr) -> bool {
+    match &expr.kind {
+        ExprKind::Binary {
+            op: BinaryOp::Sub,
+            lhs,
+            ..
+        } => lhs.kind == ExprKind::IntLit("0".to_string()),
+        _ => false,
+    }
+}
+
+struct Desugar;
+
+impl Fold for Desugar {
+    fn fold_expr(&mut self, expr: Expr) -> Expr {
+        let expr = fold_expr_children(self, expr);
+        let span = expr.span;
+        match expr.kind {
+            ExprKind::Unary {
+                op: UnaryOp::Plus,
+                operand,
+            } => *operand,
+            ExprKind::Unary {
+                op: UnaryOp::Minus,
+                operand,
+            } => Expr {
+                kind: ExprKind::Binary {
+                    op: BinaryOp::Sub,
+                    lhs: Box::new(Expr {
+                        kind: ExprKind::IntLit("0".to_string()),
+                        span,
+                    }),
+                    rhs: operand,
+                },
+                span,
+            },
+            ExprKind::Var { base, indices } => {
+                indices.into_iter().fold(*base, |base, index| Expr {
+                    span: base.span.merge(&index.span),
+                    kind: ExprKind::Var {
+                        base: Box::new(base),
+                        indices: vec![index],
+                    },
+                })
+            }
+            kind => Expr { kind, span },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::semantic::ast::generate_symbol_tables;
+    use crate::semantic::typed_ast::{lower, Item, StmtKind};
+    use crate::syntactic::parser::tests::tokens_of;
+    use crate::syntactic::parser::Parser;
+
+    fn program_of(source: &str) -> Program {
+        let (_, ast, spans, _) = Parser::new().parse(tokens_of(source)).unwrap();
+        desugar(&lower(&ast, &spans))
+    }
+
+    /// The value written by the only statement of `main`
+    fn written(program: &Program) -> &Expr {
+        match &program.items[0] {
+            Item::Func(main) => match &main.body[0].kind {
+                StmtKind::Write(expr) => expr,
+                other => panic!("{:?}", other),
+            },
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn lowers_signs_to_subtractions() {
+        let program = program_of("func main() -> void { write(+-1); }");
+        let expr = written(&program);
+        assert!(is_negation(expr));
+        match &expr.kind {
+            ExprKind::Binary { rhs, .. } => {
+                assert_eq!(rhs.kind, ExprKind::IntLit("1".to_string()))
+            }
+            other => panic!("{:?}", other),
+        }
+        assert!(!is_negation(written(&program_of(
+            "func main() -> void { write(1 - 0); }"
+        ))));
+    }
+
+    #[test]
+    fn negates_integers_and_floats() {
+        let program = program_of(
+            "const F: float = -1.5;
+             func main() -> void {
+               let f: float = -F;
+               let i: integer = -(2 - 3);
+               f = -f;
+             }",
+        );
+        let (_, errors) = generate_symbol_tables(&program);
+        assert!(errors.is_empty(), "{:?}", errors);
+    }
+}
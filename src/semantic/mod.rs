@@ -1,4 +1,11 @@
 pub mod ast;
 pub mod concept;
-mod semantic_error;
+pub mod constant_folding;
+pub mod desugar;
+pub mod pretty_printer;
+pub mod semantic_error;
+pub mod serialization;
+pub mod source_map;
 pub mod symbol_table;
+pub mod typed_ast;
+pub mod visit;
@@ -0,0 +1,766 @@
+use crate::lexical::span::Span;
+use crate::lexical::token::{Token, ValidTokenType};
+use crate::semantic::ast::AbstractSyntaxTree;
+use crate::semantic::typed_ast::{
+    lower, BaseType, BinaryOp, Block, ConstDecl, Expr, ExprKind, FuncDecl, FuncDef, ImplDef,
+    Initializer, Item, Lowering, Member, MemberDecl, Param, Program, Stmt, StmtKind, StructDecl,
+    TypeSpec, VarDecl, Visibility,
+};
+use crate::syntactic::precedence;
+use crate::syntactic::precedence::{Associativity, ARITH_MIN_PRECEDENCE};
+use crate::syntactic::tree::NodeId;
+use std::collections::HashMap;
+use std::str::FromStr;
+
+/// Print the AST back as canonical source. Parsing the output gives the same AST.
+pub fn unparse(ast: &AbstractSyntaxTree) -> String {
+    print_program(&Lowering::new(ast, None).program(ast.get_root()))
+}
+
+pub fn print_program(program: &Program) -> String {
+    let mut printer = PrettyPrinter::new(FormatOptions::default());
+    printer.print_program(program);
+    printer.finish()
+}
+
+/// Format a parsed source file, keeping its comments. `tokens` are all the tokens of
+/// the file, comments included, and `spans` the source ranges of the AST nodes.
+pub fn format(
+    ast: &AbstractSyntaxTree,
+    spans: &HashMap<NodeId, Span>,
+    tokens: &[Token],
+    options: FormatOptions,
+) -> String {
+    let mut printer = PrettyPrinter::new(options);
+    printer.keep_comments(tokens);
+    printer.print_program(&lower(ast, spans));
+    printer.finish()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BraceStyle {
+    /// `if (a) then {`
+    SameLine,
+    /// the opening brace on a line of its own, under the start of the statement
+    NextLine,
+}
+
+impl FromStr for BraceStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "same-line" => Ok(BraceStyle::SameLine),
+            "next-line" => Ok(BraceStyle::NextLine),
+            other => Err(format!(
+                "unknown brace style `{}`, expected one of `same-line`, `next-line`",
+                other
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct FormatOptions {
+    /// spaces per level of indentation
+    pub indent_width: usize,
+    pub brace_style: BraceStyle,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        Self {
+            indent_width: 2,
+            brace_style: BraceStyle::SameLine,
+        }
+    }
+}
+
+/// The comments of the source being formatted, and how far the printer is through it
+struct SourceComments {
+    comments: Vec<Token>,
+    next_comment: usize,
+    /// the tokens other than comments, to find the braces of blocks
+    tokens: Vec<Token>,
+    next_token: usize,
+    /// the closing brace of each opening brace, by the start of the opening brace
+    closing_braces: HashMap<(u32, u32), Span>,
+    /// the end of the source printed so far
+    cursor: (u32, u32),
+}
+
+impl SourceComments {
+    fn new(tokens: &[Token]) -> Self {
+        let (comments, tokens): (Vec<Token>, Vec<Token>) =
+            tokens.iter().cloned().partition(|token| {
+                matches!(
+                    token.get_valid_token_type(),
+                    Some(ValidTokenType::InlineCmt | ValidTokenType::BlockCmt)
+                )
+            });
+        let mut closing_braces = HashMap::new();
+        let mut open_braces = vec![];
+        for token in tokens.iter() {
+            match token.get_valid_token_type() {
+                Some(ValidTokenType::OpenCuBr) => open_braces.push(token.location),
+                Some(ValidTokenType::CloseCuBr) => {
+                    if let Some(open_brace) = open_braces.pop() {
+                        closing_braces.insert(open_brace, token.get_span());
+                    }
+                }
+                _ => {}
+            }
+        }
+        Self {
+            comments,
+            next_comment: 0,
+            tokens,
+            next_token: 0,
+            closing_braces,
+            cursor: (0, 0),
+        }
+    }
+
+    fn advance(&mut self, position: (u32, u32)) {
+        self.cursor = self.cursor.max(position);
+    }
+
+    /// The next token after the cursor that is not of one of the `skipped` types
+    fn next_token(&mut self, skipped: &[ValidTokenType]) -> Option<&Token> {
+        while self.next_token < self.tokens.len()
+            && self.tokens[self.next_token].location < self.cursor
+        {
+            self.next_token += 1;
+        }
+        self.tokens[self.next_token..].iter().find(|token| {
+            !token
+                .get_valid_token_type()
+                .is_some_and(|token_type| skipped.contains(&token_type))
+        })
+    }
+
+    /// The next comment, if it starts before `position`
+    fn next_comment_before(&self, position: (u32, u32)) -> Option<&Token> {
+        self.comments
+            .get(self.next_comment)
+            .filter(|comment| comment.location < position)
+    }
+}
+
+/// Prints the typed AST as source text: one statement per line, blocks indented,
+/// single spaces around binary operators and parentheses only where the precedence of
+/// operators needs them. When formatting a file, comments are printed before the
+/// construct that follows them, or at the end of the line they ended in the source.
+pub struct PrettyPrinter {
+    options: FormatOptions,
+    output: String,
+    depth: usize,
+    source: Option<SourceComments>,
+    /// an empty line of the source may be kept here: not at the start of a block
+    blank_line_allowed: bool,
+}
+
+impl PrettyPrinter {
+    pub fn new(options: FormatOptions) -> Self {
+        Self {
+            options,
+            output: String::new(),
+            depth: 0,
+            source: None,
+            blank_line_allowed: false,
+        }
+    }
+
+    /// Print the comments among `tokens` along with the program, and keep the empty
+    /// lines between statements
+    pub fn keep_comments(&mut self, tokens: &[Token]) {
+        self.source = Some(SourceComments::new(tokens));
+    }
+
+    pub fn finish(self) -> String {
+        self.output
+    }
+
+    /// Items are separated by an empty line, except for consecutive variables and constants
+    pub fn print_program(&mut self, program: &Program) {
+        let mut previous: Option<&Item> = None;
+        for item in program.items.iter() {
+            if let Some(previous) = previous {
+                if !(is_declaration(previous) && is_declaration(item)) {
+                    self.trailing_comments();
+                    self.output.push('\n');
+                    self.blank_line_allowed = false;
+                }
+            }
+            self.print_item(item);
+            previous = Some(item);
+        }
+        self.comments_before((u32::MAX, u32::MAX));
+    }
+
+    pub fn print_item(&mut self, item: &Item) {
+        match item {
+            Item::Struct(struct_decl) => self.print_struct_decl(struct_decl),
+            Item::Impl(impl_def) => self.print_impl_def(impl_def),
+            Item::Func(func_def) => self.print_func_def(func_def),
+            Item::Var(var_decl) => {
+                self.simple_line(format!("{};", var_decl_text(var_decl)), var_decl.span)
+            }
+            Item::Const(const_decl) => {
+                self.simple_line(const_decl_text(const_decl), const_decl.span)
+            }
+            Item::Error(span) => self.simple_line(format!("/* syntax error at {} */", span), *span),
+        }
+    }
+
+    fn print_struct_decl(&mut self, struct_decl: &StructDecl) {
+        self.node_start(struct_decl.span);
+        let mut head = format!("struct {}", struct_decl.name.name);
+        self.advance(struct_decl.name.span.end);
+        if !struct_decl.inherits.is_empty() {
+            let parents: Vec<&str> = struct_decl
+                .inherits
+                .iter()
+                .map(|parent| parent.name.as_str())
+                .collect();
+            head.push_str(&format!(" inherits {}", parents.join(", ")));
+            for parent in struct_decl.inherits.iter() {
+                self.advance(parent.span.end);
+            }
+        }
+        let closing_brace = self.open_brace(&head, &[]);
+        for member_decl in struct_decl.members.iter() {
+            self.print_member_decl(member_decl);
+        }
+        self.close_brace(closing_brace);
+        self.line("};");
+    }
+
+    fn print_member_decl(&mut self, member_decl: &MemberDecl) {
+        let visibility = match member_decl.visibility {
+            Visibility::Public => "public",
+            Visibility::Private => "private",
+        };
+        let member = match &member_decl.member {
+            Member::Var(var_decl) => var_decl_text(var_decl),
+            Member::Func(func_decl) => func_decl_text(func_decl),
+        };
+        self.simple_line(format!("{} {};", visibility, member), member_decl.span);
+    }
+
+    fn print_impl_def(&mut self, impl_def: &ImplDef) {
+        self.node_start(impl_def.span);
+        self.advance(impl_def.name.span.end);
+        let closing_brace = self.open_brace(&format!("impl {}", impl_def.name.name), &[]);
+        for (i, func_def) in impl_def.funcs.iter().enumerate() {
+            if i > 0 {
+                self.trailing_comments();
+                self.output.push('\n');
+                self.blank_line_allowed = false;
+            }
+            self.print_func_def(func_def);
+        }
+        self.close_brace(closing_brace);
+        self.line("}");
+    }
+
+    fn print_func_def(&mut self, func_def: &FuncDef) {
+        self.node_start(func_def.span);
+        let head = func_head_text(&func_def.name.name, &func_def.params, &func_def.return_type);
+        self.advance(func_def.return_type.span.end);
+        let closing_brace = self.open_brace(&head, &[]);
+        for stmt in func_def.body.iter() {
+            self.print_stmt(stmt);
+        }
+        self.close_brace(closing_brace);
+        self.line("}");
+    }
+
+    pub fn print_stmt(&mut self, stmt: &Stmt) {
+        match &stmt.kind {
+            StmtKind::VarDecl(var_decl) => {
+                self.simple_line(format!("{};", var_decl_text(var_decl)), stmt.span)
+            }
+            StmtKind::Assign { .. } | StmtKind::Expr(_) => {
+                self.simple_line(format!("{};", simple_stmt_text(stmt)), stmt.span)
+            }
+            StmtKind::If { .. } => {
+                self.node_start(stmt.span);
+                self.print_if(stmt, "");
+                self.line("};");
+            }
+            StmtKind::While { condition, body } => {
+                self.node_start(stmt.span);
+                self.advance(condition.span.end);
+                let head = format!("while ({})", expr_text(condition));
+                self.print_block(&head, body, &[ValidTokenType::ClosePar]);
+                self.line("};");
+            }
+            StmtKind::For {
+                variable,
+                initial_value,
+                condition,
+                step,
+                body,
+            } => {
+                self.node_start(stmt.span);
+                self.advance(step.span.end);
+                let head = format!(
+                    "for (let {}: {} = {}; {}; {})",
+                    variable.name.name,
+                    variable.var_type,
+                    expr_text(initial_value),
+                    expr_text(condition),
+                    simple_stmt_text(step)
+                );
+                self.print_block(&head, body, &[ValidTokenType::ClosePar]);
+                self.line("};");
+            }
+            StmtKind::Read(variable) => {
+                self.simple_line(format!("read({});", expr_text(variable)), stmt.span)
+            }
+            StmtKind::Write(value) => {
+                self.simple_line(format!("write({});", expr_text(value)), stmt.span)
+            }
+            StmtKind::Return(value) => {
+                self.simple_line(format!("return ({});", expr_text(value)), stmt.span)
+            }
+            StmtKind::Break => self.simple_line("break;".to_string(), stmt.span),
+            StmtKind::Continue => self.simple_line("continue;".to_string(), stmt.span),
+            // a block is only a statement as the branch or body of another one
+            StmtKind::Block(block) => {
+                for stmt in block.stmts.iter() {
+                    self.print_stmt(stmt);
+                }
+            }
+            StmtKind::Error => {
+                self.simple_line(format!("/* syntax error at {} */", stmt.span), stmt.span)
+            }
+        }
+    }
+
+    /// Print an if statement, leaving its last closing brace to the caller. An else-if
+    /// continues the closing line of the branch before it.
+    fn print_if(&mut self, stmt: &Stmt, prefix: &str) {
+        let StmtKind::If {
+            condition,
+            then_branch,
+            else_branch,
+        } = &stmt.kind
+        else {
+            return;
+        };
+        self.advance(condition.span.end);
+        let head = format!("{}if ({}) then", prefix, expr_text(condition));
+        self.print_block(
+            &head,
+            then_branch,
+            &[ValidTokenType::ClosePar, ValidTokenType::KwThen],
+        );
+        let else_skipped = [
+            ValidTokenType::ClosePar,
+            ValidTokenType::Semi,
+            ValidTokenType::KwElse,
+        ];
+        match else_branch.as_deref() {
+            None => {}
+            Some(
+                else_if @ Stmt {
+                    kind: StmtKind::If { .. },
+                    ..
+                },
+            ) => {
+                let prefix = format!("{}else ", self.else_prefix());
+                self.print_if(else_if, &prefix);
+            }
+            Some(Stmt {
+                kind: StmtKind::Block(block),
+                ..
+            }) => {
+                let head = format!("{}else", self.else_prefix());
+                self.print_block(&head, block, &else_skipped);
+            }
+            Some(other) => {
+                let head = format!("{}else", self.else_prefix());
+                let closing_brace = self.open_brace(&head, &else_skipped);
+                self.print_stmt(other);
+                self.close_brace(closing_brace);
+            }
+        }
+    }
+
+    /// What comes before `else` on its line: the closing brace of the branch before it,
+    /// unless braces are on lines of their own
+    fn else_prefix(&mut self) -> String {
+        match self.options.brace_style {
+            BraceStyle::SameLine => "} ".to_string(),
+            BraceStyle::NextLine => {
+                self.line("}");
+                String::new()
+            }
+        }
+    }
+
+    /// Print `head {` and the statements of the block, leaving the closing brace to the caller
+    fn print_block(&mut self, head: &str, block: &Block, skipped: &[ValidTokenType]) {
+        let closing_brace = self.open_brace(head, skipped);
+        for stmt in block.stmts.iter() {
+            self.print_stmt(stmt);
+        }
+        self.close_brace(closing_brace);
+    }
+
+    /// Print the line opening a block and indent. The opening brace in the source is the
+    /// next token that is not of a `skipped` type, if the block has braces; its closing
+    /// brace is returned.
+    fn open_brace(&mut self, head: &str, skipped: &[ValidTokenType]) -> Option<Span> {
+        let mut closing_brace = None;
+        if let Some(source) = self.source.as_mut() {
+            let open_brace = source
+                .next_token(skipped)
+                .filter(|token| token.get_valid_token_type() == Some(ValidTokenType::OpenCuBr))
+                .map(|token| token.get_span());
+            if let Some(open_brace) = open_brace {
+                source.advance(open_brace.end);
+                closing_brace = source.closing_braces.get(&open_brace.start).copied();
+            }
+        }
+        match self.options.brace_style {
+            BraceStyle::SameLine => self.line(&format!("{} {{", head)),
+            BraceStyle::NextLine => {
+                self.line(head);
+                self.line("{");
+            }
+        }
+        self.depth += 1;
+        self.blank_line_allowed = false;
+        closing_brace
+    }
+
+    /// Print the comments at the end of a block and dedent. Without braces in the source,
+    /// the block ends before the next token, or at the end of its last line.
+    fn close_brace(&mut self, closing_brace: Option<Span>) {
+        let end = match (closing_brace, self.source.as_mut()) {
+            (Some(closing_brace), _) => Some(closing_brace.start),
+            (None, Some(source)) => source.next_token(&[]).map(|token| token.location),
+            (None, None) => None,
+        };
+        if let Some(end) = end {
+            self.comments_before(end);
+        }
+        match closing_brace {
+            Some(closing_brace) => self.advance(closing_brace.end),
+            None => self.trailing_comments(),
+        }
+        self.depth -= 1;
+    }
+
+    /// Print a construct that fits on one line
+    fn simple_line(&mut self, text: String, span: Span) {
+        self.node_start(span);
+        self.line(&text);
+        self.advance(span.end);
+    }
+
+    /// Print the comments before a construct starting at `span`, and keep an empty line
+    /// before it
+    fn node_start(&mut self, span: Span) {
+        self.comments_before(span.start);
+        self.blank_line_before(span.start.0);
+    }
+
+    /// Print the comments starting before `position`. A comment on the line where the
+    /// source printed so far ends stays at the end of that line.
+    fn comments_before(&mut self, position: (u32, u32)) {
+        loop {
+            let (comment, cursor) = match self.source.as_ref() {
+                Some(source) => match source.next_comment_before(position) {
+                    Some(comment) => (comment.clone(), source.cursor),
+                    None => return,
+                },
+                None => return,
+            };
+            let span = comment.get_span();
+            let lexeme = comment.lexeme.trim_end();
+            let trailing = span.start.0 == cursor.0
+                && span.end.0 == span.start.0
+                && self.output.ends_with('\n')
+                && !self.output.ends_with("\n\n");
+            if trailing {
+                self.output.pop();
+                self.output.push(' ');
+                self.output.push_str(lexeme);
+                self.output.push('\n');
+            } else {
+                self.blank_line_before(span.start.0);
+                // the lines of a block comment after the first are kept as they are
+                self.line(lexeme);
+            }
+            if let Some(source) = self.source.as_mut() {
+                source.next_comment += 1;
+                source.advance(span.end);
+            }
+        }
+    }
+
+    /// Print the comments on the line where the source printed so far ends
+    fn trailing_comments(&mut self) {
+        if let Some(cursor) = self.source.as_ref().map(|source| source.cursor) {
+            self.comments_before((cursor.0, u32::MAX));
+        }
+    }
+
+    /// Keep one empty line of the source before a line starting at source line `line`
+    fn blank_line_before(&mut self, line: u32) {
+        if let Some(source) = self.source.as_ref() {
+            if self.blank_line_allowed && source.cursor.0 > 0 && line > source.cursor.0 + 1 {
+                self.output.push('\n');
+                self.blank_line_allowed = false;
+            }
+        }
+    }
+
+    fn advance(&mut self, position: (u32, u32)) {
+        if let Some(source) = self.source.as_mut() {
+            source.advance(position);
+        }
+    }
+
+    fn line(&mut self, text: &str) {
+        for _ in 0..self.depth * self.options.indent_width {
+            self.output.push(' ');
+        }
+        self.output.push_str(text);
+        self.output.push('\n');
+        self.blank_line_allowed = true;
+    }
+}
+
+fn is_declaration(item: &Item) -> bool {
+    matches!(item, Item::Var(_) | Item::Const(_))
+}
+
+fn var_decl_text(var_decl: &VarDecl) -> String {
+    let mut text = format!("let {}", var_decl.name.name);
+    if var_decl.var_type.base != BaseType::Inferred {
+        text.push_str(&format!(": {}", var_decl.var_type));
+    }
+    if let Some(initializer) = &var_decl.initializer {
+        text.push_str(&format!(" = {}", initializer_text(initializer)));
+    }
+    text
+}
+
+fn const_decl_text(const_decl: &ConstDecl) -> String {
+    format!(
+        "const {}: {} = {};",
+        const_decl.name.name,
+        const_decl.const_type,
+        expr_text(&const_decl.value)
+    )
+}
+
+fn initializer_text(initializer: &Initializer) -> String {
+    match initializer {
+        Initializer::Expr(expr) => expr_text(expr),
+        Initializer::List(elements, _) => {
+            let elements: Vec<String> = elements.iter().map(initializer_text).collect();
+            format!("{{{}}}", elements.join(", "))
+        }
+    }
+}
+
+fn func_decl_text(func_decl: &FuncDecl) -> String {
+    func_head_text(
+        &func_decl.name.name,
+        &func_decl.params,
+        &func_decl.return_type,
+    )
+}
+
+fn func_head_text(name: &str, params: &[Param], return_type: &TypeSpec) -> String {
+    let params: Vec<String> = params
+        .iter()
+        .map(|param| format!("{}: {}", param.name.name, param.param_type))
+        .collect();
+    format!("func {}({}) -> {}", name, params.join(", "), return_type)
+}
+
+/// An assignment or a call, without the semicolon, as it also appears in a for loop
+fn simple_stmt_text(stmt: &Stmt) -> String {
+    match &stmt.kind {
+        StmtKind::Assign { target, value } => {
+            format!("{} = {}", expr_text(target), expr_text(value))
+        }
+        StmtKind::Expr(expr) => expr_text(expr),
+        _ => format!("/* syntax error at {} */", stmt.span),
+    }
+}
+
+pub fn expr_text(expr: &Expr) -> String {
+    match &expr.kind {
+        ExprKind::IntLit(value) | ExprKind::FloatLit(value) => value.clone(),
+        ExprKind::BoolLit(value) => value.to_string(),
+        ExprKind::Id(ident) => ident.name.clone(),
+        ExprKind::Var { base, indices } => {
+            let mut text = expr_text(base);
+            for index in indices.iter() {
+                text.push_str(&format!("[{}]", operand_text(index, ARITH_MIN_PRECEDENCE)));
+            }
+            text
+        }
+        ExprKind::Member { object, member } => format!("{}.{}", expr_text(object), member.name),
+        ExprKind::Call { callee, args } => {
+            let args: Vec<String> = args.iter().map(expr_text).collect();
+            format!("{}({})", expr_text(callee), args.join(", "))
+        }
+        ExprKind::Binary { op, lhs, rhs } => {
+            let operator = operator_of(op);
+            // an operand binding as tight as the operator only stays unparenthesized on
+            // the side the operator associates to
+            let (lhs_min, rhs_min) = match operator.associativity {
+                Associativity::Left => (operator.precedence, operator.precedence + 1),
+                Associativity::None => (operator.precedence + 1, operator.precedence + 1),
+            };
+            format!(
+                "{} {} {}",
+                operand_text(lhs, lhs_min),
+                op,
+                operand_text(rhs, rhs_min)
+            )
+        }
+        // the operand of a unary operator is a single factor
+        ExprKind::Unary { op, operand } => format!("{}{}", op, operand_text(operand, usize::MAX)),
+        ExprKind::Error => "/* syntax error */".to_string(),
+    }
+}
+
+/// The text of `expr`, parenthesized when it is a binary expression whose operator
+/// binds looser than `min_precedence`
+fn operand_text(expr: &Expr, min_precedence: usize) -> String {
+    match &expr.kind {
+        ExprKind::Binary { op, .. } if operator_of(op).precedence < min_precedence => {
+            format!("({})", expr_text(expr))
+        }
+        _ => expr_text(expr),
+    }
+}
+
+fn operator_of(op: &BinaryOp) -> &'static precedence::Operator {
+    let token_type = match op {
+        BinaryOp::Eq => ValidTokenType::Eq,
+        BinaryOp::NotEq => ValidTokenType::NotEq,
+        BinaryOp::Lt => ValidTokenType::Lt,
+        BinaryOp::Gt => ValidTokenType::Gt,
+        BinaryOp::Leq => ValidTokenType::Leq,
+        BinaryOp::Geq => ValidTokenType::Geq,
+        BinaryOp::Add => ValidTokenType::Plus,
+        BinaryOp::Sub => ValidTokenType::Minus,
+        BinaryOp::Mul => ValidTokenType::Mult,
+        BinaryOp::Div => ValidTokenType::Div,
+        BinaryOp::And => ValidTokenType::And,
+        BinaryOp::Or => ValidTokenType::Or,
+    };
+    precedence::get_operator(&token_type).expect("every binary operator is in the table")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::semantic::serialization::to_sexpr;
+    use crate::syntactic::parser::tests::{sample_tokens, tokens_of};
+    use crate::syntactic::parser::Parser;
+    use std::path::Path;
+
+    /// The AST without its spans, which change when the source is printed again
+    fn shape(ast: &AbstractSyntaxTree) -> String {
+        to_sexpr(ast, &HashMap::new())
+    }
+
+    #[test]
+    fn prints_the_samples_back_to_the_same_ast() {
+        let mut samples = vec![];
+        sample_tokens(Path::new("resource"), &mut samples);
+        let mut printed_samples = 0;
+        for (path, tokens) in samples {
+            let ast = match Parser::new().parse(tokens) {
+                Ok((_, ast, _, errors)) if errors.is_empty() => ast,
+                _ => continue,
+            };
+            let printed = unparse(&ast);
+            let reparsed = match Parser::new().parse(tokens_of(&printed)) {
+                Ok((_, ast, _, errors)) if errors.is_empty() => ast,
+                _ => panic!("{} printed with syntax errors:\n{}", path, printed),
+            };
+            assert_eq!(shape(&reparsed), shape(&ast), "{}", path);
+            assert_eq!(unparse(&reparsed), printed, "{} printed twice", path);
+            printed_samples += 1;
+        }
+        assert!(printed_samples > 0);
+    }
+
+    const UNFORMATTED: &str = "// leading\nfunc   main() -> void { /* block */ let x: integer; // after x\n if (x==1) then { write(x); } else { x = 2; }; /* multi\nline */\n while (x < 3) { x = x + 1; };\n}\n// trailing\n";
+
+    fn format_source(source: &str, options: FormatOptions) -> String {
+        let tokens = tokens_of(source);
+        let (_, ast, spans, errors) = Parser::new().parse(tokens.clone()).unwrap();
+        assert!(errors.is_empty(), "{:?}", errors);
+        format(&ast, &spans, &tokens, options)
+    }
+
+    fn comments_of(source: &str) -> Vec<String> {
+        tokens_of(source)
+            .into_iter()
+            .filter(|token| {
+                matches!(
+                    token.get_valid_token_type(),
+                    Some(ValidTokenType::InlineCmt | ValidTokenType::BlockCmt)
+                )
+            })
+            .map(|token| token.lexeme)
+            .collect()
+    }
+
+    #[test]
+    fn keeps_every_comment() {
+        let formatted = format_source(UNFORMATTED, FormatOptions::default());
+        assert_eq!(comments_of(&formatted), comments_of(UNFORMATTED));
+        assert_eq!(comments_of(UNFORMATTED).len(), 5);
+    }
+
+    #[test]
+    fn formats_a_formatted_source_to_itself() {
+        for options in [
+            FormatOptions::default(),
+            FormatOptions {
+                indent_width: 4,
+                brace_style: BraceStyle::NextLine,
+            },
+        ] {
+            let formatted = format_source(UNFORMATTED, options);
+            // what `--check` compares
+            assert_ne!(formatted, UNFORMATTED);
+            assert_eq!(format_source(&formatted, options), formatted);
+        }
+    }
+
+    #[test]
+    fn indents_and_places_braces_as_asked() {
+        let source =
+            "func main() -> void { let x: integer; if (x == 1) then { write(x); } else { x = 2; }; }";
+        assert_eq!(
+            format_source(source, FormatOptions::default()),
+            "func main() -> void {\n  let x: integer;\n  if (x == 1) then {\n    write(x);\n  } else {\n    x = 2;\n  };\n}\n"
+        );
+        assert_eq!(
+            format_source(
+                source,
+                FormatOptions {
+                    indent_width: 4,
+                    brace_style: BraceStyle::NextLine,
+                }
+            ),
+            "func main() -> void\n{\n    let x: integer;\n    if (x == 1) then\n    {\n        write(x);\n    }\n    else\n    {\n        x = 2;\n    };\n}\n"
+        );
+    }
+}
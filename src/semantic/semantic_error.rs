@@ -1,7 +1,5 @@
 use std::error::Error;
 use std::fmt::{Debug, Display, Formatter};
-use std::fs::File;
-use std::io::Write;
 
 pub struct SemanticError {
     error_type: SemanticErrType,
@@ -9,24 +7,30 @@ pub struct SemanticError {
 }
 
 impl SemanticError {
-    pub fn report_error(message: &str) -> Self {
-        Self::report(SemanticErrType::Error, message)
+    pub fn error(message: &str) -> Self {
+        Self::new(SemanticErrType::Error, message)
     }
-    pub fn report(error_type: SemanticErrType, message: &str) -> Self {
-        let e = Self {
+
+    pub fn warning(message: &str) -> Self {
+        Self::new(SemanticErrType::Warning, message)
+    }
+
+    pub fn new(error_type: SemanticErrType, message: &str) -> Self {
+        Self {
             error_type,
             message: message.to_string(),
-        };
-        let mut out_errors = File::create("resource/semantics/outsemanticerrors").unwrap();
-        out_errors.write_all(format!("{}\n", e).as_bytes());
-        println!("{}", e);
-        e
+        }
+    }
+
+    /// Whether it is an error rather than a warning
+    pub fn is_error(&self) -> bool {
+        matches!(self.error_type, SemanticErrType::Error)
     }
 }
 
 impl Debug for SemanticError {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        todo!()
+        write!(f, "{}", self)
     }
 }
 
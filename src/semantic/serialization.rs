@@ -0,0 +1,549 @@
+use crate::lexical::span::Span;
+use crate::semantic::ast::AbstractSyntaxTree;
+use crate::semantic::concept::{AtomicConcept, AtomicConceptType, CompositeConcept, Concept};
+use crate::syntactic::tree::{NodeId, Tree};
+use crate::syntactic::util::json_string;
+use std::collections::HashMap;
+use std::iter::Peekable;
+use std::str::{Chars, FromStr};
+
+/// An AST read back from one of its serialized forms, with the spans it had
+pub type DeserializedAst = (AbstractSyntaxTree, HashMap<NodeId, Span>);
+
+/// JSON form, each node an object with its children nested. An atom has its value,
+/// and children only if it is an `Error` placeholder holding some.
+pub fn to_json(ast: &AbstractSyntaxTree, spans: &HashMap<NodeId, Span>) -> String {
+    if ast.has_root() {
+        to_json_from_node(ast, spans, ast.get_root())
+    } else {
+        "null".to_string()
+    }
+}
+
+/// S-expression form, such as `(AddExpr @1:1-1:6 (IntLit "1" @1:1-1:2) ...)`. An atom
+/// is followed by its value, and every node by its span if it has one.
+pub fn to_sexpr(ast: &AbstractSyntaxTree, spans: &HashMap<NodeId, Span>) -> String {
+    let mut result = String::new();
+    if ast.has_root() {
+        to_sexpr_from_node(ast, spans, ast.get_root(), 0, &mut result);
+    }
+    result
+}
+
+/// Read an AST written by `to_json`
+pub fn from_json(text: &str) -> Result<DeserializedAst, String> {
+    let mut reader = JsonReader {
+        chars: text.chars().peekable(),
+    };
+    let value = reader.read_value()?;
+    reader.skip_whitespace();
+    if let Some(c) = reader.chars.next() {
+        return Err(format!("unexpected `{}` after the AST", c));
+    }
+    let mut ast = Tree::new();
+    let mut spans = HashMap::new();
+    if !matches!(value, JsonValue::Null) {
+        build_from_json(&value, None, &mut ast, &mut spans)?;
+    }
+    Ok((ast, spans))
+}
+
+/// Read an AST written by `to_sexpr`
+pub fn from_sexpr(text: &str) -> Result<DeserializedAst, String> {
+    let tokens = sexpr_tokens(text)?;
+    let mut ast = Tree::new();
+    let mut spans = HashMap::new();
+    let mut position = 0;
+    if !tokens.is_empty() {
+        build_from_sexpr(&tokens, &mut position, None, &mut ast, &mut spans)?;
+    }
+    if position < tokens.len() {
+        return Err(format!("unexpected {:?} after the AST", tokens[position]));
+    }
+    Ok((ast, spans))
+}
+
+fn to_json_from_node(
+    ast: &AbstractSyntaxTree,
+    spans: &HashMap<NodeId, Span>,
+    node_id: NodeId,
+) -> String {
+    let span = match spans.get(&node_id) {
+        Some(span) => format!(
+            "{{\"start\":{{\"line\":{},\"column\":{}}},\"end\":{{\"line\":{},\"column\":{}}}}}",
+            span.start.0, span.start.1, span.end.0, span.end.1
+        ),
+        None => "null".to_string(),
+    };
+    let children: Vec<String> = ast
+        .children(node_id)
+        .map(|child| to_json_from_node(ast, spans, child))
+        .collect();
+    match ast.get_node_value(node_id) {
+        Concept::AtomicConcept(ac) => {
+            let children = if children.is_empty() {
+                "".to_string()
+            } else {
+                format!(",\"children\":[{}]", children.join(","))
+            };
+            format!(
+                "{{\"kind\":\"atom\",\"concept\":{},\"value\":{},\"span\":{}{}}}",
+                json_string(&format!("{:?}", ac.atomic_concept_type)),
+                json_string(&ac.value),
+                span,
+                children
+            )
+        }
+        Concept::CompositeConcept(cc) => format!(
+            "{{\"kind\":\"node\",\"concept\":{},\"span\":{},\"children\":[{}]}}",
+            json_string(&cc.to_string()),
+            span,
+            children.join(",")
+        ),
+    }
+}
+
+fn to_sexpr_from_node(
+    ast: &AbstractSyntaxTree,
+    spans: &HashMap<NodeId, Span>,
+    node_id: NodeId,
+    depth: usize,
+    result: &mut String,
+) {
+    result.push_str(&"  ".repeat(depth));
+    result.push('(');
+    match ast.get_node_value(node_id) {
+        Concept::AtomicConcept(ac) => {
+            result.push_str(&format!(
+                "{:?} {}",
+                ac.atomic_concept_type,
+                json_string(&ac.value)
+            ));
+        }
+        Concept::CompositeConcept(cc) => result.push_str(&cc.to_string()),
+    }
+    if let Some(span) = spans.get(&node_id) {
+        result.push_str(&format!(" @{}", span));
+    }
+    for child in ast.children(node_id) {
+        result.push('\n');
+        to_sexpr_from_node(ast, spans, child, depth + 1, result);
+    }
+    result.push(')');
+    if depth == 0 {
+        result.push('\n');
+    }
+}
+
+/// The concept named `name`, an atom if `value` is given
+fn concept_of(name: &str, value: Option<String>) -> Result<Concept, String> {
+    match value {
+        Some(value) => AtomicConceptType::from_str(name)
+            .map(|atomic_concept_type| {
+                Concept::AtomicConcept(AtomicConcept {
+                    atomic_concept_type,
+                    value,
+                })
+            })
+            .map_err(|_| format!("unknown atomic concept `{}`", name)),
+        None => CompositeConcept::from_str(name)
+            .map(Concept::CompositeConcept)
+            .map_err(|_| format!("unknown composite concept `{}`", name)),
+    }
+}
+
+enum JsonValue {
+    Null,
+    Number(f64),
+    String(String),
+    Array(Vec<JsonValue>),
+    Object(Vec<(String, JsonValue)>),
+}
+
+impl JsonValue {
+    fn get(&self, key: &str) -> Option<&JsonValue> {
+        match self {
+            JsonValue::Object(members) => members
+                .iter()
+                .find(|(name, _)| name == key)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    fn get_str(&self, key: &str) -> Result<&str, String> {
+        match self.get(key) {
+            Some(JsonValue::String(s)) => Ok(s),
+            _ => Err(format!("expected a string `{}`", key)),
+        }
+    }
+
+    fn get_position(&self, key: &str) -> Result<(u32, u32), String> {
+        let position = self
+            .get(key)
+            .ok_or_else(|| format!("expected a position `{}`", key))?;
+        let number = |key: &str| match position.get(key) {
+            Some(JsonValue::Number(n)) if *n >= 0.0 => Ok(*n as u32),
+            _ => Err(format!("expected a number `{}`", key)),
+        };
+        Ok((number("line")?, number("column")?))
+    }
+}
+
+fn build_from_json(
+    value: &JsonValue,
+    parent: Option<NodeId>,
+    ast: &mut AbstractSyntaxTree,
+    spans: &mut HashMap<NodeId, Span>,
+) -> Result<(), String> {
+    let name = value.get_str("concept")?;
+    let concept = match value.get_str("kind")? {
+        "atom" => concept_of(name, Some(value.get_str("value")?.to_string()))?,
+        "node" => concept_of(name, None)?,
+        kind => return Err(format!("unknown kind `{}`", kind)),
+    };
+    let node_id = ast.insert_node(parent, concept);
+    match value.get("span") {
+        None | Some(JsonValue::Null) => {}
+        Some(span) => {
+            spans.insert(
+                node_id,
+                Span::new(span.get_position("start")?, span.get_position("end")?),
+            );
+        }
+    }
+    match value.get("children") {
+        None => Ok(()),
+        Some(JsonValue::Array(children)) => {
+            for child in children {
+                build_from_json(child, Some(node_id), ast, spans)?;
+            }
+            Ok(())
+        }
+        Some(_) => Err("expected an array `children`".to_string()),
+    }
+}
+
+struct JsonReader<'a> {
+    chars: Peekable<Chars<'a>>,
+}
+
+impl<'a> JsonReader<'a> {
+    fn skip_whitespace(&mut self) {
+        while self.chars.next_if(|c| c.is_whitespace()).is_some() {}
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        self.skip_whitespace();
+        match self.chars.next() {
+            Some(c) if c == expected => Ok(()),
+            Some(c) => Err(format!("expected `{}`, found `{}`", expected, c)),
+            None => Err(format!("expected `{}`, found the end", expected)),
+        }
+    }
+
+    fn read_value(&mut self) -> Result<JsonValue, String> {
+        self.skip_whitespace();
+        match self.chars.peek() {
+            Some('{') => {
+                self.chars.next();
+                let mut members = vec![];
+                self.skip_whitespace();
+                if self.chars.next_if_eq(&'}').is_some() {
+                    return Ok(JsonValue::Object(members));
+                }
+                loop {
+                    self.skip_whitespace();
+                    let key = self.read_string()?;
+                    self.expect(':')?;
+                    members.push((key, self.read_value()?));
+                    self.skip_whitespace();
+                    match self.chars.next() {
+                        Some(',') => {}
+                        Some('}') => return Ok(JsonValue::Object(members)),
+                        _ => return Err("expected `,` or `}` in an object".to_string()),
+                    }
+                }
+            }
+            Some('[') => {
+                self.chars.next();
+                let mut elements = vec![];
+                self.skip_whitespace();
+                if self.chars.next_if_eq(&']').is_some() {
+                    return Ok(JsonValue::Array(elements));
+                }
+                loop {
+                    elements.push(self.read_value()?);
+                    self.skip_whitespace();
+                    match self.chars.next() {
+                        Some(',') => {}
+                        Some(']') => return Ok(JsonValue::Array(elements)),
+                        _ => return Err("expected `,` or `]` in an array".to_string()),
+                    }
+                }
+            }
+            Some('"') => self.read_string().map(JsonValue::String),
+            Some(c) if *c == '-' || c.is_ascii_digit() => {
+                let mut number = String::new();
+                while let Some(c) = self
+                    .chars
+                    .next_if(|c| c.is_ascii_digit() || "+-.eE".contains(*c))
+                {
+                    number.push(c);
+                }
+                number
+                    .parse()
+                    .map(JsonValue::Number)
+                    .map_err(|_| format!("invalid number `{}`", number))
+            }
+            Some(c) if c.is_alphabetic() => {
+                let mut word = String::new();
+                while let Some(c) = self.chars.next_if(|c| c.is_alphabetic()) {
+                    word.push(c);
+                }
+                // the AST has no booleans
+                match word.as_str() {
+                    "null" => Ok(JsonValue::Null),
+                    _ => Err(format!("unexpected `{}`", word)),
+                }
+            }
+            Some(c) => Err(format!("unexpected `{}`", c)),
+            None => Err("unexpected end of the JSON".to_string()),
+        }
+    }
+
+    fn read_string(&mut self) -> Result<String, String> {
+        if self.chars.next() != Some('"') {
+            return Err("expected a string".to_string());
+        }
+        read_escaped(&mut self.chars)
+    }
+}
+
+/// The rest of a string after its opening quote, with the escapes of `json_string`
+fn read_escaped(chars: &mut Peekable<Chars>) -> Result<String, String> {
+    let mut s = String::new();
+    loop {
+        match chars.next() {
+            Some('"') => return Ok(s),
+            Some('\\') => match chars.next() {
+                Some('n') => s.push('\n'),
+                Some('r') => s.push('\r'),
+                Some('t') => s.push('\t'),
+                Some('b') => s.push('\u{8}'),
+                Some('f') => s.push('\u{c}'),
+                Some('u') => {
+                    let code: String = chars.take(4).collect();
+                    let c = u32::from_str_radix(&code, 16)
+                        .ok()
+                        .and_then(char::from_u32)
+                        .ok_or_else(|| format!("invalid escape `\\u{}`", code))?;
+                    s.push(c);
+                }
+                Some(c) => s.push(c),
+                None => return Err("unterminated string".to_string()),
+            },
+            Some(c) => s.push(c),
+            None => return Err("unterminated string".to_string()),
+        }
+    }
+}
+
+#[derive(Debug)]
+enum SexprToken {
+    Open,
+    Close,
+    Name(String),
+    Value(String),
+    Span(Span),
+}
+
+fn sexpr_tokens(text: &str) -> Result<Vec<SexprToken>, String> {
+    let mut tokens = vec![];
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '(' => tokens.push(SexprToken::Open),
+            ')' => tokens.push(SexprToken::Close),
+            '"' => tokens.push(SexprToken::Value(read_escaped(&mut chars)?)),
+            c if c.is_whitespace() => {}
+            c => {
+                let mut word = c.to_string();
+                while let Some(c) = chars.next_if(|c| !c.is_whitespace() && !"()\"".contains(*c)) {
+                    word.push(c);
+                }
+                match word.strip_prefix('@') {
+                    Some(span) => tokens.push(SexprToken::Span(parse_span(span)?)),
+                    None => tokens.push(SexprToken::Name(word)),
+                }
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+/// A span as displayed, `line:column-line:column`
+fn parse_span(text: &str) -> Result<Span, String> {
+    let position = |text: &str| -> Option<(u32, u32)> {
+        let (line, column) = text.split_once(':')?;
+        Some((line.parse().ok()?, column.parse().ok()?))
+    };
+    text.split_once('-')
+        .and_then(|(start, end)| Some(Span::new(position(start)?, position(end)?)))
+        .ok_or_else(|| format!("invalid span `{}`", text))
+}
+
+fn build_from_sexpr(
+    tokens: &[SexprToken],
+    position: &mut usize,
+    parent: Option<NodeId>,
+    ast: &mut AbstractSyntaxTree,
+    spans: &mut HashMap<NodeId, Span>,
+) -> Result<(), String> {
+    let mut next = || {
+        let token = tokens.get(*position);
+        *position += 1;
+        token.ok_or_else(|| "unexpected end of the AST".to_string())
+    };
+    if !matches!(next()?, SexprToken::Open) {
+        return Err("expected `(`".to_string());
+    }
+    let name = match next()? {
+        SexprToken::Name(name) => name.clone(),
+        token => return Err(format!("expected a concept, found {:?}", token)),
+    };
+    let value = match tokens.get(*position) {
+        Some(SexprToken::Value(value)) => {
+            *position += 1;
+            Some(value.clone())
+        }
+        _ => None,
+    };
+    let node_id = ast.insert_node(parent, concept_of(&name, value)?);
+    if let Some(SexprToken::Span(span)) = tokens.get(*position) {
+        *position += 1;
+        spans.insert(node_id, *span);
+    }
+    loop {
+        match tokens.get(*position) {
+            Some(SexprToken::Close) => {
+                *position += 1;
+                return Ok(());
+            }
+            Some(SexprToken::Open) => {
+                build_from_sexpr(tokens, position, Some(node_id), ast, spans)?
+            }
+            Some(token) => return Err(format!("unexpected {:?} in {}", token, name)),
+            None => return Err(format!("unclosed {}", name)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::syntactic::parser::tests::sample_tokens;
+    use crate::syntactic::parser::Parser;
+    use std::path::Path;
+
+    /// Compare two ASTs node by node, with their spans
+    fn assert_same_tree(
+        expected: (&AbstractSyntaxTree, &HashMap<NodeId, Span>, NodeId),
+        actual: (&AbstractSyntaxTree, &HashMap<NodeId, Span>, NodeId),
+        name: &str,
+    ) {
+        let (expected_ast, expected_spans, expected_node) = expected;
+        let (actual_ast, actual_spans, actual_node) = actual;
+        assert!(
+            expected_ast.get_node_value(expected_node) == actual_ast.get_node_value(actual_node),
+            "{}: {} read back as {}",
+            name,
+            expected_ast.get_node_value(expected_node).label(),
+            actual_ast.get_node_value(actual_node).label()
+        );
+        assert_eq!(
+            expected_spans.get(&expected_node),
+            actual_spans.get(&actual_node),
+            "{}: span of {}",
+            name,
+            expected_ast.get_node_value(expected_node).label()
+        );
+        let expected_children: Vec<NodeId> = expected_ast.children(expected_node).collect();
+        let actual_children: Vec<NodeId> = actual_ast.children(actual_node).collect();
+        assert_eq!(expected_children.len(), actual_children.len(), "{}", name);
+        for (expected_child, actual_child) in expected_children.into_iter().zip(actual_children) {
+            assert_same_tree(
+                (expected_ast, expected_spans, expected_child),
+                (actual_ast, actual_spans, actual_child),
+                name,
+            );
+        }
+    }
+
+    #[test]
+    fn reads_back_the_samples_in_both_forms() {
+        let mut samples = vec![];
+        sample_tokens(Path::new("resource"), &mut samples);
+        assert!(!samples.is_empty());
+        for (path, tokens) in samples {
+            // samples with syntax errors too, for their `Error` placeholders
+            let (ast, spans) = match Parser::new().parse(tokens) {
+                Ok((_, ast, spans, _)) => (ast, spans),
+                Err(_) => continue,
+            };
+            let forms = [
+                ("JSON", from_json(&to_json(&ast, &spans))),
+                ("S-expression", from_sexpr(&to_sexpr(&ast, &spans))),
+            ];
+            for (form, read) in forms {
+                let name = format!("{} as {}", path, form);
+                let (read_ast, read_spans) = read.unwrap_or_else(|e| panic!("{}: {}", name, e));
+                assert_same_tree(
+                    (&ast, &spans, ast.get_root()),
+                    (&read_ast, &read_spans, read_ast.get_root()),
+                    &name,
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn reads_escaped_values_and_missing_spans() {
+        let mut ast = Tree::new();
+        let root = ast.insert_node(None, concept_of("Prog", None).unwrap());
+        let value = "a \"quoted\"\\ value\n\twith \u{1} é";
+        let atom = ast.insert_node(
+            Some(root),
+            concept_of("FloatLit", Some(value.to_string())).unwrap(),
+        );
+        let spans = HashMap::from([(atom, Span::new((1, 2), (3, 4)))]);
+        for (read_ast, read_spans) in [
+            from_json(&to_json(&ast, &spans)).unwrap(),
+            from_sexpr(&to_sexpr(&ast, &spans)).unwrap(),
+        ] {
+            assert_same_tree(
+                (&ast, &spans, root),
+                (&read_ast, &read_spans, read_ast.get_root()),
+                value,
+            );
+        }
+    }
+
+    #[test]
+    fn reads_an_empty_ast() {
+        let ast = Tree::new();
+        let spans = HashMap::new();
+        assert!(!from_json(&to_json(&ast, &spans)).unwrap().0.has_root());
+        assert!(!from_sexpr(&to_sexpr(&ast, &spans)).unwrap().0.has_root());
+    }
+
+    #[test]
+    fn rejects_malformed_input() {
+        assert!(from_json("{\"kind\":\"node\"").is_err());
+        assert!(from_json("{\"kind\":\"node\",\"concept\":\"Nope\",\"children\":[]}").is_err());
+        assert!(from_json("null null").is_err());
+        assert!(from_sexpr("(Prog (IntLit \"1\")").is_err());
+        assert!(from_sexpr("(Prog @1:0-x)").is_err());
+        assert!(from_sexpr("(IntLit)").is_err());
+    }
+}
@@ -0,0 +1,191 @@
+use crate::lexical::span::Span;
+use crate::semantic::ast::AbstractSyntaxTree;
+use crate::syntactic::tree::NodeId;
+use std::collections::HashMap;
+
+/// The source range of each AST node, with the queries editor tooling starts from: the
+/// node at a position and the nodes around it. Node ids are indices into the tree of
+/// one parse; a node is followed across parses of an edited source by its `NodeKey`.
+pub struct SourceMap<'a> {
+    ast: &'a AbstractSyntaxTree,
+    spans: &'a HashMap<NodeId, Span>,
+}
+
+/// A node identified by its place in the tree rather than by its id: the kind of each
+/// node from the root to it, with its index among its siblings. The key still finds the
+/// node after an edit inside it, after it, or that only moves it in the source, and finds
+/// nothing once a node on its path is of another kind.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct NodeKey(Vec<(usize, String)>);
+
+impl<'a> SourceMap<'a> {
+    /// `spans` are the source ranges of the nodes of `ast`, as returned by the parser
+    pub fn new(ast: &'a AbstractSyntaxTree, spans: &'a HashMap<NodeId, Span>) -> Self {
+        Self { ast, spans }
+    }
+
+    pub fn span(&self, node: NodeId) -> Option<Span> {
+        self.spans.get(&node).copied()
+    }
+
+    /// The innermost node whose span contains the (line, column) position
+    pub fn node_at(&self, position: (u32, u32)) -> Option<NodeId> {
+        let mut node = self.ast.get_root();
+        if !self.contains(node, position) {
+            return None;
+        }
+        // spans of children are inside the span of their parent
+        while let Some(child) = self
+            .ast
+            .children(node)
+            .find(|child| self.contains(*child, position))
+        {
+            node = child;
+        }
+        Some(node)
+    }
+
+    /// The node at the position and the nodes enclosing it, innermost first, each with a
+    /// larger span than the one before: the steps of expanding a selection
+    pub fn selection_ranges(&self, position: (u32, u32)) -> Vec<(NodeId, Span)> {
+        let node = match self.node_at(position) {
+            Some(node) => node,
+            None => return vec![],
+        };
+        let mut ranges: Vec<(NodeId, Span)> = vec![];
+        for node in std::iter::once(node).chain(self.ast.ancestors(node)) {
+            if let Some(span) = self.span(node) {
+                if ranges.last().is_none_or(|(_, last)| *last != span) {
+                    ranges.push((node, span));
+                }
+            }
+        }
+        ranges
+    }
+
+    /// The key to find the node again in a later parse
+    pub fn key(&self, node: NodeId) -> NodeKey {
+        let mut path: Vec<(usize, String)> = std::iter::once(node)
+            .chain(self.ast.ancestors(node))
+            .map(|node| {
+                (
+                    self.ast.get_child_index(node).unwrap_or(0),
+                    self.ast.get_node_value(node).to_string(),
+                )
+            })
+            .collect();
+        path.reverse();
+        NodeKey(path)
+    }
+
+    /// The node with the key, which may come from another parse
+    pub fn find(&self, key: &NodeKey) -> Option<NodeId> {
+        let ((_, root_kind), path) = key.0.split_first()?;
+        let mut node = self.ast.get_root();
+        if self.ast.get_node_value(node).to_string() != *root_kind {
+            return None;
+        }
+        for (index, kind) in path {
+            node = *self.ast.get_children(node).get(*index)?;
+            if self.ast.get_node_value(node).to_string() != *kind {
+                return None;
+            }
+        }
+        Some(node)
+    }
+
+    fn contains(&self, node: NodeId, position: (u32, u32)) -> bool {
+        self.span(node).is_some_and(|span| span.contains(position))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::syntactic::parser::tests::tokens_of;
+    use crate::syntactic::parser::Parser;
+
+    const SOURCE: &str = "func main() -> void
+{
+  let x: integer;
+  x = 1 + 2;
+}
+";
+
+    /// The labels of the selection ranges at the position, innermost first
+    fn labels_at(position: (u32, u32)) -> Vec<String> {
+        let (_, ast, spans, _) = Parser::new().parse(tokens_of(SOURCE)).unwrap();
+        let source_map = SourceMap::new(&ast, &spans);
+        source_map
+            .selection_ranges(position)
+            .into_iter()
+            .map(|(node, span)| format!("{} {}", span, ast.get_node_value(node).label()))
+            .collect()
+    }
+
+    #[test]
+    fn finds_the_declarations_from_their_keywords() {
+        assert_eq!(labels_at((1, 0)), ["1:0-5:1 FuncDef"]);
+        assert_eq!(
+            labels_at((3, 2)),
+            ["3:2-3:16 VarDecl", "2:0-5:1 FuncBody", "1:0-5:1 FuncDef"]
+        );
+    }
+
+    #[test]
+    fn expands_from_the_innermost_node() {
+        assert_eq!(
+            labels_at((4, 6)),
+            [
+                "4:6-4:7 IntLit: 1",
+                "4:6-4:11 AddExpr",
+                "4:2-4:11 Assign",
+                "2:0-5:1 FuncBody",
+                "1:0-5:1 FuncDef"
+            ]
+        );
+        // braces belong to the body, not to a statement
+        assert_eq!(labels_at((5, 0)), ["2:0-5:1 FuncBody", "1:0-5:1 FuncDef"]);
+    }
+
+    #[test]
+    fn finds_no_node_outside_the_program() {
+        let (_, ast, spans, _) = Parser::new().parse(tokens_of(SOURCE)).unwrap();
+        let source_map = SourceMap::new(&ast, &spans);
+        assert_eq!(source_map.node_at((9, 0)), None);
+        assert!(source_map.selection_ranges((9, 0)).is_empty());
+        assert_eq!(
+            source_map
+                .node_at((1, 0))
+                .and_then(|node| source_map.span(node)),
+            Some(Span::new((1, 0), (5, 1)))
+        );
+    }
+
+    #[test]
+    fn finds_a_node_again_after_an_edit() {
+        let (_, ast, spans, _) = Parser::new().parse(tokens_of(SOURCE)).unwrap();
+        let source_map = SourceMap::new(&ast, &spans);
+        let key = source_map.key(source_map.node_at((4, 6)).unwrap());
+        assert_eq!(source_map.find(&key), source_map.node_at((4, 6)));
+
+        // the literal is changed and the statement moved down
+        let edited = SOURCE.replace("  x = 1 + 2;", "\n  // one more\n  x = 10 +   2;");
+        let (_, edited_ast, edited_spans, _) = Parser::new().parse(tokens_of(&edited)).unwrap();
+        let edited_map = SourceMap::new(&edited_ast, &edited_spans);
+        let node = edited_map.find(&key).unwrap();
+        assert_eq!(edited_ast.get_node_value(node).label(), "IntLit: 10");
+        assert_eq!(edited_map.span(node), Some(Span::new((6, 6), (6, 8))));
+
+        // a statement inserted before it changes its place among its siblings
+        let edited = SOURCE.replace("  x = 1 + 2;", "  x = 0;\n  x = 1 + 2;");
+        let (_, edited_ast, edited_spans, _) = Parser::new().parse(tokens_of(&edited)).unwrap();
+        let edited_map = SourceMap::new(&edited_ast, &edited_spans);
+        assert_eq!(
+            edited_map
+                .find(&key)
+                .map(|node| edited_ast.get_node_value(node).label()),
+            None
+        );
+    }
+}
@@ -1,7 +1,9 @@
-use crate::semantic::ast::{create_table, AbstractSyntaxTree};
-use crate::semantic::concept::{AtomicConceptType, CompositeConcept, Concept};
-use crate::semantic::semantic_error::{SemanticErrType, SemanticError};
-use crate::syntactic::tree::NodeId;
+use crate::semantic::ast::{create_func_table, create_struct_table};
+use crate::semantic::constant_folding::ConstantValue;
+use crate::semantic::semantic_error::SemanticError;
+use crate::semantic::typed_ast::{
+    BaseType, FuncDecl, FuncDef, Param, StructDecl, TypeSpec, VarDecl,
+};
 use std::collections::HashMap;
 use std::fmt::{Display, Formatter};
 
@@ -30,8 +32,8 @@ impl SymbolTable {
     pub fn get_all_entries_by_name(&self, entry_name: &str) -> Vec<SymbolTableEntry> {
         self.entries
             .values()
-            .cloned()
             .filter(|entry| entry.name.eq(entry_name))
+            .cloned()
             .collect::<Vec<SymbolTableEntry>>()
     }
 
@@ -51,12 +53,32 @@ impl SymbolTable {
     pub fn get_all_entries_by_kind(&self, symbol_kind: SymbolKind) -> Vec<SymbolTableEntry> {
         self.entries
             .values()
-            .cloned()
             .filter(|entry| entry.kind.eq(&symbol_kind))
+            .cloned()
             .collect::<Vec<SymbolTableEntry>>()
     }
 
-    pub fn insert(&mut self, entry: SymbolTableEntry) -> Option<SymbolTableEntry> {
+    /// Give a variable declared without a type the one inferred from its initializer
+    pub fn set_inferred_type(&mut self, entry_name: &str, type_name: String) {
+        let key = self
+            .entries
+            .keys()
+            .find(|key| key.0.eq(entry_name))
+            .cloned();
+        if let Some(mut entry) = key.and_then(|key| self.entries.remove(&key)) {
+            entry.symbol_type = SymbolType { name: type_name };
+            self.entries
+                .insert((entry.name.clone(), entry.symbol_type.clone()), entry);
+        }
+    }
+
+    /// Insert an entry, unless it is a duplicate. Errors and warnings are added to
+    /// `errors`.
+    pub fn insert(
+        &mut self,
+        entry: SymbolTableEntry,
+        errors: &mut Vec<SemanticError>,
+    ) -> Option<SymbolTableEntry> {
         let key = (entry.name.clone(), entry.symbol_type.clone());
         if matches!(entry.kind, SymbolKind::Function) {
             // to insert function
@@ -66,40 +88,41 @@ impl SymbolTable {
                     self.entries.insert(key, entry)
                 } else {
                     // existing entry has link: duplicate definition
-                    SemanticError::report_error(&format!(
+                    errors.push(SemanticError::error(&format!(
                         "function {} of the same type is already defined.",
                         &entry.name
-                    ));
+                    )));
                     None
                 }
             } else {
                 if entry.link.is_none() {
                     // no existing entry and new entry has no link: function decl
                     if self.entries.keys().any(|key| key.0.eq(&entry.name)) {
-                        SemanticError::report(
-                            SemanticErrType::Warning,
-                            &format!("function {} is overloaded", &entry.name),
-                        );
+                        errors.push(SemanticError::warning(&format!(
+                            "function {} is overloaded",
+                            &entry.name
+                        )));
                     }
                     self.entries.insert(key, entry)
+                } else if self.name.eq("global") {
+                    self.entries.insert(key, entry)
                 } else {
-                    return if self.name.eq("global") {
-                        self.entries.insert(key, entry)
-                    } else {
-                        // no existing entry and new entry has link: impl without decl
-                        SemanticError::report_error(&format!(
-                            "definition provided for undeclared function {}. ",
-                            &entry.name
-                        ));
-                        None
-                    };
+                    // no existing entry and new entry has link: impl without decl
+                    errors.push(SemanticError::error(&format!(
+                        "definition provided for undeclared function {}. ",
+                        &entry.name
+                    )));
+                    None
                 }
             }
         } else {
             // insert entries other than function
             if self.entries.keys().any(|key| key.0.eq(&entry.name)) {
                 // name is already in the table: duplicate definition
-                SemanticError::report_error(&format!("{} is already defined. ", &entry.name));
+                errors.push(SemanticError::error(&format!(
+                    "{} is already defined. ",
+                    &entry.name
+                )));
                 None
             } else {
                 // name is new, then key must be new
@@ -111,32 +134,31 @@ impl SymbolTable {
 
 impl Display for SymbolTable {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(
+        writeln!(
             f,
-            "=========================================================\n"
-        );
-        write!(f, "{}\n", self.name);
-        write!(
+            "========================================================="
+        )?;
+        writeln!(f, "{}", self.name)?;
+        writeln!(
             f,
-            "---------------------------------------------------------\n"
-        );
-        write!(
+            "---------------------------------------------------------"
+        )?;
+        writeln!(
             f,
-            "{0: <14} | {1: <14} | {2: <20} | {3: <14}\n",
+            "{0: <14} | {1: <14} | {2: <20} | {3: <14}",
             "name", "kind", "type", "link"
-        );
-        write!(
+        )?;
+        writeln!(
             f,
-            "---------------------------------------------------------\n"
-        );
+            "---------------------------------------------------------"
+        )?;
         for (_, entry) in self.entries.iter() {
-            write!(f, "{}\n", entry);
+            writeln!(f, "{}", entry)?;
         }
-        write!(
+        writeln!(
             f,
-            "=========================================================\n"
-        );
-        Ok(())
+            "========================================================="
+        )
     }
 }
 
@@ -149,132 +171,72 @@ pub struct SymbolTableEntry {
 }
 
 impl SymbolTableEntry {
-    pub fn from_node(
-        node: NodeId,
-        ast: &AbstractSyntaxTree,
+    /// The entry of a function definition, linked to the table of its body
+    pub fn from_func_def(
+        func_def: &FuncDef,
         table_container: &mut HashMap<String, SymbolTable>,
-        name_prefix: String,
-    ) -> Option<Self> {
-        let concept = ast.get_node_value(node);
-        match concept.get_composite_concept() {
-            CompositeConcept::FuncDef => {
-                let func_def_elements = ast.get_children(node);
-
-                let name = ast
-                    .get_node_value(func_def_elements[0])
-                    .get_atomic_concept()
-                    .get_value();
-
-                Some(SymbolTableEntry {
-                    name,
-                    kind: SymbolKind::Function,
-                    symbol_type: SymbolType::from_node(node, ast), // fParams type
-                    link: Some(create_table(ast, node, table_container, name_prefix)), // funcDef table
-                })
-            }
-            CompositeConcept::FuncDecl => {
-                let func_decl_elements = ast.get_children(node);
-                let name = ast
-                    .get_node_value(func_decl_elements[0])
-                    .get_atomic_concept()
-                    .get_value();
+        name_prefix: &str,
+        errors: &mut Vec<SemanticError>,
+    ) -> Self {
+        SymbolTableEntry {
+            name: func_def.name.name.clone(),
+            kind: SymbolKind::Function,
+            symbol_type: SymbolType::of_function(&func_def.params, &func_def.return_type),
+            link: Some(create_func_table(
+                func_def,
+                table_container,
+                name_prefix,
+                errors,
+            )),
+        }
+    }
 
-                Some(SymbolTableEntry {
-                    name,
-                    kind: SymbolKind::Function,
-                    symbol_type: SymbolType::from_node(node, ast),
-                    link: None,
-                })
-            }
-            CompositeConcept::StructDecl => {
-                let struct_decl_elements = ast.get_children(node);
-                let name = ast
-                    .get_node_value(struct_decl_elements[0])
-                    .get_atomic_concept()
-                    .get_value();
+    /// The entry of a member function declared in a struct, linked once it is implemented
+    pub fn from_func_decl(func_decl: &FuncDecl) -> Self {
+        SymbolTableEntry {
+            name: func_decl.name.name.clone(),
+            kind: SymbolKind::Function,
+            symbol_type: SymbolType::of_function(&func_decl.params, &func_decl.return_type),
+            link: None,
+        }
+    }
 
-                Some(SymbolTableEntry {
-                    name: name.clone(),
-                    kind: SymbolKind::Class,
-                    symbol_type: SymbolType { name },
-                    link: Some(create_table(ast, node, table_container, name_prefix)),
-                })
-            }
-            CompositeConcept::VarDecl => {
-                let var_decl_elements = ast.get_children(node);
-                let name = ast
-                    .get_node_value(var_decl_elements[0])
-                    .get_atomic_concept()
-                    .get_value();
-                Some(SymbolTableEntry {
-                    name,
-                    kind: SymbolKind::Variable,
-                    symbol_type: SymbolType::from_node(node, ast),
-                    link: None,
-                })
-            }
-            CompositeConcept::StructMemberDecl => {
-                let struct_member_decl_elements = ast.get_children(node);
-                Self::from_node(
-                    struct_member_decl_elements[1], // funcDecl or varDecl
-                    ast,
-                    table_container,
-                    name_prefix,
-                )
-            }
-            CompositeConcept::ImplDef => {
-                let impl_def_children = ast.get_children(node);
-                let target_struct_name = ast
-                    .get_node_value(impl_def_children[0])
-                    .get_atomic_concept()
-                    .get_value();
-                let table_name = format!("{}:{}", name_prefix, target_struct_name);
+    /// The entry of a struct, linked to the table of its members
+    pub fn from_struct_decl(
+        struct_decl: &StructDecl,
+        table_container: &mut HashMap<String, SymbolTable>,
+        name_prefix: &str,
+        errors: &mut Vec<SemanticError>,
+    ) -> Self {
+        let name = struct_decl.name.name.clone();
+        SymbolTableEntry {
+            name: name.clone(),
+            kind: SymbolKind::Class,
+            symbol_type: SymbolType { name },
+            link: Some(create_struct_table(
+                struct_decl,
+                table_container,
+                name_prefix,
+                errors,
+            )),
+        }
+    }
 
-                let mut new_entry_set = vec![];
-                for func_def_node in ast.get_children(impl_def_children[1]) {
-                    let new_entry = match SymbolTableEntry::from_node(
-                        func_def_node,
-                        ast,
-                        table_container,
-                        table_name.clone(),
-                    ) {
-                        None => {
-                            panic!("Something other than funcDef in impl")
-                        }
-                        Some(entry) => entry,
-                    };
-                    new_entry_set.push(new_entry);
-                }
+    pub fn from_var_decl(var_decl: &VarDecl) -> Self {
+        SymbolTableEntry {
+            name: var_decl.name.name.clone(),
+            kind: SymbolKind::Variable,
+            symbol_type: SymbolType::from_type_spec(&var_decl.var_type),
+            link: None,
+        }
+    }
 
-                match table_container.get_mut(&*table_name) {
-                    None => {
-                        SemanticError::report_error(&format!(
-                            "struct {} is undefined",
-                            target_struct_name
-                        ));
-                    }
-                    Some(table) => {
-                        for new_entry in new_entry_set {
-                            table.insert(new_entry);
-                        }
-                    }
-                }
-                None
-            }
-            CompositeConcept::FParam => {
-                let param_children = ast.get_children(node);
-                let name = ast
-                    .get_node_value(param_children[0])
-                    .get_atomic_concept()
-                    .get_value();
-                Some(SymbolTableEntry {
-                    name,
-                    kind: SymbolKind::Parameter,
-                    symbol_type: SymbolType::from_node(node, ast),
-                    link: None,
-                })
-            }
-            _ => None,
+    pub fn from_param(param: &Param) -> Self {
+        SymbolTableEntry {
+            name: param.name.name.clone(),
+            kind: SymbolKind::Parameter,
+            symbol_type: SymbolType::from_type_spec(&param.param_type),
+            link: None,
         }
     }
 }
@@ -302,6 +264,8 @@ pub enum SymbolKind {
     Parameter,
     Inherits,
     Class,
+    /// a `const` with its value folded at compile time
+    Constant(ConstantValue),
 }
 
 impl Display for SymbolKind {
@@ -315,6 +279,7 @@ impl Display for SymbolKind {
                 SymbolKind::Parameter => "Parameter",
                 SymbolKind::Inherits => "Inherits",
                 SymbolKind::Class => "Class",
+                SymbolKind::Constant(_) => "Constant",
             }
         )
     }
@@ -326,83 +291,51 @@ pub struct SymbolType {
 }
 
 impl SymbolType {
-    pub fn from_node(node: NodeId, ast: &AbstractSyntaxTree) -> Self {
-        let concept = ast.get_node_value(node);
-        let name = match concept {
-            Concept::AtomicConcept(ac) => match ac.atomic_concept_type {
-                AtomicConceptType::Float | AtomicConceptType::FloatLit => "float".to_string(),
-                AtomicConceptType::Integer
-                | AtomicConceptType::IntLit
-                | AtomicConceptType::EmptyArraySize => "integer".to_string(),
-                AtomicConceptType::Id => ac.get_value(),
-                AtomicConceptType::Void => "".to_string(),
-                _ => panic!("Error in getting atomic concept symbol type"),
-                // AtomicConceptType::Visibility => {}
-                // AtomicConceptType::Epsilon => {}
-                // | AtomicConceptType::RelOp
-                // | AtomicConceptType::MultiOp
-                // | AtomicConceptType::AddOp
-                // | AtomicConceptType::Sign
-            },
-            Concept::CompositeConcept(cc) => match cc {
-                CompositeConcept::ArraySizes => {
-                    let array_sizes_elements = ast.get_children(node);
-                    array_sizes_elements
-                        .iter()
-                        .map(|x| SymbolType::from_node(*x, ast).name)
-                        .map(|x| format!("[{}]", x))
-                        .collect::<Vec<String>>()
-                        .join("")
-                }
-                CompositeConcept::FParam => {
-                    let f_param_elements = ast.get_children(node);
-                    // get second node type text: type
-                    let type_symbol_type = SymbolType::from_node(f_param_elements[1], ast);
-                    let array_sizes_symbol_type = SymbolType::from_node(f_param_elements[2], ast);
-                    format!("{}{}", type_symbol_type.name, array_sizes_symbol_type.name)
-                }
-                CompositeConcept::FParams => {
-                    let f_params_elements = ast.get_children(node);
-                    f_params_elements
-                        .iter()
-                        .map(|x| SymbolType::from_node(*x, ast).name)
-                        .collect::<Vec<String>>()
-                        .join(",")
-                }
-                CompositeConcept::Type => {
-                    let type_children = ast.get_children(node);
-                    SymbolType::from_node(type_children[0], ast).name
-                }
-                CompositeConcept::VarDecl => {
-                    let var_decl_elements = ast.get_children(node);
-                    let type_symbol_type = SymbolType::from_node(var_decl_elements[1], ast);
-                    let array_sizes_symbol_type = SymbolType::from_node(var_decl_elements[2], ast);
-                    format!("{}{}", type_symbol_type.name, array_sizes_symbol_type.name)
-                }
-                CompositeConcept::FuncDecl | CompositeConcept::FuncDef => {
-                    let func_def_elements = ast.get_children(node);
-                    let f_params = SymbolType::from_node(func_def_elements[1], ast);
-                    let return_type = SymbolType::from_node(func_def_elements[2], ast);
-                    format!("{}:{}", return_type.name, f_params.name)
-                }
-                CompositeConcept::ImplDef => "".to_string(),
-                cc => panic!("Unhandled cc: {}", cc),
-            },
-        };
+    pub fn new(name: String) -> Self {
         Self { name }
     }
 
-    pub fn get_name(&self) -> String {
-        self.name.clone()
+    /// The type of a variable or a parameter, such as `integer[integer][integer]` for
+    /// `integer[2][3]`. Array sizes are left out, only the number of dimensions counts.
+    pub fn from_type_spec(type_spec: &TypeSpec) -> Self {
+        Self {
+            name: format!(
+                "{}{}",
+                Self::of_base(&type_spec.base).name,
+                "[integer]".repeat(type_spec.dimensions.len())
+            ),
+        }
+    }
+
+    /// The type of the elements of a variable, without its dimensions
+    pub fn of_base(base: &BaseType) -> Self {
+        let name = match base {
+            BaseType::Integer => "integer".to_string(),
+            BaseType::Float => "float".to_string(),
+            BaseType::Bool => "bool".to_string(),
+            BaseType::Class(name) => name.clone(),
+            BaseType::Void => "".to_string(),
+            // given once the initializer is typed, see `SymbolTable::set_inferred_type`
+            BaseType::Inferred => "".to_string(),
+        };
+        Self { name }
     }
 
-    fn is_f_params_same(&self, other: &SymbolType) -> bool {
-        if !self.name.contains(":") || !other.name.contains(":") {
-            panic!("Not function type")
+    /// The type of a function, its return type and the types of its parameters such as
+    /// `float:integer,float[integer]`
+    pub fn of_function(params: &[Param], return_type: &TypeSpec) -> Self {
+        let params = params
+            .iter()
+            .map(|param| Self::from_type_spec(&param.param_type).name)
+            .collect::<Vec<String>>()
+            .join(",");
+        Self {
+            name: format!("{}:{}", Self::from_type_spec(return_type).name, params),
         }
-        let self_param_split = self.name.split(":").collect::<Vec<&str>>();
-        let other_param_split = other.name.split(":").collect::<Vec<&str>>();
-        self_param_split[1].eq(other_param_split[1])
+    }
+
+    pub fn get_name(&self) -> String {
+        self.name.clone()
     }
 }
 
@@ -0,0 +1,712 @@
+use crate::lexical::span::Span;
+use crate::semantic::ast::{contains_error, AbstractSyntaxTree};
+use crate::semantic::concept::{AtomicConceptType, CompositeConcept, Concept};
+use crate::syntactic::tree::NodeId;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+
+/// The AST with a type for each construct, lowered from the `Concept` tree so that
+/// later phases read named fields instead of indexing children. Every node has the
+/// source range it was parsed from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Program {
+    pub items: Vec<Item>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Item {
+    Struct(StructDecl),
+    Impl(ImplDef),
+    Func(FuncDef),
+    Var(VarDecl),
+    Const(ConstDecl),
+    /// a declaration broken by a syntax error
+    Error(Span),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ident {
+    pub name: String,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BaseType {
+    Integer,
+    Float,
+    Bool,
+    Void,
+    Class(String),
+    /// of a `let` without a type, given by the symbol table once its initializer is typed
+    Inferred,
+}
+
+/// A type as written in the source, such as `integer[3][]`
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypeSpec {
+    pub base: BaseType,
+    /// size of each dimension, `None` for `[]`
+    pub dimensions: Vec<Option<usize>>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StructDecl {
+    pub name: Ident,
+    pub inherits: Vec<Ident>,
+    pub members: Vec<MemberDecl>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Visibility {
+    Public,
+    Private,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MemberDecl {
+    pub visibility: Visibility,
+    pub member: Member,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Member {
+    Var(VarDecl),
+    Func(FuncDecl),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FuncDecl {
+    pub name: Ident,
+    pub params: Vec<Param>,
+    pub return_type: TypeSpec,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImplDef {
+    pub name: Ident,
+    pub funcs: Vec<FuncDef>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FuncDef {
+    pub name: Ident,
+    pub params: Vec<Param>,
+    pub return_type: TypeSpec,
+    pub body: Vec<Stmt>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Param {
+    pub name: Ident,
+    pub param_type: TypeSpec,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct VarDecl {
+    pub name: Ident,
+    pub var_type: TypeSpec,
+    pub initializer: Option<Initializer>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Initializer {
+    Expr(Expr),
+    /// `{1, 2, 3}`, nested for each further dimension
+    List(Vec<Initializer>, Span),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConstDecl {
+    pub name: Ident,
+    pub const_type: TypeSpec,
+    pub value: Expr,
+    pub span: Span,
+}
+
+/// The statements of a `{ }` block, or the single statement in its place
+#[derive(Debug, Clone, PartialEq)]
+pub struct Block {
+    pub stmts: Vec<Stmt>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stmt {
+    pub kind: StmtKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum StmtKind {
+    VarDecl(VarDecl),
+    Assign {
+        target: Expr,
+        value: Expr,
+    },
+    /// an expression evaluated for its effect, a function call
+    Expr(Expr),
+    If {
+        condition: Expr,
+        then_branch: Block,
+        /// a `Block`, or an `If` for an else-if
+        else_branch: Option<Box<Stmt>>,
+    },
+    While {
+        condition: Expr,
+        body: Block,
+    },
+    For {
+        variable: VarDecl,
+        initial_value: Expr,
+        condition: Expr,
+        step: Box<Stmt>,
+        body: Block,
+    },
+    Read(Expr),
+    Write(Expr),
+    Return(Expr),
+    Break,
+    Continue,
+    Block(Block),
+    /// a statement broken by a syntax error
+    Error,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expr {
+    pub kind: ExprKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExprKind {
+    IntLit(String),
+    FloatLit(String),
+    BoolLit(bool),
+    Id(Ident),
+    /// a variable or member with its indices, `a[1][2]`
+    Var {
+        base: Box<Expr>,
+        indices: Vec<Expr>,
+    },
+    /// `object.member`
+    Member {
+        object: Box<Expr>,
+        member: Ident,
+    },
+    Call {
+        callee: Box<Expr>,
+        args: Vec<Expr>,
+    },
+    Binary {
+        op: BinaryOp,
+        lhs: Box<Expr>,
+        rhs: Box<Expr>,
+    },
+    Unary {
+        op: UnaryOp,
+        operand: Box<Expr>,
+    },
+    /// an expression broken by a syntax error
+    Error,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    Eq,
+    NotEq,
+    Lt,
+    Gt,
+    Leq,
+    Geq,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    And,
+    Or,
+}
+
+impl BinaryOp {
+    pub fn from_lexeme(lexeme: &str) -> Option<Self> {
+        match lexeme {
+            "==" => Some(BinaryOp::Eq),
+            "<>" => Some(BinaryOp::NotEq),
+            "<" => Some(BinaryOp::Lt),
+            ">" => Some(BinaryOp::Gt),
+            "<=" => Some(BinaryOp::Leq),
+            ">=" => Some(BinaryOp::Geq),
+            "+" => Some(BinaryOp::Add),
+            "-" => Some(BinaryOp::Sub),
+            "*" => Some(BinaryOp::Mul),
+            "/" => Some(BinaryOp::Div),
+            "&" => Some(BinaryOp::And),
+            "|" => Some(BinaryOp::Or),
+            _ => None,
+        }
+    }
+
+    pub fn lexeme(&self) -> &'static str {
+        match self {
+            BinaryOp::Eq => "==",
+            BinaryOp::NotEq => "<>",
+            BinaryOp::Lt => "<",
+            BinaryOp::Gt => ">",
+            BinaryOp::Leq => "<=",
+            BinaryOp::Geq => ">=",
+            BinaryOp::Add => "+",
+            BinaryOp::Sub => "-",
+            BinaryOp::Mul => "*",
+            BinaryOp::Div => "/",
+            BinaryOp::And => "&",
+            BinaryOp::Or => "|",
+        }
+    }
+}
+
+impl Display for BinaryOp {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.lexeme())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnaryOp {
+    Not,
+    Plus,
+    Minus,
+}
+
+impl Display for UnaryOp {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let lexeme = match self {
+            UnaryOp::Not => "!",
+            UnaryOp::Plus => "+",
+            UnaryOp::Minus => "-",
+        };
+        write!(f, "{}", lexeme)
+    }
+}
+
+/// Lower the whole `Concept` tree. `spans` has the source range of its nodes, as
+/// recorded by the parser.
+pub fn lower(ast: &AbstractSyntaxTree, spans: &HashMap<NodeId, Span>) -> Program {
+    Lowering::new(ast, Some(spans)).program(ast.get_root())
+}
+
+/// Conversion of `Concept` nodes into typed nodes. Placeholders left by syntax errors
+/// become `Error` items, statements or expressions.
+pub struct Lowering<'a> {
+    ast: &'a AbstractSyntaxTree,
+    spans: Option<&'a HashMap<NodeId, Span>>,
+}
+
+impl<'a> Lowering<'a> {
+    /// Without `spans`, every node gets an empty span at the start of the source
+    pub fn new(ast: &'a AbstractSyntaxTree, spans: Option<&'a HashMap<NodeId, Span>>) -> Self {
+        Self { ast, spans }
+    }
+
+    pub fn program(&self, node: NodeId) -> Program {
+        Program {
+            items: self
+                .children(node)
+                .into_iter()
+                .map(|child| self.item(child))
+                .collect(),
+            span: self.span(node),
+        }
+    }
+
+    pub fn item(&self, node: NodeId) -> Item {
+        if contains_error(self.ast, node) {
+            return Item::Error(self.span(node));
+        }
+        match self.composite(node) {
+            Some(CompositeConcept::StructDecl) => Item::Struct(self.struct_decl(node)),
+            Some(CompositeConcept::ImplDef) => Item::Impl(self.impl_def(node)),
+            Some(CompositeConcept::FuncDef) => Item::Func(self.func_def(node)),
+            Some(CompositeConcept::VarDecl) => Item::Var(self.var_decl(node)),
+            Some(CompositeConcept::ConstDecl) => Item::Const(self.const_decl(node)),
+            _ => Item::Error(self.span(node)),
+        }
+    }
+
+    pub fn stmt(&self, node: NodeId) -> Stmt {
+        let span = self.span(node);
+        if contains_error(self.ast, node) {
+            return Stmt {
+                kind: StmtKind::Error,
+                span,
+            };
+        }
+        let children = self.children(node);
+        let kind = match self.composite(node) {
+            Some(CompositeConcept::VarDecl) => StmtKind::VarDecl(self.var_decl(node)),
+            Some(CompositeConcept::Assign) => StmtKind::Assign {
+                target: self.expr(children[0]),
+                value: self.expr(children[1]),
+            },
+            Some(CompositeConcept::FuncCall | CompositeConcept::Dot) => {
+                StmtKind::Expr(self.expr(node))
+            }
+            Some(CompositeConcept::IfThenElse) => StmtKind::If {
+                condition: self.expr(children[0]),
+                then_branch: self.block(children[1]),
+                else_branch: match self.atomic(children[2]) {
+                    Some(AtomicConceptType::NoElse) => None,
+                    _ if self.composite(children[2]) == Some(CompositeConcept::IfThenElse) => {
+                        Some(Box::new(self.stmt(children[2])))
+                    }
+                    _ => {
+                        let block = self.block(children[2]);
+                        Some(Box::new(Stmt {
+                            span: block.span,
+                            kind: StmtKind::Block(block),
+                        }))
+                    }
+                },
+            },
+            Some(CompositeConcept::While) => StmtKind::While {
+                condition: self.expr(children[0]),
+                body: self.block(children[1]),
+            },
+            Some(CompositeConcept::For) => StmtKind::For {
+                variable: self.var_decl(children[0]),
+                initial_value: self.expr(children[1]),
+                condition: self.expr(children[2]),
+                step: Box::new(self.stmt(children[3])),
+                body: self.block(children[4]),
+            },
+            Some(CompositeConcept::Read) => StmtKind::Read(self.expr(children[0])),
+            Some(CompositeConcept::Write) => StmtKind::Write(self.expr(children[0])),
+            Some(CompositeConcept::Return) => StmtKind::Return(self.expr(children[0])),
+            Some(CompositeConcept::Break) => StmtKind::Break,
+            Some(CompositeConcept::Continue) => StmtKind::Continue,
+            Some(CompositeConcept::StmtBlock) => StmtKind::Block(self.block(node)),
+            _ => StmtKind::Error,
+        };
+        Stmt { kind, span }
+    }
+
+    pub fn expr(&self, node: NodeId) -> Expr {
+        let span = self.span(node);
+        let children = self.children(node);
+        let kind = match self.ast.get_node_value(node) {
+            Concept::AtomicConcept(ac) => match ac.atomic_concept_type {
+                AtomicConceptType::IntLit => ExprKind::IntLit(ac.get_value()),
+                AtomicConceptType::FloatLit => ExprKind::FloatLit(ac.get_value()),
+                AtomicConceptType::BoolLit => ExprKind::BoolLit(ac.value == "true"),
+                AtomicConceptType::Id => ExprKind::Id(self.ident(node)),
+                _ => ExprKind::Error,
+            },
+            Concept::CompositeConcept(cc) => match cc {
+                // a variable without indices is its name alone
+                CompositeConcept::Var if self.children(children[1]).is_empty() => {
+                    return self.expr(children[0]);
+                }
+                CompositeConcept::Var => ExprKind::Var {
+                    base: Box::new(self.expr(children[0])),
+                    indices: self
+                        .children(children[1])
+                        .into_iter()
+                        .map(|index| self.expr(index))
+                        .collect(),
+                },
+                CompositeConcept::Dot
+                    if self.atomic(children[1]) == Some(AtomicConceptType::Id) =>
+                {
+                    ExprKind::Member {
+                        object: Box::new(self.expr(children[0])),
+                        member: self.ident(children[1]),
+                    }
+                }
+                CompositeConcept::FuncCall => ExprKind::Call {
+                    callee: Box::new(self.expr(children[0])),
+                    args: self
+                        .children(children[1])
+                        .into_iter()
+                        .map(|arg| self.expr(arg))
+                        .collect(),
+                },
+                CompositeConcept::RelExpr
+                | CompositeConcept::AddExpr
+                | CompositeConcept::MultExpr
+                | CompositeConcept::AndExpr
+                | CompositeConcept::OrExpr => {
+                    match BinaryOp::from_lexeme(&self.value(children[1])) {
+                        Some(op) => ExprKind::Binary {
+                            op,
+                            lhs: Box::new(self.expr(children[0])),
+                            rhs: Box::new(self.expr(children[2])),
+                        },
+                        None => ExprKind::Error,
+                    }
+                }
+                CompositeConcept::NotExpr => ExprKind::Unary {
+                    op: UnaryOp::Not,
+                    operand: Box::new(self.expr(children[0])),
+                },
+                CompositeConcept::SignedExpr => ExprKind::Unary {
+                    op: if self.value(children[0]) == "-" {
+                        UnaryOp::Minus
+                    } else {
+                        UnaryOp::Plus
+                    },
+                    operand: Box::new(self.expr(children[1])),
+                },
+                _ => ExprKind::Error,
+            },
+        };
+        Expr { kind, span }
+    }
+
+    fn block(&self, node: NodeId) -> Block {
+        let stmts = match self.composite(node) {
+            Some(CompositeConcept::StmtBlock) => self
+                .children(node)
+                .into_iter()
+                .map(|stmt| self.stmt(stmt))
+                .collect(),
+            _ => vec![self.stmt(node)],
+        };
+        Block {
+            stmts,
+            span: self.span(node),
+        }
+    }
+
+    fn struct_decl(&self, node: NodeId) -> StructDecl {
+        let children = self.children(node);
+        StructDecl {
+            name: self.ident(children[0]),
+            inherits: self
+                .children(children[1])
+                .into_iter()
+                .map(|inherit| self.ident(inherit))
+                .collect(),
+            members: self
+                .children(children[2])
+                .into_iter()
+                .map(|member_decl| {
+                    let member_children = self.children(member_decl);
+                    MemberDecl {
+                        visibility: if self.value(member_children[0]) == "private" {
+                            Visibility::Private
+                        } else {
+                            Visibility::Public
+                        },
+                        member: match self.composite(member_children[1]) {
+                            Some(CompositeConcept::FuncDecl) => {
+                                Member::Func(self.func_decl(member_children[1]))
+                            }
+                            _ => Member::Var(self.var_decl(member_children[1])),
+                        },
+                        span: self.span(member_decl),
+                    }
+                })
+                .collect(),
+            span: self.span(node),
+        }
+    }
+
+    fn impl_def(&self, node: NodeId) -> ImplDef {
+        let children = self.children(node);
+        ImplDef {
+            name: self.ident(children[0]),
+            funcs: self
+                .children(children[1])
+                .into_iter()
+                .map(|func_def| self.func_def(func_def))
+                .collect(),
+            span: self.span(node),
+        }
+    }
+
+    fn func_decl(&self, node: NodeId) -> FuncDecl {
+        let children = self.children(node);
+        FuncDecl {
+            name: self.ident(children[0]),
+            params: self.params(children[1]),
+            return_type: self.type_spec(children[2], None),
+            span: self.span(node),
+        }
+    }
+
+    fn func_def(&self, node: NodeId) -> FuncDef {
+        let children = self.children(node);
+        FuncDef {
+            name: self.ident(children[0]),
+            params: self.params(children[1]),
+            return_type: self.type_spec(children[2], None),
+            body: self
+                .children(children[3])
+                .into_iter()
+                .map(|stmt| self.stmt(stmt))
+                .collect(),
+            span: self.span(node),
+        }
+    }
+
+    fn params(&self, node: NodeId) -> Vec<Param> {
+        self.children(node)
+            .into_iter()
+            .map(|param| {
+                let children = self.children(param);
+                Param {
+                    name: self.ident(children[0]),
+                    param_type: self.type_spec(children[1], Some(children[2])),
+                    span: self.span(param),
+                }
+            })
+            .collect()
+    }
+
+    fn var_decl(&self, node: NodeId) -> VarDecl {
+        let children = self.children(node);
+        VarDecl {
+            name: self.ident(children[0]),
+            var_type: self.type_spec(children[1], Some(children[2])),
+            initializer: children.get(3).map(|init| self.initializer(*init)),
+            span: self.span(node),
+        }
+    }
+
+    fn initializer(&self, node: NodeId) -> Initializer {
+        match self.composite(node) {
+            Some(CompositeConcept::InitList) => Initializer::List(
+                self.children(node)
+                    .into_iter()
+                    .map(|element| self.initializer(element))
+                    .collect(),
+                self.span(node),
+            ),
+            _ => Initializer::Expr(self.expr(node)),
+        }
+    }
+
+    fn const_decl(&self, node: NodeId) -> ConstDecl {
+        let children = self.children(node);
+        ConstDecl {
+            name: self.ident(children[0]),
+            const_type: self.type_spec(children[1], None),
+            value: self.expr(children[2]),
+            span: self.span(node),
+        }
+    }
+
+    /// The type of a `Type` node or of the `Void` atom, with the sizes of `array_sizes`
+    fn type_spec(&self, type_node: NodeId, array_sizes: Option<NodeId>) -> TypeSpec {
+        // the atom is under a Type node, except for void
+        let atom = match self.composite(type_node) {
+            Some(CompositeConcept::Type) => self.children(type_node)[0],
+            _ => type_node,
+        };
+        let base = match self.atomic(atom) {
+            Some(AtomicConceptType::Integer) => BaseType::Integer,
+            Some(AtomicConceptType::Float) => BaseType::Float,
+            Some(AtomicConceptType::Bool) => BaseType::Bool,
+            Some(AtomicConceptType::InferredType) => BaseType::Inferred,
+            Some(AtomicConceptType::Id) => BaseType::Class(self.value(atom)),
+            _ => BaseType::Void,
+        };
+        let mut span = self.span(type_node);
+        let mut dimensions = vec![];
+        if let Some(array_sizes) = array_sizes {
+            for size in self.children(array_sizes) {
+                span = span.merge(&self.span(size));
+                dimensions.push(self.value(size).parse().ok());
+            }
+        }
+        TypeSpec {
+            base,
+            dimensions,
+            span,
+        }
+    }
+
+    fn ident(&self, node: NodeId) -> Ident {
+        Ident {
+            name: self.value(node),
+            span: self.span(node),
+        }
+    }
+
+    /// The recorded span of the node, or else the one of its closest ancestor with one,
+    /// for placeholders made during error recovery
+    fn span(&self, node: NodeId) -> Span {
+        let spans = match self.spans {
+            Some(spans) => spans,
+            None => return Span::new((0, 0), (0, 0)),
+        };
+        let mut current = Some(node);
+        while let Some(node) = current {
+            if let Some(span) = spans.get(&node) {
+                return *span;
+            }
+            current = self.ast.get_parent(node);
+        }
+        Span::new((0, 0), (0, 0))
+    }
+
+    fn children(&self, node: NodeId) -> Vec<NodeId> {
+        self.ast.get_children(node)
+    }
+
+    fn composite(&self, node: NodeId) -> Option<CompositeConcept> {
+        match self.ast.get_node_value(node) {
+            Concept::CompositeConcept(cc) => Some(cc.clone()),
+            Concept::AtomicConcept(_) => None,
+        }
+    }
+
+    fn atomic(&self, node: NodeId) -> Option<AtomicConceptType> {
+        match self.ast.get_node_value(node) {
+            Concept::AtomicConcept(ac) => Some(ac.atomic_concept_type.clone()),
+            Concept::CompositeConcept(_) => None,
+        }
+    }
+
+    /// The value of an atom, empty for a composite node
+    fn value(&self, node: NodeId) -> String {
+        match self.ast.get_node_value(node) {
+            Concept::AtomicConcept(ac) => ac.get_value(),
+            Concept::CompositeConcept(_) => "".to_string(),
+        }
+    }
+}
+
+impl Display for BaseType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BaseType::Integer => write!(f, "integer"),
+            BaseType::Float => write!(f, "float"),
+            BaseType::Bool => write!(f, "bool"),
+            BaseType::Void => write!(f, "void"),
+            BaseType::Class(name) => write!(f, "{}", name),
+            BaseType::Inferred => write!(f, "_"),
+        }
+    }
+}
+
+impl Display for TypeSpec {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.base)?;
+        for dimension in self.dimensions.iter() {
+            match dimension {
+                Some(size) => write!(f, "[{}]", size)?,
+                None => write!(f, "[]")?,
+            }
+        }
+        Ok(())
+    }
+}
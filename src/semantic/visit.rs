@@ -0,0 +1,852 @@
+use crate::semantic::typed_ast::{
+    Block, ConstDecl, Expr, ExprKind, FuncDecl, FuncDef, Ident, ImplDef, Initializer, Item, Member,
+    MemberDecl, Param, Program, Stmt, StmtKind, StructDecl, TypeSpec, VarDecl,
+};
+
+/// Traversal of the typed AST. Each method visits the children of its node through the
+/// matching `walk_` function, so a pass only overrides the nodes it cares about and
+/// calls `walk_` itself to go on below them.
+pub trait Visitor<'ast>: Sized {
+    fn visit_program(&mut self, program: &'ast Program) {
+        walk_program(self, program)
+    }
+
+    fn visit_item(&mut self, item: &'ast Item) {
+        walk_item(self, item)
+    }
+
+    fn visit_struct_decl(&mut self, struct_decl: &'ast StructDecl) {
+        walk_struct_decl(self, struct_decl)
+    }
+
+    fn visit_member_decl(&mut self, member_decl: &'ast MemberDecl) {
+        walk_member_decl(self, member_decl)
+    }
+
+    fn visit_impl_def(&mut self, impl_def: &'ast ImplDef) {
+        walk_impl_def(self, impl_def)
+    }
+
+    fn visit_func_decl(&mut self, func_decl: &'ast FuncDecl) {
+        walk_func_decl(self, func_decl)
+    }
+
+    fn visit_func_def(&mut self, func_def: &'ast FuncDef) {
+        walk_func_def(self, func_def)
+    }
+
+    fn visit_param(&mut self, param: &'ast Param) {
+        walk_param(self, param)
+    }
+
+    fn visit_var_decl(&mut self, var_decl: &'ast VarDecl) {
+        walk_var_decl(self, var_decl)
+    }
+
+    fn visit_initializer(&mut self, initializer: &'ast Initializer) {
+        walk_initializer(self, initializer)
+    }
+
+    fn visit_const_decl(&mut self, const_decl: &'ast ConstDecl) {
+        walk_const_decl(self, const_decl)
+    }
+
+    fn visit_block(&mut self, block: &'ast Block) {
+        walk_block(self, block)
+    }
+
+    fn visit_stmt(&mut self, stmt: &'ast Stmt) {
+        walk_stmt(self, stmt)
+    }
+
+    fn visit_expr(&mut self, expr: &'ast Expr) {
+        walk_expr(self, expr)
+    }
+
+    fn visit_type_spec(&mut self, _type_spec: &'ast TypeSpec) {}
+
+    fn visit_ident(&mut self, _ident: &'ast Ident) {}
+}
+
+pub fn walk_program<'ast, V: Visitor<'ast>>(visitor: &mut V, program: &'ast Program) {
+    for item in program.items.iter() {
+        visitor.visit_item(item);
+    }
+}
+
+pub fn walk_item<'ast, V: Visitor<'ast>>(visitor: &mut V, item: &'ast Item) {
+    match item {
+        Item::Struct(struct_decl) => visitor.visit_struct_decl(struct_decl),
+        Item::Impl(impl_def) => visitor.visit_impl_def(impl_def),
+        Item::Func(func_def) => visitor.visit_func_def(func_def),
+        Item::Var(var_decl) => visitor.visit_var_decl(var_decl),
+        Item::Const(const_decl) => visitor.visit_const_decl(const_decl),
+        Item::Error(_) => {}
+    }
+}
+
+pub fn walk_struct_decl<'ast, V: Visitor<'ast>>(visitor: &mut V, struct_decl: &'ast StructDecl) {
+    visitor.visit_ident(&struct_decl.name);
+    for inherit in struct_decl.inherits.iter() {
+        visitor.visit_ident(inherit);
+    }
+    for member_decl in struct_decl.members.iter() {
+        visitor.visit_member_decl(member_decl);
+    }
+}
+
+pub fn walk_member_decl<'ast, V: Visitor<'ast>>(visitor: &mut V, member_decl: &'ast MemberDecl) {
+    match &member_decl.member {
+        Member::Var(var_decl) => visitor.visit_var_decl(var_decl),
+        Member::Func(func_decl) => visitor.visit_func_decl(func_decl),
+    }
+}
+
+pub fn walk_impl_def<'ast, V: Visitor<'ast>>(visitor: &mut V, impl_def: &'ast ImplDef) {
+    visitor.visit_ident(&impl_def.name);
+    for func_def in impl_def.funcs.iter() {
+        visitor.visit_func_def(func_def);
+    }
+}
+
+pub fn walk_func_decl<'ast, V: Visitor<'ast>>(visitor: &mut V, func_decl: &'ast FuncDecl) {
+    visitor.visit_ident(&func_decl.name);
+    for param in func_decl.params.iter() {
+        visitor.visit_param(param);
+    }
+    visitor.visit_type_spec(&func_decl.return_type);
+}
+
+pub fn walk_func_def<'ast, V: Visitor<'ast>>(visitor: &mut V, func_def: &'ast FuncDef) {
+    visitor.visit_ident(&func_def.name);
+    for param in func_def.params.iter() {
+        visitor.visit_param(param);
+    }
+    visitor.visit_type_spec(&func_def.return_type);
+    for stmt in func_def.body.iter() {
+        visitor.visit_stmt(stmt);
+    }
+}
+
+pub fn walk_param<'ast, V: Visitor<'ast>>(visitor: &mut V, param: &'ast Param) {
+    visitor.visit_ident(&param.name);
+    visitor.visit_type_spec(&param.param_type);
+}
+
+pub fn walk_var_decl<'ast, V: Visitor<'ast>>(visitor: &mut V, var_decl: &'ast VarDecl) {
+    visitor.visit_ident(&var_decl.name);
+    visitor.visit_type_spec(&var_decl.var_type);
+    if let Some(initializer) = &var_decl.initializer {
+        visitor.visit_initializer(initializer);
+    }
+}
+
+pub fn walk_initializer<'ast, V: Visitor<'ast>>(visitor: &mut V, initializer: &'ast Initializer) {
+    match initializer {
+        Initializer::Expr(expr) => visitor.visit_expr(expr),
+        Initializer::List(elements, _) => {
+            for element in elements.iter() {
+                visitor.visit_initializer(element);
+            }
+        }
+    }
+}
+
+pub fn walk_const_decl<'ast, V: Visitor<'ast>>(visitor: &mut V, const_decl: &'ast ConstDecl) {
+    visitor.visit_ident(&const_decl.name);
+    visitor.visit_type_spec(&const_decl.const_type);
+    visitor.visit_expr(&const_decl.value);
+}
+
+pub fn walk_block<'ast, V: Visitor<'ast>>(visitor: &mut V, block: &'ast Block) {
+    for stmt in block.stmts.iter() {
+        visitor.visit_stmt(stmt);
+    }
+}
+
+pub fn walk_stmt<'ast, V: Visitor<'ast>>(visitor: &mut V, stmt: &'ast Stmt) {
+    match &stmt.kind {
+        StmtKind::VarDecl(var_decl) => visitor.visit_var_decl(var_decl),
+        StmtKind::Assign { target, value } => {
+            visitor.visit_expr(target);
+            visitor.visit_expr(value);
+        }
+        StmtKind::Expr(expr)
+        | StmtKind::Read(expr)
+        | StmtKind::Write(expr)
+        | StmtKind::Return(expr) => visitor.visit_expr(expr),
+        StmtKind::If {
+            condition,
+            then_branch,
+            else_branch,
+        } => {
+            visitor.visit_expr(condition);
+            visitor.visit_block(then_branch);
+            if let Some(else_branch) = else_branch {
+                visitor.visit_stmt(else_branch);
+            }
+        }
+        StmtKind::While { condition, body } => {
+            visitor.visit_expr(condition);
+            visitor.visit_block(body);
+        }
+        StmtKind::For {
+            variable,
+            initial_value,
+            condition,
+            step,
+            body,
+        } => {
+            visitor.visit_var_decl(variable);
+            visitor.visit_expr(initial_value);
+            visitor.visit_expr(condition);
+            visitor.visit_stmt(step);
+            visitor.visit_block(body);
+        }
+        StmtKind::Block(block) => visitor.visit_block(block),
+        StmtKind::Break | StmtKind::Continue | StmtKind::Error => {}
+    }
+}
+
+pub fn walk_expr<'ast, V: Visitor<'ast>>(visitor: &mut V, expr: &'ast Expr) {
+    match &expr.kind {
+        ExprKind::Id(id) => visitor.visit_ident(id),
+        ExprKind::Var { base, indices } => {
+            visitor.visit_expr(base);
+            for index in indices.iter() {
+                visitor.visit_expr(index);
+            }
+        }
+        ExprKind::Member { object, member } => {
+            visitor.visit_expr(object);
+            visitor.visit_ident(member);
+        }
+        ExprKind::Call { callee, args } => {
+            visitor.visit_expr(callee);
+            for arg in args.iter() {
+                visitor.visit_expr(arg);
+            }
+        }
+        ExprKind::Binary { lhs, rhs, .. } => {
+            visitor.visit_expr(lhs);
+            visitor.visit_expr(rhs);
+        }
+        ExprKind::Unary { operand, .. } => visitor.visit_expr(operand),
+        ExprKind::IntLit(_) | ExprKind::FloatLit(_) | ExprKind::BoolLit(_) | ExprKind::Error => {}
+    }
+}
+
+/// Traversal of the typed AST that may change nodes in place. It goes through the
+/// nodes in the same order as `Visitor`.
+pub trait VisitorMut: Sized {
+    fn visit_program_mut(&mut self, program: &mut Program) {
+        walk_program_mut(self, program)
+    }
+
+    fn visit_item_mut(&mut self, item: &mut Item) {
+        walk_item_mut(self, item)
+    }
+
+    fn visit_struct_decl_mut(&mut self, struct_decl: &mut StructDecl) {
+        walk_struct_decl_mut(self, struct_decl)
+    }
+
+    fn visit_member_decl_mut(&mut self, member_decl: &mut MemberDecl) {
+        walk_member_decl_mut(self, member_decl)
+    }
+
+    fn visit_impl_def_mut(&mut self, impl_def: &mut ImplDef) {
+        walk_impl_def_mut(self, impl_def)
+    }
+
+    fn visit_func_decl_mut(&mut self, func_decl: &mut FuncDecl) {
+        walk_func_decl_mut(self, func_decl)
+    }
+
+    fn visit_func_def_mut(&mut self, func_def: &mut FuncDef) {
+        walk_func_def_mut(self, func_def)
+    }
+
+    fn visit_param_mut(&mut self, param: &mut Param) {
+        walk_param_mut(self, param)
+    }
+
+    fn visit_var_decl_mut(&mut self, var_decl: &mut VarDecl) {
+        walk_var_decl_mut(self, var_decl)
+    }
+
+    fn visit_initializer_mut(&mut self, initializer: &mut Initializer) {
+        walk_initializer_mut(self, initializer)
+    }
+
+    fn visit_const_decl_mut(&mut self, const_decl: &mut ConstDecl) {
+        walk_const_decl_mut(self, const_decl)
+    }
+
+    fn visit_block_mut(&mut self, block: &mut Block) {
+        walk_block_mut(self, block)
+    }
+
+    fn visit_stmt_mut(&mut self, stmt: &mut Stmt) {
+        walk_stmt_mut(self, stmt)
+    }
+
+    fn visit_expr_mut(&mut self, expr: &mut Expr) {
+        walk_expr_mut(self, expr)
+    }
+
+    fn visit_type_spec_mut(&mut self, _type_spec: &mut TypeSpec) {}
+
+    fn visit_ident_mut(&mut self, _ident: &mut Ident) {}
+}
+
+pub fn walk_program_mut<V: VisitorMut>(visitor: &mut V, program: &mut Program) {
+    for item in program.items.iter_mut() {
+        visitor.visit_item_mut(item);
+    }
+}
+
+pub fn walk_item_mut<V: VisitorMut>(visitor: &mut V, item: &mut Item) {
+    match item {
+        Item::Struct(struct_decl) => visitor.visit_struct_decl_mut(struct_decl),
+        Item::Impl(impl_def) => visitor.visit_impl_def_mut(impl_def),
+        Item::Func(func_def) => visitor.visit_func_def_mut(func_def),
+        Item::Var(var_decl) => visitor.visit_var_decl_mut(var_decl),
+        Item::Const(const_decl) => visitor.visit_const_decl_mut(const_decl),
+        Item::Error(_) => {}
+    }
+}
+
+pub fn walk_struct_decl_mut<V: VisitorMut>(visitor: &mut V, struct_decl: &mut StructDecl) {
+    visitor.visit_ident_mut(&mut struct_decl.name);
+    for inherit in struct_decl.inherits.iter_mut() {
+        visitor.visit_ident_mut(inherit);
+    }
+    for member_decl in struct_decl.members.iter_mut() {
+        visitor.visit_member_decl_mut(member_decl);
+    }
+}
+
+pub fn walk_member_decl_mut<V: VisitorMut>(visitor: &mut V, member_decl: &mut MemberDecl) {
+    match &mut member_decl.member {
+        Member::Var(var_decl) => visitor.visit_var_decl_mut(var_decl),
+        Member::Func(func_decl) => visitor.visit_func_decl_mut(func_decl),
+    }
+}
+
+pub fn walk_impl_def_mut<V: VisitorMut>(visitor: &mut V, impl_def: &mut ImplDef) {
+    visitor.visit_ident_mut(&mut impl_def.name);
+    for func_def in impl_def.funcs.iter_mut() {
+        visitor.visit_func_def_mut(func_def);
+    }
+}
+
+pub fn walk_func_decl_mut<V: VisitorMut>(visitor: &mut V, func_decl: &mut FuncDecl) {
+    visitor.visit_ident_mut(&mut func_decl.name);
+    for param in func_decl.params.iter_mut() {
+        visitor.visit_param_mut(param);
+    }
+    visitor.visit_type_spec_mut(&mut func_decl.return_type);
+}
+
+pub fn walk_func_def_mut<V: VisitorMut>(visitor: &mut V, func_def: &mut FuncDef) {
+    visitor.visit_ident_mut(&mut func_def.name);
+    for param in func_def.params.iter_mut() {
+        visitor.visit_param_mut(param);
+    }
+    visitor.visit_type_spec_mut(&mut func_def.return_type);
+    for stmt in func_def.body.iter_mut() {
+        visitor.visit_stmt_mut(stmt);
+    }
+}
+
+pub fn walk_param_mut<V: VisitorMut>(visitor: &mut V, param: &mut Param) {
+    visitor.visit_ident_mut(&mut param.name);
+    visitor.visit_type_spec_mut(&mut param.param_type);
+}
+
+pub fn walk_var_decl_mut<V: VisitorMut>(visitor: &mut V, var_decl: &mut VarDecl) {
+    visitor.visit_ident_mut(&mut var_decl.name);
+    visitor.visit_type_spec_mut(&mut var_decl.var_type);
+    if let Some(initializer) = &mut var_decl.initializer {
+        visitor.visit_initializer_mut(initializer);
+    }
+}
+
+pub fn walk_initializer_mut<V: VisitorMut>(visitor: &mut V, initializer: &mut Initializer) {
+    match initializer {
+        Initializer::Expr(expr) => visitor.visit_expr_mut(expr),
+        Initializer::List(elements, _) => {
+            for element in elements.iter_mut() {
+                visitor.visit_initializer_mut(element);
+            }
+        }
+    }
+}
+
+pub fn walk_const_decl_mut<V: VisitorMut>(visitor: &mut V, const_decl: &mut ConstDecl) {
+    visitor.visit_ident_mut(&mut const_decl.name);
+    visitor.visit_type_spec_mut(&mut const_decl.const_type);
+    visitor.visit_expr_mut(&mut const_decl.value);
+}
+
+pub fn walk_block_mut<V: VisitorMut>(visitor: &mut V, block: &mut Block) {
+    for stmt in block.stmts.iter_mut() {
+        visitor.visit_stmt_mut(stmt);
+    }
+}
+
+pub fn walk_stmt_mut<V: VisitorMut>(visitor: &mut V, stmt: &mut Stmt) {
+    match &mut stmt.kind {
+        StmtKind::VarDecl(var_decl) => visitor.visit_var_decl_mut(var_decl),
+        StmtKind::Assign { target, value } => {
+            visitor.visit_expr_mut(target);
+            visitor.visit_expr_mut(value);
+        }
+        StmtKind::Expr(expr)
+        | StmtKind::Read(expr)
+        | StmtKind::Write(expr)
+        | StmtKind::Return(expr) => visitor.visit_expr_mut(expr),
+        StmtKind::If {
+            condition,
+            then_branch,
+            else_branch,
+        } => {
+            visitor.visit_expr_mut(condition);
+            visitor.visit_block_mut(then_branch);
+            if let Some(else_branch) = else_branch {
+                visitor.visit_stmt_mut(else_branch);
+            }
+        }
+        StmtKind::While { condition, body } => {
+            visitor.visit_expr_mut(condition);
+            visitor.visit_block_mut(body);
+        }
+        StmtKind::For {
+            variable,
+            initial_value,
+            condition,
+            step,
+            body,
+        } => {
+            visitor.visit_var_decl_mut(variable);
+            visitor.visit_expr_mut(initial_value);
+            visitor.visit_expr_mut(condition);
+            visitor.visit_stmt_mut(step);
+            visitor.visit_block_mut(body);
+        }
+        StmtKind::Block(block) => visitor.visit_block_mut(block),
+        StmtKind::Break | StmtKind::Continue | StmtKind::Error => {}
+    }
+}
+
+pub fn walk_expr_mut<V: VisitorMut>(visitor: &mut V, expr: &mut Expr) {
+    match &mut expr.kind {
+        ExprKind::Id(id) => visitor.visit_ident_mut(id),
+        ExprKind::Var { base, indices } => {
+            visitor.visit_expr_mut(base);
+            for index in indices.iter_mut() {
+                visitor.visit_expr_mut(index);
+            }
+        }
+        ExprKind::Member { object, member } => {
+            visitor.visit_expr_mut(object);
+            visitor.visit_ident_mut(member);
+        }
+        ExprKind::Call { callee, args } => {
+            visitor.visit_expr_mut(callee);
+            for arg in args.iter_mut() {
+                visitor.visit_expr_mut(arg);
+            }
+        }
+        ExprKind::Binary { lhs, rhs, .. } => {
+            visitor.visit_expr_mut(lhs);
+            visitor.visit_expr_mut(rhs);
+        }
+        ExprKind::Unary { operand, .. } => visitor.visit_expr_mut(operand),
+        ExprKind::IntLit(_) | ExprKind::FloatLit(_) | ExprKind::BoolLit(_) | ExprKind::Error => {}
+    }
+}
+
+/// Rewriting of the typed AST into a new one. Each method takes a node and returns the
+/// node to put in its place; by default the node is rebuilt from its children folded
+/// by the matching `fold_..._children` function.
+pub trait Fold: Sized {
+    fn fold_program(&mut self, program: Program) -> Program {
+        fold_program_children(self, program)
+    }
+
+    fn fold_item(&mut self, item: Item) -> Item {
+        fold_item_children(self, item)
+    }
+
+    fn fold_struct_decl(&mut self, struct_decl: StructDecl) -> StructDecl {
+        fold_struct_decl_children(self, struct_decl)
+    }
+
+    fn fold_member_decl(&mut self, member_decl: MemberDecl) -> MemberDecl {
+        fold_member_decl_children(self, member_decl)
+    }
+
+    fn fold_impl_def(&mut self, impl_def: ImplDef) -> ImplDef {
+        fold_impl_def_children(self, impl_def)
+    }
+
+    fn fold_func_decl(&mut self, func_decl: FuncDecl) -> FuncDecl {
+        fold_func_decl_children(self, func_decl)
+    }
+
+    fn fold_func_def(&mut self, func_def: FuncDef) -> FuncDef {
+        fold_func_def_children(self, func_def)
+    }
+
+    fn fold_param(&mut self, param: Param) -> Param {
+        fold_param_children(self, param)
+    }
+
+    fn fold_var_decl(&mut self, var_decl: VarDecl) -> VarDecl {
+        fold_var_decl_children(self, var_decl)
+    }
+
+    fn fold_initializer(&mut self, initializer: Initializer) -> Initializer {
+        fold_initializer_children(self, initializer)
+    }
+
+    fn fold_const_decl(&mut self, const_decl: ConstDecl) -> ConstDecl {
+        fold_const_decl_children(self, const_decl)
+    }
+
+    fn fold_block(&mut self, block: Block) -> Block {
+        fold_block_children(self, block)
+    }
+
+    fn fold_stmt(&mut self, stmt: Stmt) -> Stmt {
+        fold_stmt_children(self, stmt)
+    }
+
+    fn fold_expr(&mut self, expr: Expr) -> Expr {
+        fold_expr_children(self, expr)
+    }
+
+    fn fold_type_spec(&mut self, type_spec: TypeSpec) -> TypeSpec {
+        type_spec
+    }
+
+    fn fold_ident(&mut self, ident: Ident) -> Ident {
+        ident
+    }
+}
+
+pub fn fold_program_children<F: Fold>(folder: &mut F, program: Program) -> Program {
+    Program {
+        items: program
+            .items
+            .into_iter()
+            .map(|item| folder.fold_item(item))
+            .collect(),
+        span: program.span,
+    }
+}
+
+pub fn fold_item_children<F: Fold>(folder: &mut F, item: Item) -> Item {
+    match item {
+        Item::Struct(struct_decl) => Item::Struct(folder.fold_struct_decl(struct_decl)),
+        Item::Impl(impl_def) => Item::Impl(folder.fold_impl_def(impl_def)),
+        Item::Func(func_def) => Item::Func(folder.fold_func_def(func_def)),
+        Item::Var(var_decl) => Item::Var(folder.fold_var_decl(var_decl)),
+        Item::Const(const_decl) => Item::Const(folder.fold_const_decl(const_decl)),
+        Item::Error(span) => Item::Error(span),
+    }
+}
+
+pub fn fold_struct_decl_children<F: Fold>(folder: &mut F, struct_decl: StructDecl) -> StructDecl {
+    StructDecl {
+        name: folder.fold_ident(struct_decl.name),
+        inherits: struct_decl
+            .inherits
+            .into_iter()
+            .map(|inherit| folder.fold_ident(inherit))
+            .collect(),
+        members: struct_decl
+            .members
+            .into_iter()
+            .map(|member_decl| folder.fold_member_decl(member_decl))
+            .collect(),
+        span: struct_decl.span,
+    }
+}
+
+pub fn fold_member_decl_children<F: Fold>(folder: &mut F, member_decl: MemberDecl) -> MemberDecl {
+    MemberDecl {
+        visibility: member_decl.visibility,
+        member: match member_decl.member {
+            Member::Var(var_decl) => Member::Var(folder.fold_var_decl(var_decl)),
+            Member::Func(func_decl) => Member::Func(folder.fold_func_decl(func_decl)),
+        },
+        span: member_decl.span,
+    }
+}
+
+pub fn fold_impl_def_children<F: Fold>(folder: &mut F, impl_def: ImplDef) -> ImplDef {
+    ImplDef {
+        name: folder.fold_ident(impl_def.name),
+        funcs: impl_def
+            .funcs
+            .into_iter()
+            .map(|func_def| folder.fold_func_def(func_def))
+            .collect(),
+        span: impl_def.span,
+    }
+}
+
+pub fn fold_func_decl_children<F: Fold>(folder: &mut F, func_decl: FuncDecl) -> FuncDecl {
+    FuncDecl {
+        name: folder.fold_ident(func_decl.name),
+        params: func_decl
+            .params
+            .into_iter()
+            .map(|param| folder.fold_param(param))
+            .collect(),
+        return_type: folder.fold_type_spec(func_decl.return_type),
+        span: func_decl.span,
+    }
+}
+
+pub fn fold_func_def_children<F: Fold>(folder: &mut F, func_def: FuncDef) -> FuncDef {
+    FuncDef {
+        name: folder.fold_ident(func_def.name),
+        params: func_def
+            .params
+            .into_iter()
+            .map(|param| folder.fold_param(param))
+            .collect(),
+        return_type: folder.fold_type_spec(func_def.return_type),
+        body: func_def
+            .body
+            .into_iter()
+            .map(|stmt| folder.fold_stmt(stmt))
+            .collect(),
+        span: func_def.span,
+    }
+}
+
+pub fn fold_param_children<F: Fold>(folder: &mut F, param: Param) -> Param {
+    Param {
+        name: folder.fold_ident(param.name),
+        param_type: folder.fold_type_spec(param.param_type),
+        span: param.span,
+    }
+}
+
+pub fn fold_var_decl_children<F: Fold>(folder: &mut F, var_decl: VarDecl) -> VarDecl {
+    VarDecl {
+        name: folder.fold_ident(var_decl.name),
+        var_type: folder.fold_type_spec(var_decl.var_type),
+        initializer: var_decl
+            .initializer
+            .map(|initializer| folder.fold_initializer(initializer)),
+        span: var_decl.span,
+    }
+}
+
+pub fn fold_initializer_children<F: Fold>(folder: &mut F, initializer: Initializer) -> Initializer {
+    match initializer {
+        Initializer::Expr(expr) => Initializer::Expr(folder.fold_expr(expr)),
+        Initializer::List(elements, span) => Initializer::List(
+            elements
+                .into_iter()
+                .map(|element| folder.fold_initializer(element))
+                .collect(),
+            span,
+        ),
+    }
+}
+
+pub fn fold_const_decl_children<F: Fold>(folder: &mut F, const_decl: ConstDecl) -> ConstDecl {
+    ConstDecl {
+        name: folder.fold_ident(const_decl.name),
+        const_type: folder.fold_type_spec(const_decl.const_type),
+        value: folder.fold_expr(const_decl.value),
+        span: const_decl.span,
+    }
+}
+
+pub fn fold_block_children<F: Fold>(folder: &mut F, block: Block) -> Block {
+    Block {
+        stmts: block
+            .stmts
+            .into_iter()
+            .map(|stmt| folder.fold_stmt(stmt))
+            .collect(),
+        span: block.span,
+    }
+}
+
+pub fn fold_stmt_children<F: Fold>(folder: &mut F, stmt: Stmt) -> Stmt {
+    let kind = match stmt.kind {
+        StmtKind::VarDecl(var_decl) => StmtKind::VarDecl(folder.fold_var_decl(var_decl)),
+        StmtKind::Assign { target, value } => StmtKind::Assign {
+            target: folder.fold_expr(target),
+            value: folder.fold_expr(value),
+        },
+        StmtKind::Expr(expr) => StmtKind::Expr(folder.fold_expr(expr)),
+        StmtKind::If {
+            condition,
+            then_branch,
+            else_branch,
+        } => StmtKind::If {
+            condition: folder.fold_expr(condition),
+            then_branch: folder.fold_block(then_branch),
+            else_branch: else_branch.map(|else_branch| Box::new(folder.fold_stmt(*else_branch))),
+        },
+        StmtKind::While { condition, body } => StmtKind::While {
+            condition: folder.fold_expr(condition),
+            body: folder.fold_block(body),
+        },
+        StmtKind::For {
+            variable,
+            initial_value,
+            condition,
+            step,
+            body,
+        } => StmtKind::For {
+            variable: folder.fold_var_decl(variable),
+            initial_value: folder.fold_expr(initial_value),
+            condition: folder.fold_expr(condition),
+            step: Box::new(folder.fold_stmt(*step)),
+            body: folder.fold_block(body),
+        },
+        StmtKind::Read(expr) => StmtKind::Read(folder.fold_expr(expr)),
+        StmtKind::Write(expr) => StmtKind::Write(folder.fold_expr(expr)),
+        StmtKind::Return(expr) => StmtKind::Return(folder.fold_expr(expr)),
+        StmtKind::Block(block) => StmtKind::Block(folder.fold_block(block)),
+        kind @ (StmtKind::Break | StmtKind::Continue | StmtKind::Error) => kind,
+    };
+    Stmt {
+        kind,
+        span: stmt.span,
+    }
+}
+
+pub fn fold_expr_children<F: Fold>(folder: &mut F, expr: Expr) -> Expr {
+    let kind = match expr.kind {
+        ExprKind::Id(id) => ExprKind::Id(folder.fold_ident(id)),
+        ExprKind::Var { base, indices } => ExprKind::Var {
+            base: Box::new(folder.fold_expr(*base)),
+            indices: indices
+                .into_iter()
+                .map(|index| folder.fold_expr(index))
+                .collect(),
+        },
+        ExprKind::Member { object, member } => ExprKind::Member {
+            object: Box::new(folder.fold_expr(*object)),
+            member: folder.fold_ident(member),
+        },
+        ExprKind::Call { callee, args } => ExprKind::Call {
+            callee: Box::new(folder.fold_expr(*callee)),
+            args: args.into_iter().map(|arg| folder.fold_expr(arg)).collect(),
+        },
+        ExprKind::Binary { op, lhs, rhs } => ExprKind::Binary {
+            op,
+            lhs: Box::new(folder.fold_expr(*lhs)),
+            rhs: Box::new(folder.fold_expr(*rhs)),
+        },
+        ExprKind::Unary { op, operand } => ExprKind::Unary {
+            op,
+            operand: Box::new(folder.fold_expr(*operand)),
+        },
+        kind @ (ExprKind::IntLit(_)
+        | ExprKind::FloatLit(_)
+        | ExprKind::BoolLit(_)
+        | ExprKind::Error) => kind,
+    };
+    Expr {
+        kind,
+        span: expr.span,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::semantic::typed_ast::lower;
+    use crate::syntactic::parser::tests::tokens_of;
+    use crate::syntactic::parser::Parser;
+
+    fn program_of(source: &str) -> Program {
+        let (_, ast, spans, _) = Parser::new().parse(tokens_of(source)).unwrap();
+        lower(&ast, &spans)
+    }
+
+    /// The names of the identifiers, in the order they are visited
+    #[derive(Default)]
+    struct Idents(Vec<String>);
+
+    impl<'ast> Visitor<'ast> for Idents {
+        fn visit_ident(&mut self, ident: &'ast Ident) {
+            self.0.push(ident.name.clone());
+        }
+    }
+
+    /// Rename every identifier
+    struct Rename;
+
+    impl Fold for Rename {
+        fn fold_ident(&mut self, ident: Ident) -> Ident {
+            Ident {
+                name: ident.name.to_uppercase(),
+                span: ident.span,
+            }
+        }
+    }
+
+    /// Rename every identifier in place
+    struct RenameInPlace;
+
+    impl VisitorMut for RenameInPlace {
+        fn visit_ident_mut(&mut self, ident: &mut Ident) {
+            ident.name = ident.name.to_uppercase();
+        }
+    }
+
+    const SOURCE: &str = "
+        struct S { public let a: integer; };
+        func f(p: integer[]) -> void {
+          let v: integer = p[0];
+          for (let i: integer = 0; i < v; i = i + 1) { if (g(i)) then write(i); ; };
+        }";
+
+    #[test]
+    fn visits_nodes_in_source_order() {
+        let mut idents = Idents::default();
+        idents.visit_program(&program_of(SOURCE));
+        assert_eq!(
+            idents.0,
+            ["S", "a", "f", "p", "v", "p", "i", "i", "v", "i", "i", "g", "i", "i"]
+        );
+    }
+
+    #[test]
+    fn folds_into_a_program_of_the_same_shape() {
+        let program = program_of(SOURCE);
+        let renamed = Rename.fold_program(program.clone());
+        assert_ne!(renamed, program);
+        let mut idents = Idents::default();
+        idents.visit_program(&renamed);
+        assert_eq!(idents.0[..4], ["S", "A", "F", "P"]);
+        // folding again with the default methods changes nothing
+        struct Identity;
+        impl Fold for Identity {}
+        assert_eq!(Identity.fold_program(renamed.clone()), renamed);
+    }
+
+    #[test]
+    fn visits_mutably_in_source_order() {
+        let mut program = program_of(SOURCE);
+        RenameInPlace.visit_program_mut(&mut program);
+        assert_eq!(program, Rename.fold_program(program_of(SOURCE)));
+        let mut idents = Idents::default();
+        idents.visit_program(&program);
+        assert_eq!(idents.0[..4], ["S", "A", "F", "P"]);
+    }
+}
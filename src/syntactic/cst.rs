@@ -0,0 +1,265 @@
+use crate::lexical::span::Span;
+use crate::lexical::token::Token;
+use crate::syntactic::parser::SymbolOrToken;
+use crate::syntactic::symbol::{Symbol, Terminal};
+use crate::syntactic::trace::symbol_name;
+use crate::syntactic::tree::{escape_dot, NodeId, Tree};
+use crate::syntactic::util::json_string;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+
+/// The parse tree built by the parser, with the source range covered by each node.
+/// Action symbols are left out of every export. A terminal and the token matched
+/// against it are shown as one leaf; a terminal with no token was missing in the source.
+pub struct ConcreteSyntaxTree {
+    tree: Tree<SymbolOrToken>,
+    /// range of the tokens under each node, if it covers any
+    spans: HashMap<NodeId, Span>,
+}
+
+enum CstNode<'a> {
+    NonTerminal(String),
+    Token(String, &'a Token),
+    Missing(String),
+}
+
+impl ConcreteSyntaxTree {
+    pub fn new(tree: Tree<SymbolOrToken>) -> Self {
+        let mut cst = Self {
+            tree,
+            spans: HashMap::new(),
+        };
+        if cst.tree.has_root() {
+            cst.compute_span(cst.tree.get_root());
+        }
+        cst
+    }
+
+    /// The source range covered by the node. `None` if it derived no token.
+    pub fn get_span(&self, node_id: NodeId) -> Option<Span> {
+        self.spans.get(&node_id).copied()
+    }
+
+    /// Indented text form, one node per line
+    pub fn to_text(&self) -> String {
+        let mut result = String::new();
+        if self.tree.has_root() {
+            self.to_text_from_node(self.tree.get_root(), 0, &mut result);
+        }
+        result
+    }
+
+    /// Graphviz DOT form
+    pub fn to_dot(&self) -> String {
+        let mut result = String::from("digraph cst {\n    node [shape=box];\n");
+        if self.tree.has_root() {
+            self.to_dot_from_node(self.tree.get_root(), &mut result);
+        }
+        result + "}\n"
+    }
+
+    /// JSON form, each node an object with its children nested
+    pub fn to_json(&self) -> String {
+        if self.tree.has_root() {
+            self.to_json_from_node(self.tree.get_root())
+        } else {
+            "null".to_string()
+        }
+    }
+
+    fn compute_span(&mut self, node_id: NodeId) -> Option<Span> {
+        let span = match self.tree.get_node_value(node_id) {
+            SymbolOrToken::Token(token) => Some(token.get_span()),
+            SymbolOrToken::Symbol(_) => {
+                let mut span: Option<Span> = None;
+                for child in self.tree.get_children(node_id) {
+                    if let Some(child_span) = self.compute_span(child) {
+                        span = Some(match span {
+                            Some(span) => span.merge(&child_span),
+                            None => child_span,
+                        });
+                    }
+                }
+                span
+            }
+        };
+        if let Some(span) = span {
+            self.spans.insert(node_id, span);
+        }
+        span
+    }
+
+    /// How the node is shown, `None` for action symbols
+    fn get_cst_node(&self, node_id: NodeId) -> Option<CstNode<'_>> {
+        match self.tree.get_node_value(node_id) {
+            SymbolOrToken::Token(token) => Some(CstNode::Token(
+                symbol_name(&Symbol::Terminal(Terminal::ValidTokenType(
+                    token.get_valid_token_type()?,
+                )))?,
+                token,
+            )),
+            SymbolOrToken::Symbol(Symbol::ActionSymbol(_)) => None,
+            SymbolOrToken::Symbol(symbol @ Symbol::NonTerminal(_)) => {
+                Some(CstNode::NonTerminal(symbol_name(symbol)?))
+            }
+            SymbolOrToken::Symbol(symbol @ Symbol::Terminal(_)) => {
+                let name = symbol_name(symbol)?;
+                match self.tree.get_children(node_id).first() {
+                    Some(child) => match self.tree.get_node_value(*child) {
+                        SymbolOrToken::Token(token) => Some(CstNode::Token(name, token)),
+                        SymbolOrToken::Symbol(_) => Some(CstNode::Missing(name)),
+                    },
+                    None => Some(CstNode::Missing(name)),
+                }
+            }
+        }
+    }
+
+    /// Children shown under a nonterminal
+    fn get_cst_children(&self, node_id: NodeId) -> Vec<NodeId> {
+        match self.get_cst_node(node_id) {
+            Some(CstNode::NonTerminal(_)) => self
+                .tree
+                .get_children(node_id)
+                .into_iter()
+                .filter(|child| self.get_cst_node(*child).is_some())
+                .collect(),
+            _ => vec![],
+        }
+    }
+
+    fn get_label(&self, node_id: NodeId, separator: &str) -> String {
+        let span = match self.get_span(node_id) {
+            Some(span) => format!("{}{}", separator, span),
+            None => "".to_string(),
+        };
+        match self.get_cst_node(node_id) {
+            Some(CstNode::NonTerminal(name)) => name + &span,
+            Some(CstNode::Token(name, token)) => {
+                format!("{}{}`{}`{}", name, separator, token.lexeme, span)
+            }
+            Some(CstNode::Missing(name)) => format!("{}{}<missing>", name, separator),
+            None => "".to_string(),
+        }
+    }
+
+    fn to_text_from_node(&self, node_id: NodeId, depth: usize, result: &mut String) {
+        result.push_str(&"| ".repeat(depth));
+        result.push_str(&self.get_label(node_id, " "));
+        result.push('\n');
+        for child in self.get_cst_children(node_id) {
+            self.to_text_from_node(child, depth + 1, result);
+        }
+    }
+
+    fn to_dot_from_node(&self, node_id: NodeId, result: &mut String) {
+        let label = escape_dot(&self.get_label(node_id, "\n"));
+        let shape = match self.get_cst_node(node_id) {
+            Some(CstNode::NonTerminal(_)) => "",
+            Some(CstNode::Missing(_)) => ", shape=ellipse, style=dashed",
+            _ => ", shape=ellipse",
+        };
+        result.push_str(&format!(
+            "    n{} [label=\"{}\"{}];\n",
+            node_id, label, shape
+        ));
+        for child in self.get_cst_children(node_id) {
+            result.push_str(&format!("    n{} -> n{};\n", node_id, child));
+            self.to_dot_from_node(child, result);
+        }
+    }
+
+    fn to_json_from_node(&self, node_id: NodeId) -> String {
+        let span = match self.get_span(node_id) {
+            Some(span) => format!(
+                "{{\"start\":{{\"line\":{},\"column\":{}}},\"end\":{{\"line\":{},\"column\":{}}}}}",
+                span.start.0, span.start.1, span.end.0, span.end.1
+            ),
+            None => "null".to_string(),
+        };
+        match self.get_cst_node(node_id) {
+            Some(CstNode::NonTerminal(name)) => {
+                let children: Vec<String> = self
+                    .get_cst_children(node_id)
+                    .into_iter()
+                    .map(|child| self.to_json_from_node(child))
+                    .collect();
+                format!(
+                    "{{\"kind\":\"nonterminal\",\"symbol\":{},\"span\":{},\"children\":[{}]}}",
+                    json_string(&name),
+                    span,
+                    children.join(",")
+                )
+            }
+            Some(CstNode::Token(name, token)) => format!(
+                "{{\"kind\":\"token\",\"symbol\":{},\"lexeme\":{},\"span\":{}}}",
+                json_string(&name),
+                json_string(&token.lexeme),
+                span
+            ),
+            Some(CstNode::Missing(name)) => format!(
+                "{{\"kind\":\"missing\",\"symbol\":{},\"span\":null}}",
+                json_string(&name)
+            ),
+            None => "null".to_string(),
+        }
+    }
+}
+
+impl Display for ConcreteSyntaxTree {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.to_text())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::syntactic::parser::tests::tokens_of;
+    use crate::syntactic::parser::Parser;
+
+    fn cst_of(source: &str) -> ConcreteSyntaxTree {
+        Parser::new().parse(tokens_of(source)).unwrap().0
+    }
+
+    const SOURCE: &str = "func main() -> void {\n  write(1 + x);\n}\n";
+
+    #[test]
+    fn writes_the_range_of_each_node_in_text() {
+        let text = cst_of(SOURCE).to_text();
+        let lines: Vec<&str> = text
+            .lines()
+            .map(|line| line.trim_start_matches("| "))
+            .collect();
+        assert_eq!(lines[0], "Start 1:0-3:1");
+        assert!(lines.contains(&"Id `main` 1:5-1:9"));
+        assert!(lines.contains(&"Expr 2:8-2:13"));
+        assert!(lines.contains(&"Id `x` 2:12-2:13"));
+        // an empty derivation covers no token
+        assert!(lines.contains(&"FParams"));
+    }
+
+    #[test]
+    fn writes_the_range_of_each_node_in_dot() {
+        let dot = cst_of(SOURCE).to_dot();
+        assert!(dot.starts_with("digraph cst {\n"));
+        assert!(dot.contains("[label=\"Expr\\n2:8-2:13\"];"));
+        assert!(dot.contains("[label=\"Id\\n`x`\\n2:12-2:13\", shape=ellipse];"));
+        assert!(dot.contains("[label=\"FParams\"];"));
+    }
+
+    #[test]
+    fn writes_the_range_of_each_node_in_json() {
+        let json = cst_of(SOURCE).to_json();
+        assert!(json.starts_with("{\"kind\":\"nonterminal\",\"symbol\":\"Start\""));
+        assert!(json.contains(
+            "{\"kind\":\"nonterminal\",\"symbol\":\"Expr\",\"span\":{\"start\":{\"line\":2,\"column\":8},\"end\":{\"line\":2,\"column\":13}},\"children\":["
+        ));
+        assert!(json.contains(
+            "{\"kind\":\"token\",\"symbol\":\"Id\",\"lexeme\":\"x\",\"span\":{\"start\":{\"line\":2,\"column\":12},\"end\":{\"line\":2,\"column\":13}}}"
+        ));
+        assert!(json.contains(
+            "{\"kind\":\"nonterminal\",\"symbol\":\"FParams\",\"span\":null,\"children\":[]}"
+        ));
+    }
+}
@@ -3,7 +3,7 @@ use regex::Regex;
 use std::fmt::{Display, Formatter};
 
 pub struct Derivation {
-    from: NonTerminal,
+    pub from: NonTerminal,
     pub to: Vec<Symbol>,
 }
 
@@ -16,13 +16,13 @@ impl Derivation {
             .split(derivation_string)
             .map(|x| x.trim().to_string())
             .collect();
-        let from_symbol = match Symbol::from_string(&*production[0]) {
+        let from_symbol = match Symbol::from_string(&production[0]) {
             Symbol::NonTerminal(n) => n,
             Symbol::Terminal(_) | Symbol::ActionSymbol(_) => panic!("Unexpected symbol string"),
         };
         let right_symbols: Vec<Symbol> = Regex::new(r" ")
             .unwrap()
-            .split(&*production[1])
+            .split(&production[1])
             .map(|x| Symbol::from_string(x.trim()))
             .collect();
 
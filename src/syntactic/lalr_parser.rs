@@ -0,0 +1,288 @@
+use crate::lexical::span::Span;
+use crate::lexical::token::Token;
+use crate::semantic::concept::Concept;
+use crate::syntactic::lalr_table::{Conflict, LalrTable, LrAction};
+use crate::syntactic::parser::{get_terminal, prepare_tokens, MAX_SYNTAX_ERRORS};
+use crate::syntactic::semantic_action::{ActionTokens, SemanticAction};
+use crate::syntactic::symbol::{ActionSymbol, Terminal};
+use crate::syntactic::syntax_error::{SyntaxErrType, SyntaxError};
+use crate::syntactic::tree::{NodeId, Tree};
+use crate::syntactic::util;
+use std::collections::HashMap;
+
+/// The AST with the source range of each of its nodes, and the syntax errors recovered from
+pub type LalrParseOutput = (Tree<Concept>, HashMap<NodeId, Span>, Vec<SyntaxError>);
+
+/// Tokens to shift after a syntax error before reporting errors again, as in yacc
+const RECOVERY_SHIFTS: usize = 3;
+
+struct StackEntry {
+    state: usize,
+    /// size of the semantic stack when the entry was pushed
+    semantic_depth: usize,
+    /// index of the first token of the symbol
+    start: usize,
+}
+
+/// Shift-reduce parser driven by an LALR(1) table. It consumes the same tokens as the
+/// LL(1) `Parser` and builds the same AST. On a syntax error, it pops the stack down to
+/// a state where the grammar allows `error`, shifts it in place of the tokens skipped,
+/// and discards tokens until one can follow.
+pub struct LalrParser {
+    table: LalrTable,
+    semantic_actions: HashMap<ActionSymbol, SemanticAction>,
+}
+
+impl Default for LalrParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LalrParser {
+    pub fn new() -> Self {
+        Self {
+            table: LalrTable::new(&util::read_lalr_grammar()),
+            semantic_actions: util::read_semantic_actions(),
+        }
+    }
+
+    /// Conflicts of the grammar left after applying the precedence declarations
+    pub fn get_conflicts(&self) -> &[Conflict] {
+        self.table.get_conflicts()
+    }
+
+    /// The AST of the tokens, with the source range of each of its nodes and the syntax
+    /// errors recovered from. The errors are returned alone if the parser cannot recover,
+    /// or after `MAX_SYNTAX_ERRORS` errors.
+    pub fn parse(&self, raw_tokens: Vec<Token>) -> Result<LalrParseOutput, Vec<SyntaxError>> {
+        let tokens = prepare_tokens(&raw_tokens);
+        let mut token_index = 0;
+        let mut stack = vec![StackEntry {
+            state: 0,
+            semantic_depth: 0,
+            start: 0,
+        }];
+        let mut semantic_stack: Vec<NodeId> = Vec::new();
+        let mut ast: Tree<Concept> = Tree::new();
+        let mut ast_spans: HashMap<NodeId, Span> = HashMap::new();
+        let mut ast_anchors: HashMap<NodeId, usize> = HashMap::new();
+        let mut outstanding_dot = false;
+        let mut errors: Vec<SyntaxError> = Vec::new();
+        let mut shifts_to_recover: usize = 0;
+
+        loop {
+            let state = stack.last().unwrap().state;
+            let lookahead = get_terminal(&tokens[token_index]);
+            match self.table.get_action(state, &lookahead) {
+                Some(LrAction::Shift(next_state)) => {
+                    stack.push(StackEntry {
+                        state: next_state,
+                        semantic_depth: semantic_stack.len(),
+                        start: token_index,
+                    });
+                    token_index += 1;
+                    shifts_to_recover = shifts_to_recover.saturating_sub(1);
+                }
+                Some(LrAction::Reduce(production)) => {
+                    let (length, covered, action_symbols) = self.table.get_reduction(production);
+                    let at = ActionTokens {
+                        tokens: &tokens,
+                        production_start: match covered {
+                            0 => token_index,
+                            _ => stack[stack.len() - covered].start,
+                        },
+                        token_index,
+                    };
+                    for action_symbol in action_symbols {
+                        self.semantic_actions[action_symbol].perform(
+                            &at,
+                            &mut semantic_stack,
+                            &mut ast,
+                            &mut ast_spans,
+                            &mut ast_anchors,
+                            &mut outstanding_dot,
+                        );
+                    }
+                    let start = match length {
+                        0 => token_index,
+                        _ => stack[stack.len() - length].start,
+                    };
+                    stack.truncate(stack.len() - length);
+                    let state = stack.last().unwrap().state;
+                    stack.push(StackEntry {
+                        state: self.table.get_goto(state, production).unwrap(),
+                        semantic_depth: semantic_stack.len(),
+                        start,
+                    });
+                }
+                Some(LrAction::Accept) => {
+                    if let Some(prog) = semantic_stack.last() {
+                        ast.set_root(*prog);
+                    }
+                    return Ok((ast, ast_spans, errors));
+                }
+                None if shifts_to_recover == RECOVERY_SHIFTS => {
+                    // nothing could follow `error` yet: skip the token
+                    if lookahead.eq(&Terminal::EOF) {
+                        return Err(errors);
+                    }
+                    token_index += 1;
+                }
+                None => {
+                    // an error right after recovering is not reported again
+                    if shifts_to_recover == 0 {
+                        errors.push(SyntaxError::new(
+                            SyntaxErrType::UnexpectedToken,
+                            tokens[token_index].clone(),
+                            self.table.get_expected_terminals(state),
+                            None,
+                        ));
+                        if errors.len() >= MAX_SYNTAX_ERRORS {
+                            return Err(errors);
+                        }
+                    }
+                    let error_state = loop {
+                        let state = match stack.last() {
+                            Some(entry) => entry.state,
+                            None => return Err(errors),
+                        };
+                        match self.table.get_action(state, &Terminal::ERROR) {
+                            Some(LrAction::Shift(error_state)) => break error_state,
+                            _ => {
+                                stack.pop();
+                            }
+                        }
+                    };
+                    // drop the concepts of the part given up. Those of the entries left
+                    // may already be taken by an action in the middle of a production.
+                    let semantic_depth = stack
+                        .last()
+                        .unwrap()
+                        .semantic_depth
+                        .min(semantic_stack.len());
+                    for item in semantic_stack.drain(semantic_depth..) {
+                        ast.remove_subtree(item);
+                    }
+                    let error = ast.insert_node(None, Concept::create_error("skipped tokens"));
+                    ast_spans.insert(error, tokens[token_index].get_span());
+                    semantic_stack.push(error);
+                    outstanding_dot = false;
+                    stack.push(StackEntry {
+                        state: error_state,
+                        semantic_depth: semantic_stack.len(),
+                        start: token_index,
+                    });
+                    shifts_to_recover = RECOVERY_SHIFTS;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::semantic::serialization::to_sexpr;
+    use crate::syntactic::parser::tests::{mutate, sample_tokens, tokens_of, Random};
+    use crate::syntactic::parser::Parser;
+    use std::path::Path;
+
+    /// The AST built by each parser, with its spans
+    fn ll_and_lalr_asts(tokens: Vec<Token>) -> (String, String) {
+        let ll_ast = match Parser::new().parse(tokens.clone()) {
+            Ok((_, ast, spans, errors)) if errors.is_empty() => to_sexpr(&ast, &spans),
+            _ => panic!("the LL(1) parser found syntax errors"),
+        };
+        let lalr_ast = match LalrParser::new().parse(tokens) {
+            Ok((ast, spans, errors)) if errors.is_empty() => to_sexpr(&ast, &spans),
+            _ => panic!("the LALR(1) parser found syntax errors"),
+        };
+        (ll_ast, lalr_ast)
+    }
+
+    #[test]
+    fn precedence_declarations_resolve_every_conflict() {
+        let conflicts: Vec<String> = LalrParser::new()
+            .get_conflicts()
+            .iter()
+            .map(|conflict| conflict.to_string())
+            .collect();
+        assert!(conflicts.is_empty(), "{}", conflicts.join("\n"));
+    }
+
+    #[test]
+    fn builds_the_same_ast_as_the_ll_parser_on_the_samples() {
+        let mut samples = vec![];
+        sample_tokens(Path::new("resource"), &mut samples);
+        let mut compared = 0;
+        for (path, tokens) in samples {
+            let ll_errors = match Parser::new().parse(tokens.clone()) {
+                Ok((_, _, _, errors)) | Err(errors) => errors,
+            };
+            if !ll_errors.is_empty() {
+                continue;
+            }
+            let (ll_ast, lalr_ast) = ll_and_lalr_asts(tokens);
+            assert_eq!(ll_ast, lalr_ast, "ASTs of {} differ", path);
+            compared += 1;
+        }
+        assert!(compared > 0);
+    }
+
+    #[test]
+    fn builds_the_same_ast_as_the_ll_parser_on_each_construct() {
+        let sources = [
+            "func f(a: integer, b: float[2][], c: Q) -> void { x = a + b * c - -d / (e + f); }",
+            "func f() -> bool { return(a < b & c >= d | !e & f == g); }",
+            "func f() -> void { if (a) then if (b) then x = 1; else x = 2;; else { x = 3; }; }",
+            "func f() -> void { if (a) then x = 1; else if (b) then x = 2; else if (c) then; }",
+            "func f() -> void { while (i < 10) { i = i + 1; if (i == 5) then break; else; }; }",
+            "func f() -> void { for (let i: integer = 0; i < 10; i = i + 1) continue; ; }",
+            "func f() -> void { a.b[1].c = g(1, 2).d[i + 1]; a.h().g(); read(a.f().b[2]); }",
+            "func f() -> void { let a: integer[3] = {1, 2, {3}}; let b = 1.5e2; write(true); }",
+            "struct S inherits A, B { public let a: integer; private func f() -> S; };",
+            "impl S { func f() -> void { } } const c: integer = 4; let g: bool = false;",
+        ];
+        for source in sources {
+            let (ll_ast, lalr_ast) = ll_and_lalr_asts(tokens_of(source));
+            assert_eq!(ll_ast, lalr_ast, "ASTs of `{}` differ", source);
+        }
+    }
+
+    #[test]
+    fn recovers_at_the_next_statement_or_declaration() {
+        let source =
+            "func f() -> void { x = ; write(1); y = 2 +; } struct S { x; }; let a: integer;";
+        let (ast, _, errors) = LalrParser::new().parse(tokens_of(source)).ok().unwrap();
+        assert_eq!(errors.len(), 3);
+        let sexpr = to_sexpr(&ast, &HashMap::new());
+        assert_eq!(sexpr.matches("(Error \"skipped tokens\")").count(), 3);
+        assert!(sexpr.contains("(Write"));
+        assert!(sexpr.contains("(VarDecl"));
+    }
+
+    #[test]
+    fn recovers_from_truncated_and_mutated_samples() {
+        let mut samples = vec![];
+        sample_tokens(Path::new("resource"), &mut samples);
+        let parser = LalrParser::new();
+        let mut random = Random(0x9e37_79b9_7f4a_7c15);
+        for (path, tokens) in samples {
+            let mut inputs: Vec<Vec<Token>> = (0..tokens.len())
+                .map(|length| tokens[..length].to_vec())
+                .collect();
+            inputs.extend((0..30).map(|_| mutate(&tokens, &mut random)));
+            for input in inputs {
+                let errors = match parser.parse(input) {
+                    Ok((_, _, errors)) | Err(errors) => errors,
+                };
+                assert!(
+                    errors.len() <= MAX_SYNTAX_ERRORS,
+                    "too many errors in {}",
+                    path
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,684 @@
+use crate::syntactic::precedence::{Associativity, OPERATORS};
+use crate::syntactic::symbol::{ActionSymbol, Symbol, Terminal};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+/// Lookahead placeholder used while computing which lookaheads propagate
+const PROPAGATE: usize = usize::MAX;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+enum GrammarSymbol {
+    Terminal(usize),
+    NonTerminal(usize),
+}
+
+/// Nonterminal of the LR grammar
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub enum LrNonTerminal {
+    /// `Start'`, the start symbol of the augmented grammar
+    Augmented,
+    Named(String),
+    /// deriving only epsilon, standing for the action symbols in the middle of a production.
+    /// The actions are performed when the marker is reduced.
+    Marker(Vec<ActionSymbol>),
+}
+
+impl Display for LrNonTerminal {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LrNonTerminal::Augmented => write!(f, "Start'"),
+            LrNonTerminal::Named(name) => write!(f, "{}", name),
+            LrNonTerminal::Marker(action_symbols) => {
+                let action_symbols: Vec<String> = action_symbols
+                    .iter()
+                    .map(|action_symbol| format!("{:?}", action_symbol))
+                    .collect();
+                write!(f, "@{}", action_symbols.join("@"))
+            }
+        }
+    }
+}
+
+pub struct Production {
+    lhs: usize,
+    rhs: Vec<GrammarSymbol>,
+    /// semantic actions performed when reducing
+    actions: Vec<ActionSymbol>,
+    /// for a marker, the symbols before it in the production it stands in
+    preceding: usize,
+    /// precedence level and associativity, used to resolve conflicts
+    precedence: Option<(usize, Associativity)>,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum LrAction {
+    Shift(usize),
+    Reduce(usize),
+    Accept,
+}
+
+pub enum ConflictKind {
+    /// the shift was kept
+    ShiftReduce { production: String },
+    /// the production listed first in the grammar was kept
+    ReduceReduce { kept: String, dropped: String },
+}
+
+/// A conflict the precedence declarations could not resolve
+pub struct Conflict {
+    pub state: usize,
+    pub terminal: Terminal,
+    pub kind: ConflictKind,
+}
+
+impl Display for Conflict {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self.kind {
+            ConflictKind::ShiftReduce { production } => write!(
+                f,
+                "shift/reduce conflict in state {} on `{}`: shift, or reduce {} (shift chosen)",
+                self.state,
+                self.terminal.spelling(),
+                production
+            ),
+            ConflictKind::ReduceReduce { kept, dropped } => write!(
+                f,
+                "reduce/reduce conflict in state {} on `{}`: reduce {}, or reduce {} (first chosen)",
+                self.state,
+                self.terminal.spelling(),
+                kept,
+                dropped
+            ),
+        }
+    }
+}
+
+/// LALR(1) parsing table generated from `resource/ast/LALR(1) Grammar.txt`. The grammar
+/// uses the action symbols of the LL(1) table with actions, so reductions build the same
+/// AST. Shift/reduce conflicts are resolved with the precedence declarations of the
+/// grammar, as in yacc: a production has the precedence of its rightmost terminal,
+/// unless it is given another one with `%prec`.
+pub struct LalrTable {
+    terminals: Vec<Terminal>,
+    terminal_indices: HashMap<Terminal, usize>,
+    /// precedence level and associativity of each declared terminal, the loosest first
+    terminal_precedences: HashMap<usize, (usize, Associativity)>,
+    nonterminals: Vec<LrNonTerminal>,
+    productions: Vec<Production>,
+    actions: Vec<HashMap<usize, LrAction>>,
+    gotos: Vec<HashMap<usize, usize>>,
+    conflicts: Vec<Conflict>,
+}
+
+impl LalrTable {
+    pub fn new(grammar: &str) -> Self {
+        let mut table = Self {
+            terminals: vec![],
+            terminal_indices: HashMap::new(),
+            terminal_precedences: HashMap::new(),
+            nonterminals: vec![LrNonTerminal::Augmented],
+            productions: vec![],
+            actions: vec![],
+            gotos: vec![],
+            conflicts: vec![],
+        };
+        table.build_grammar(grammar);
+        let (first_sets, nullable) = table.compute_first_sets();
+        let (kernels, transitions) = table.build_lr0_states();
+        let lookaheads = table.compute_lookaheads(&kernels, &transitions, &first_sets, &nullable);
+        table.fill_table(&kernels, &transitions, &lookaheads, &first_sets, &nullable);
+        table
+    }
+
+    pub fn get_action(&self, state: usize, terminal: &Terminal) -> Option<LrAction> {
+        let terminal_index = self.terminal_indices.get(terminal)?;
+        self.actions[state].get(terminal_index).copied()
+    }
+
+    /// State after reducing production `production` with `state` on top of the stack
+    pub fn get_goto(&self, state: usize, production: usize) -> Option<usize> {
+        self.gotos[state]
+            .get(&self.productions[production].lhs)
+            .copied()
+    }
+
+    /// Number of symbols to pop, number of symbols on top of the stack the actions cover,
+    /// and the actions to perform when reducing a production. The actions of a marker
+    /// cover the symbols before it in the production it stands in.
+    pub fn get_reduction(&self, production: usize) -> (usize, usize, &[ActionSymbol]) {
+        let production = &self.productions[production];
+        (
+            production.rhs.len(),
+            production.rhs.len() + production.preceding,
+            &production.actions,
+        )
+    }
+
+    /// Terminals of the source with an action in a state, in a stable order
+    pub fn get_expected_terminals(&self, state: usize) -> Vec<Terminal> {
+        let mut terminals: Vec<Terminal> = self.actions[state]
+            .keys()
+            .map(|terminal_index| self.terminals[*terminal_index].clone())
+            .filter(|terminal| !matches!(terminal, Terminal::ERROR))
+            .collect();
+        terminals.sort();
+        terminals
+    }
+
+    pub fn get_conflicts(&self) -> &[Conflict] {
+        &self.conflicts
+    }
+
+    fn terminal_index(&mut self, terminal: &Terminal) -> usize {
+        if let Some(index) = self.terminal_indices.get(terminal) {
+            return *index;
+        }
+        self.terminals.push(terminal.clone());
+        self.terminal_indices
+            .insert(terminal.clone(), self.terminals.len() - 1);
+        self.terminals.len() - 1
+    }
+
+    fn nonterminal_index(&mut self, name: &str) -> usize {
+        let nonterminal = LrNonTerminal::Named(name.to_string());
+        match self.nonterminals.iter().position(|x| x.eq(&nonterminal)) {
+            Some(index) => index,
+            None => {
+                self.nonterminals.push(nonterminal);
+                self.nonterminals.len() - 1
+            }
+        }
+    }
+
+    /// Read the precedence declarations and the productions of the grammar. The first
+    /// production is the one of the start symbol.
+    fn build_grammar(&mut self, grammar: &str) {
+        self.productions.push(Production {
+            lhs: 0,
+            rhs: vec![],
+            actions: vec![],
+            preceding: 0,
+            precedence: None,
+        });
+        self.terminal_index(&Terminal::EOF);
+
+        let lines: Vec<&str> = grammar
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .collect();
+        let mut level = 0;
+        for line in lines.iter().filter(|line| line.starts_with('%')) {
+            let mut words = line[1..].split_whitespace();
+            let associativity = match words.next() {
+                Some("left") => Associativity::Left,
+                Some("nonassoc") => Associativity::None,
+                _ => panic!("Unknown precedence declaration `{}`", line),
+            };
+            level += 1;
+            for word in words {
+                let terminal = Self::read_terminal(word);
+                let terminal_index = self.terminal_index(&terminal);
+                self.terminal_precedences
+                    .insert(terminal_index, (level, associativity));
+            }
+        }
+        // the operators of the expression parser bind tighter than the declared terminals
+        for operator in OPERATORS {
+            let terminal_index =
+                self.terminal_index(&Terminal::ValidTokenType(operator.token_type));
+            self.terminal_precedences.insert(
+                terminal_index,
+                (level + operator.precedence, operator.associativity),
+            );
+        }
+        for line in lines.iter().filter(|line| !line.starts_with('%')) {
+            let (lhs, rhs) = line
+                .split_once('→')
+                .unwrap_or_else(|| panic!("Expected `→` in production `{}`", line));
+            self.read_production(lhs.trim(), rhs.trim());
+        }
+
+        let start = match self.productions.get(1) {
+            Some(production) => production.lhs,
+            None => panic!("The grammar has no productions"),
+        };
+        self.productions[0].rhs = vec![GrammarSymbol::NonTerminal(start)];
+    }
+
+    fn read_production(&mut self, lhs: &str, rhs: &str) {
+        let lhs = self.nonterminal_index(lhs);
+        let (rhs, explicit_precedence) = match rhs.split_once("%prec") {
+            Some((rhs, terminal)) => {
+                let terminal = self.terminal_index(&Self::read_terminal(terminal.trim()));
+                let precedence = self.terminal_precedences.get(&terminal).copied();
+                if precedence.is_none() {
+                    panic!(
+                        "No precedence declared for `%prec {}`",
+                        self.terminals[terminal]
+                    );
+                }
+                (rhs, precedence)
+            }
+            None => (rhs, None),
+        };
+
+        let mut symbols = vec![];
+        let mut pending_actions = vec![];
+        let mut precedence = None;
+        for word in rhs.split_whitespace() {
+            if word == "&epsilon" {
+                continue;
+            }
+            if let Ok(action_symbol) = ActionSymbol::from_str(word) {
+                pending_actions.push(action_symbol);
+                continue;
+            }
+            // actions followed by a symbol are performed by a marker reduced before it
+            if !pending_actions.is_empty() {
+                let marker = self.add_marker(std::mem::take(&mut pending_actions), symbols.len());
+                symbols.push(marker);
+            }
+            if word.chars().any(|c| c.is_lowercase()) {
+                let terminal = self.terminal_index(&Self::read_terminal(word));
+                if let Some(terminal_precedence) = self.terminal_precedences.get(&terminal) {
+                    precedence = Some(*terminal_precedence);
+                }
+                symbols.push(GrammarSymbol::Terminal(terminal));
+            } else {
+                symbols.push(GrammarSymbol::NonTerminal(self.nonterminal_index(word)));
+            }
+        }
+        self.productions.push(Production {
+            lhs,
+            rhs: symbols,
+            actions: pending_actions,
+            preceding: 0,
+            precedence: explicit_precedence.or(precedence),
+        });
+    }
+
+    /// A new marker nonterminal, with its epsilon production performing the actions, after
+    /// `preceding` symbols of the production it stands in
+    fn add_marker(&mut self, action_symbols: Vec<ActionSymbol>, preceding: usize) -> GrammarSymbol {
+        self.nonterminals
+            .push(LrNonTerminal::Marker(action_symbols.clone()));
+        let marker = self.nonterminals.len() - 1;
+        self.productions.push(Production {
+            lhs: marker,
+            rhs: vec![],
+            actions: action_symbols,
+            preceding,
+            precedence: None,
+        });
+        GrammarSymbol::NonTerminal(marker)
+    }
+
+    fn read_terminal(word: &str) -> Terminal {
+        if word == "error" {
+            return Terminal::ERROR;
+        }
+        match Symbol::from_string(word) {
+            Symbol::Terminal(terminal) => terminal,
+            _ => panic!("Expected a terminal, found `{}`", word),
+        }
+    }
+
+    fn compute_first_sets(&self) -> (Vec<BTreeSet<usize>>, Vec<bool>) {
+        let mut first_sets = vec![BTreeSet::new(); self.nonterminals.len()];
+        let mut nullable = vec![false; self.nonterminals.len()];
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for production in self.productions.iter() {
+                let mut all_nullable = true;
+                for symbol in production.rhs.iter() {
+                    match symbol {
+                        GrammarSymbol::Terminal(terminal) => {
+                            changed |= first_sets[production.lhs].insert(*terminal);
+                            all_nullable = false;
+                        }
+                        GrammarSymbol::NonTerminal(nonterminal) => {
+                            let first_set = first_sets[*nonterminal].clone();
+                            for terminal in first_set {
+                                changed |= first_sets[production.lhs].insert(terminal);
+                            }
+                            all_nullable &= nullable[*nonterminal];
+                        }
+                    }
+                    if !all_nullable {
+                        break;
+                    }
+                }
+                if all_nullable && !nullable[production.lhs] {
+                    nullable[production.lhs] = true;
+                    changed = true;
+                }
+            }
+        }
+        (first_sets, nullable)
+    }
+
+    /// FIRST of a sequence of symbols followed by a lookahead
+    fn first_of_sequence(
+        symbols: &[GrammarSymbol],
+        lookahead: usize,
+        first_sets: &[BTreeSet<usize>],
+        nullable: &[bool],
+    ) -> BTreeSet<usize> {
+        let mut result = BTreeSet::new();
+        for symbol in symbols {
+            match symbol {
+                GrammarSymbol::Terminal(terminal) => {
+                    result.insert(*terminal);
+                    return result;
+                }
+                GrammarSymbol::NonTerminal(nonterminal) => {
+                    result.extend(first_sets[*nonterminal].iter());
+                    if !nullable[*nonterminal] {
+                        return result;
+                    }
+                }
+            }
+        }
+        result.insert(lookahead);
+        result
+    }
+
+    /// LR(0) closure of a set of items (production, dot position)
+    fn closure(&self, kernel: &[(usize, usize)]) -> Vec<(usize, usize)> {
+        let mut items: Vec<(usize, usize)> = kernel.to_vec();
+        let mut added: HashSet<usize> = HashSet::new();
+        let mut i = 0;
+        while i < items.len() {
+            let (production, dot) = items[i];
+            if let Some(GrammarSymbol::NonTerminal(nonterminal)) =
+                self.productions[production].rhs.get(dot)
+            {
+                if added.insert(*nonterminal) {
+                    for (index, candidate) in self.productions.iter().enumerate() {
+                        if candidate.lhs == *nonterminal {
+                            items.push((index, 0));
+                        }
+                    }
+                }
+            }
+            i += 1;
+        }
+        items
+    }
+
+    /// LR(1) closure of a set of items (production, dot position, lookahead)
+    fn closure_with_lookaheads(
+        &self,
+        kernel: Vec<(usize, usize, usize)>,
+        first_sets: &[BTreeSet<usize>],
+        nullable: &[bool],
+    ) -> Vec<(usize, usize, usize)> {
+        let mut items = kernel;
+        let mut seen: HashSet<(usize, usize, usize)> = items.iter().copied().collect();
+        let mut i = 0;
+        while i < items.len() {
+            let (production, dot, lookahead) = items[i];
+            let rhs = &self.productions[production].rhs;
+            if let Some(GrammarSymbol::NonTerminal(nonterminal)) = rhs.get(dot) {
+                let lookaheads =
+                    Self::first_of_sequence(&rhs[dot + 1..], lookahead, first_sets, nullable);
+                for (index, candidate) in self.productions.iter().enumerate() {
+                    if candidate.lhs == *nonterminal {
+                        for lookahead in lookaheads.iter() {
+                            if seen.insert((index, 0, *lookahead)) {
+                                items.push((index, 0, *lookahead));
+                            }
+                        }
+                    }
+                }
+            }
+            i += 1;
+        }
+        items
+    }
+
+    /// Kernels of the LR(0) states and the transitions between them
+    #[allow(clippy::type_complexity)]
+    fn build_lr0_states(&self) -> (Vec<Vec<(usize, usize)>>, Vec<HashMap<GrammarSymbol, usize>>) {
+        let mut kernels: Vec<Vec<(usize, usize)>> = vec![vec![(0, 0)]];
+        let mut state_of_kernel: HashMap<Vec<(usize, usize)>, usize> = HashMap::new();
+        state_of_kernel.insert(kernels[0].clone(), 0);
+        let mut transitions: Vec<HashMap<GrammarSymbol, usize>> = vec![];
+        let mut state = 0;
+        while state < kernels.len() {
+            // group the advanced items by the symbol after the dot, in a stable order
+            let mut next_kernels: Vec<(GrammarSymbol, Vec<(usize, usize)>)> = vec![];
+            for (production, dot) in self.closure(&kernels[state]) {
+                if let Some(symbol) = self.productions[production].rhs.get(dot) {
+                    match next_kernels.iter_mut().find(|(x, _)| x == symbol) {
+                        Some((_, kernel)) => kernel.push((production, dot + 1)),
+                        None => next_kernels.push((*symbol, vec![(production, dot + 1)])),
+                    }
+                }
+            }
+            let mut state_transitions = HashMap::new();
+            for (symbol, mut kernel) in next_kernels {
+                kernel.sort_unstable();
+                kernel.dedup();
+                let target = match state_of_kernel.get(&kernel) {
+                    Some(target) => *target,
+                    None => {
+                        kernels.push(kernel.clone());
+                        state_of_kernel.insert(kernel, kernels.len() - 1);
+                        kernels.len() - 1
+                    }
+                };
+                state_transitions.insert(symbol, target);
+            }
+            transitions.push(state_transitions);
+            state += 1;
+        }
+        (kernels, transitions)
+    }
+
+    /// Lookaheads of each kernel item, found by spontaneous generation and propagation
+    fn compute_lookaheads(
+        &self,
+        kernels: &[Vec<(usize, usize)>],
+        transitions: &[HashMap<GrammarSymbol, usize>],
+        first_sets: &[BTreeSet<usize>],
+        nullable: &[bool],
+    ) -> Vec<Vec<BTreeSet<usize>>> {
+        let mut lookaheads: Vec<Vec<BTreeSet<usize>>> = kernels
+            .iter()
+            .map(|kernel| vec![BTreeSet::new(); kernel.len()])
+            .collect();
+        lookaheads[0][0].insert(self.terminal_indices[&Terminal::EOF]);
+        let mut propagations: Vec<((usize, usize), (usize, usize))> = vec![];
+
+        for (state, kernel) in kernels.iter().enumerate() {
+            for (item_index, (production, dot)) in kernel.iter().enumerate() {
+                let closure = self.closure_with_lookaheads(
+                    vec![(*production, *dot, PROPAGATE)],
+                    first_sets,
+                    nullable,
+                );
+                for (closure_production, closure_dot, lookahead) in closure {
+                    let symbol = match self.productions[closure_production].rhs.get(closure_dot) {
+                        Some(symbol) => symbol,
+                        None => continue,
+                    };
+                    let target = transitions[state][symbol];
+                    let target_index = kernels[target]
+                        .binary_search(&(closure_production, closure_dot + 1))
+                        .unwrap();
+                    if lookahead == PROPAGATE {
+                        propagations.push(((state, item_index), (target, target_index)));
+                    } else {
+                        lookaheads[target][target_index].insert(lookahead);
+                    }
+                }
+            }
+        }
+
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for ((from_state, from_item), (to_state, to_item)) in propagations.iter() {
+                let from = lookaheads[*from_state][*from_item].clone();
+                let to = &mut lookaheads[*to_state][*to_item];
+                let size = to.len();
+                to.extend(from);
+                changed |= to.len() != size;
+            }
+        }
+        lookaheads
+    }
+
+    fn fill_table(
+        &mut self,
+        kernels: &[Vec<(usize, usize)>],
+        transitions: &[HashMap<GrammarSymbol, usize>],
+        lookaheads: &[Vec<BTreeSet<usize>>],
+        first_sets: &[BTreeSet<usize>],
+        nullable: &[bool],
+    ) {
+        for (state, kernel) in kernels.iter().enumerate() {
+            // every candidate action for each terminal, resolved afterwards
+            let mut candidates: HashMap<usize, Vec<LrAction>> = HashMap::new();
+            let mut gotos = HashMap::new();
+            for (symbol, target) in transitions[state].iter() {
+                match symbol {
+                    GrammarSymbol::Terminal(terminal) => candidates
+                        .entry(*terminal)
+                        .or_default()
+                        .push(LrAction::Shift(*target)),
+                    GrammarSymbol::NonTerminal(nonterminal) => {
+                        gotos.insert(*nonterminal, *target);
+                    }
+                }
+            }
+            let mut items = vec![];
+            for (item_index, (production, dot)) in kernel.iter().enumerate() {
+                for lookahead in lookaheads[state][item_index].iter() {
+                    items.push((*production, *dot, *lookahead));
+                }
+            }
+            for (production, dot, lookahead) in
+                self.closure_with_lookaheads(items, first_sets, nullable)
+            {
+                if dot == self.productions[production].rhs.len() {
+                    let action = if production == 0 {
+                        LrAction::Accept
+                    } else {
+                        LrAction::Reduce(production)
+                    };
+                    let actions = candidates.entry(lookahead).or_default();
+                    if !actions.contains(&action) {
+                        actions.push(action);
+                    }
+                }
+            }
+
+            let mut actions = HashMap::new();
+            let mut terminals: Vec<usize> = candidates.keys().copied().collect();
+            terminals.sort_unstable();
+            for terminal in terminals {
+                if let Some(action) = self.resolve(state, terminal, &candidates[&terminal]) {
+                    actions.insert(terminal, action);
+                }
+            }
+            self.actions.push(actions);
+            self.gotos.push(gotos);
+        }
+    }
+
+    /// Choose among the candidate actions of a state on a terminal. `None` if the
+    /// precedence declarations make it an error (a non-associative operator).
+    fn resolve(
+        &mut self,
+        state: usize,
+        terminal: usize,
+        candidates: &[LrAction],
+    ) -> Option<LrAction> {
+        let shift = candidates
+            .iter()
+            .find(|action| matches!(action, LrAction::Shift(_) | LrAction::Accept))
+            .copied();
+        let mut reductions: Vec<usize> = candidates
+            .iter()
+            .filter_map(|action| match action {
+                LrAction::Reduce(production) => Some(*production),
+                _ => None,
+            })
+            .collect();
+        reductions.sort_unstable();
+        let reduction = match reductions.first() {
+            Some(production) => *production,
+            None => return shift,
+        };
+        for dropped in reductions.iter().skip(1) {
+            self.conflicts.push(Conflict {
+                state,
+                terminal: self.terminals[terminal].clone(),
+                kind: ConflictKind::ReduceReduce {
+                    kept: self.production_to_string(reduction),
+                    dropped: self.production_to_string(*dropped),
+                },
+            });
+        }
+        let shift = match shift {
+            Some(shift) => shift,
+            None => return Some(LrAction::Reduce(reduction)),
+        };
+
+        match (
+            &self.productions[reduction].precedence,
+            self.terminal_precedences.get(&terminal),
+        ) {
+            (Some((production_level, _)), Some((terminal_level, associativity))) => {
+                if production_level < terminal_level {
+                    Some(shift)
+                } else if production_level > terminal_level {
+                    Some(LrAction::Reduce(reduction))
+                } else {
+                    match associativity {
+                        Associativity::Left => Some(LrAction::Reduce(reduction)),
+                        Associativity::None => None,
+                    }
+                }
+            }
+            _ => {
+                self.conflicts.push(Conflict {
+                    state,
+                    terminal: self.terminals[terminal].clone(),
+                    kind: ConflictKind::ShiftReduce {
+                        production: self.production_to_string(reduction),
+                    },
+                });
+                Some(shift)
+            }
+        }
+    }
+
+    fn production_to_string(&self, production: usize) -> String {
+        let production = &self.productions[production];
+        let rhs: Vec<String> = production
+            .rhs
+            .iter()
+            .map(|symbol| match symbol {
+                GrammarSymbol::Terminal(terminal) => self.terminals[*terminal].to_string(),
+                GrammarSymbol::NonTerminal(nonterminal) => {
+                    self.nonterminals[*nonterminal].to_string()
+                }
+            })
+            .collect();
+        format!(
+            "{} -> {}",
+            self.nonterminals[production.lhs],
+            if rhs.is_empty() {
+                "ε".to_string()
+            } else {
+                rhs.join(" ")
+            }
+        )
+    }
+}
@@ -1,5 +1,12 @@
+pub mod cst;
 mod derivation;
+pub mod lalr_parser;
+mod lalr_table;
 pub mod parser;
+pub mod precedence;
+mod semantic_action;
 mod symbol;
+pub mod syntax_error;
+pub mod trace;
 pub mod tree;
 pub mod util;
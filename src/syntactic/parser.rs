@@ -1,192 +1,475 @@
+use crate::lexical::span::Span;
 use crate::lexical::token::{Token, TokenType, ValidTokenType};
-use crate::semantic::concept::{AtomicConcept, AtomicConceptType, CompositeConcept, Concept};
+use crate::semantic::concept::{AtomicConcept, CompositeConcept, Concept};
+use crate::syntactic::cst::ConcreteSyntaxTree;
 use crate::syntactic::derivation::Derivation;
+use crate::syntactic::precedence::{self, Associativity, Operator};
+use crate::syntactic::semantic_action::{self, ActionTokens, SemanticAction, StackUse};
 use crate::syntactic::symbol::{ActionSymbol, NonTerminal, Symbol, Terminal};
+use crate::syntactic::syntax_error::{Repair, SyntaxErrType, SyntaxError};
+use crate::syntactic::trace::Trace;
 use crate::syntactic::tree::{NodeId, Tree};
 use crate::syntactic::util;
 use std::collections::HashMap;
-use std::error::Error;
 use std::fmt::{Display, Formatter};
-use std::fs::File;
-use std::io::Result as IOResult;
-use std::io::Write;
+
+/// The parse tree, the AST with the source range of each of its nodes, and the syntax
+/// errors recovered from
+pub type ParseOutput = (
+    ConcreteSyntaxTree,
+    Tree<Concept>,
+    HashMap<NodeId, Span>,
+    Vec<SyntaxError>,
+);
+
+/// Parsing is aborted after this many syntax errors
+pub(crate) const MAX_SYNTAX_ERRORS: usize = 50;
+/// Errors closer than this many matched tokens to the previous one are not reported
+const MIN_TOKENS_BETWEEN_ERRORS: usize = 3;
+/// Number of tokens looked at when trying a repair
+const REPAIR_WINDOW: usize = 10;
+/// A repair is only applied if the parser can match this many tokens after it
+const REPAIR_MIN_PROGRESS: usize = 3;
 
 pub struct Parser {
     parsing_table: HashMap<(NonTerminal, Terminal), Derivation>,
     first_set: HashMap<NonTerminal, Vec<Terminal>>,
     follow_set: HashMap<NonTerminal, Vec<Terminal>>,
+    sync_set: HashMap<NonTerminal, Vec<Terminal>>,
+    semantic_actions: HashMap<ActionSymbol, SemanticAction>,
+    /// how the nonterminals use the semantic stack, given whether a dot is outstanding
+    stack_uses: HashMap<(NonTerminal, bool), StackUse>,
+    trace: Option<Trace>,
+}
+
+impl Default for Parser {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Parser {
     pub fn new() -> Self {
         let (first_set, follow_set, _) = util::read_first_follow_set_and_endable();
+        let mut parsing_table = util::read_parsing_table();
+        precedence::complete_parsing_table(&mut parsing_table);
+        let semantic_actions = util::read_semantic_actions();
+        let mut stack_uses = semantic_action::get_stack_uses(&parsing_table, &semantic_actions);
+        // precedence climbing always leaves one expression
+        for nonterminal in [
+            NonTerminal::Expr,
+            NonTerminal::RelExpr,
+            NonTerminal::AndExpr,
+            NonTerminal::CompareExpr,
+            NonTerminal::ArithExpr,
+        ] {
+            for outstanding_dot in [false, true] {
+                let stack_use = StackUse {
+                    pops: 0,
+                    pushes: 1,
+                    outstanding_dot,
+                };
+                stack_uses.insert((nonterminal.clone(), outstanding_dot), stack_use);
+            }
+        }
         Self {
-            parsing_table: util::read_parsing_table(),
+            stack_uses,
+            parsing_table,
+            sync_set: util::build_sync_set(&first_set, &follow_set),
+            semantic_actions,
             first_set,
             follow_set,
+            trace: None,
         }
     }
-    pub fn parse(
-        &mut self,
-        raw_tokens: Vec<Token>,
-    ) -> Result<(Tree<SymbolOrToken>, Tree<Concept>), SyntaxError> {
-        // debug only
-        let mut error_file = File::create("resource/syntax/outsyntaxerrors").unwrap();
-        let mut derivation_file = File::create("resource/syntax/outderivations").unwrap();
 
-        let mut parsing_tree: Tree<SymbolOrToken> = Tree::new();
-        let mut parsing_stack: Vec<NodeId> = Vec::new();
-        let mut ast: Tree<Concept> = Tree::new();
-        let mut semantic_stack: Vec<NodeId> = Vec::new();
-        let mut token_index: usize = 0;
-        let mut current_node: NodeId;
-        let mut outstanding_dot: bool = false;
+    /// Trace the derivations of the following runs of `parse`, or stop tracing with `None`
+    pub fn set_trace(&mut self, trace: Option<Trace>) {
+        self.trace = trace;
+    }
 
+    pub fn parse(&mut self, raw_tokens: Vec<Token>) -> Result<ParseOutput, Vec<SyntaxError>> {
+        let mut parsing_tree: Tree<SymbolOrToken> = Tree::new();
         let start_node_id = parsing_tree.insert_node(
             None,
             SymbolOrToken::Symbol(Symbol::NonTerminal(NonTerminal::Start)),
         );
-        // helper function to handle a derivation hit in the table
-        parsing_stack.push(start_node_id);
-        let tokens = raw_tokens
-            .iter()
-            .filter(|token| {
-                !matches!(
-                    token.token_type,
-                    TokenType::ValidTokenType(ValidTokenType::BlockCmt)
-                ) && !matches!(
-                    token.token_type,
-                    TokenType::ValidTokenType(ValidTokenType::InlineCmt)
-                )
-            })
-            .cloned()
-            .collect::<Vec<Token>>();
+        let tokens = prepare_tokens(&raw_tokens);
 
-        while !parsing_stack.is_empty() {
-            // ignore comments
+        let mut state = ParseState {
+            tokens,
+            token_index: 0,
+            parsing_tree,
+            parsing_stack: vec![start_node_id],
+            ast: Tree::new(),
+            ast_spans: HashMap::new(),
+            ast_anchors: HashMap::new(),
+            production_starts: HashMap::new(),
+            semantic_stack: Vec::new(),
+            outstanding_dot: false,
+            trace: self.trace.take(),
+            errors: Vec::new(),
+            error_count: 0,
+            tokens_since_error: MIN_TOKENS_BETWEEN_ERRORS,
+            expressions: Vec::new(),
+        };
+        if let Some(trace) = &mut state.trace {
+            trace.restart();
+        }
+        self.drive(&mut state, 0);
+        if let Some(mut trace) = state.trace.take() {
+            trace.flush();
+            self.trace = Some(trace);
+        }
+        if !state.is_aborted() && !state.lookahead().eq(&Terminal::EOF) {
+            let token = state.tokens[state.token_index].clone();
+            state.report_error(SyntaxError::new(
+                SyntaxErrType::TrailingToken,
+                token,
+                vec![Terminal::EOF],
+                None,
+            ));
+        }
+        match state.semantic_stack.first() {
+            Some(prog) if !state.ast.get_node_value(*prog).is_error() => state.ast.set_root(*prog),
+            // not even the program was derived
+            _ => return Err(state.errors),
+        }
+        // let mut ast_output_file = File::create("resource/ast/outderivations").unwrap();
+        // ast_output_file.write_all(format!("{}", ast).as_bytes());
+        // println!("{}", ast);
+        Ok((
+            ConcreteSyntaxTree::new(state.parsing_tree),
+            state.ast,
+            state.ast_spans,
+            state.errors,
+        ))
+    }
 
-            match parsing_tree.get_node_value(*parsing_stack.last().unwrap()) {
+    /// Run the table-driven loop until the parsing stack shrinks to `stop_depth`
+    fn drive(&self, state: &mut ParseState, stop_depth: usize) {
+        let mut current_node: NodeId;
+        while state.parsing_stack.len() > stop_depth {
+            if state.is_aborted() {
+                // unwind the parsing stack, still performing the actions to close the AST
+                current_node = *state.parsing_stack.last().unwrap();
+                if let SymbolOrToken::Symbol(Symbol::ActionSymbol(action_symbol)) =
+                    state.parsing_tree.get_node_value(current_node)
+                {
+                    let action_symbol = action_symbol.clone();
+                    state.parsing_stack.pop();
+                    self.perform_semantic_action(state, current_node, &action_symbol);
+                } else {
+                    self.pop_unparsed(state);
+                }
+                continue;
+            }
+            match state
+                .parsing_tree
+                .get_node_value(*state.parsing_stack.last().unwrap())
+            {
                 SymbolOrToken::Symbol(symbol) => {
                     match symbol {
                         Symbol::Terminal(terminal) => {
-                            if let Terminal::ValidTokenType(top_token_type) = terminal {
-                                // parsing stack top is validTokenType: try to match token
-                                if let TokenType::ValidTokenType(lookahead_token_type) =
-                                    &tokens[token_index].token_type
-                                {
-                                    if top_token_type.eq(lookahead_token_type) {
-                                        // match token
-                                        self.write_match(
-                                            &mut derivation_file,
-                                            lookahead_token_type,
-                                        );
-                                        current_node = parsing_stack.pop().unwrap();
-                                        parsing_tree.insert_node(
-                                            Some(current_node),
-                                            SymbolOrToken::Token(tokens[token_index].clone()),
-                                        );
-                                        token_index += 1;
-                                    } else {
-                                        self.skip_error(
-                                            &tokens,
-                                            &mut token_index,
-                                            &mut parsing_stack,
-                                            &parsing_tree,
-                                            &mut error_file,
-                                        );
-                                    }
-                                } else {
-                                    // token_index += 1;
-                                    self.skip_error(
-                                        &tokens,
-                                        &mut token_index,
-                                        &mut parsing_stack,
-                                        &parsing_tree,
-                                        &mut error_file,
-                                    );
-                                }
+                            // parsing stack top is a terminal: try to match token
+                            if terminal.eq(&state.lookahead()) {
+                                state.trace_match();
+                                let token = state.tokens[state.token_index].clone();
+                                current_node = state.parsing_stack.pop().unwrap();
+                                state
+                                    .parsing_tree
+                                    .insert_node(Some(current_node), SymbolOrToken::Token(token));
+                                state.token_index += 1;
+                                state.tokens_since_error += 1;
+                            } else {
+                                self.skip_error(state);
                             }
                         }
                         Symbol::NonTerminal(nonterminal) => {
                             // parsing stack top is nonterminal: query parsing table
-                            if token_index >= tokens.len() {
-                                // end of token stream: try EOF
-                                match self
-                                    .parsing_table
-                                    .get(&(nonterminal.clone(), Terminal::EOF))
-                                {
-                                    None => {
-                                        self.skip_error(
-                                            &tokens,
-                                            &mut token_index,
-                                            &mut parsing_stack,
-                                            &parsing_tree,
-                                            &mut error_file,
-                                        );
-                                    }
-                                    Some(derivation) => {
-                                        self.write_derivation(&mut derivation_file, derivation);
-                                        current_node = parsing_stack.pop().unwrap();
-                                        Self::handle_derivation(
-                                            &mut parsing_stack,
-                                            current_node.clone(),
-                                            derivation,
-                                            &mut parsing_tree,
-                                        );
-                                    }
+                            // get new derivation: push new symbols into the stack
+                            let nonterminal = &nonterminal.clone();
+                            match self.get_derivation(nonterminal, &state.lookahead()) {
+                                None => {
+                                    self.skip_error(state);
                                 }
-                            } else if let TokenType::ValidTokenType(valid_token_type) =
-                                &tokens[token_index].token_type
-                            {
-                                // get new derivation: push new symbols into the stack
-                                match self.parsing_table.get(&(
-                                    nonterminal.clone(),
-                                    Terminal::ValidTokenType(valid_token_type.clone()),
-                                )) {
-                                    None => {
-                                        // token_index += 1;
-                                        self.skip_error(
-                                            &tokens,
-                                            &mut token_index,
-                                            &mut parsing_stack,
-                                            &parsing_tree,
-                                            &mut error_file,
-                                        );
-                                    }
-                                    Some(derivation) => {
-                                        self.write_derivation(&mut derivation_file, derivation);
-                                        current_node = parsing_stack.pop().unwrap();
-                                        // insert node
-                                        Self::handle_derivation(
-                                            &mut parsing_stack,
-                                            current_node.clone(),
-                                            derivation,
-                                            &mut parsing_tree,
+                                Some(derivation) => {
+                                    if let Some(min_precedence) =
+                                        precedence::min_precedence_of(nonterminal)
+                                    {
+                                        // expressions are parsed by precedence climbing
+                                        let expected_rel_expr =
+                                            matches!(nonterminal, NonTerminal::RelExpr);
+                                        current_node = state.parsing_stack.pop().unwrap();
+                                        self.parse_expression(
+                                            state,
+                                            current_node,
+                                            min_precedence,
+                                            expected_rel_expr,
                                         );
+                                        continue;
                                     }
+                                    current_node = state.parsing_stack.pop().unwrap();
+                                    state.trace_derivation(derivation);
+                                    state
+                                        .production_starts
+                                        .insert(current_node, state.token_index);
+                                    // insert node
+                                    Self::handle_derivation(
+                                        &mut state.parsing_stack,
+                                        current_node,
+                                        derivation,
+                                        &mut state.parsing_tree,
+                                    );
                                 }
                             }
                         }
                         Symbol::ActionSymbol(action_symbol) => {
-                            parsing_stack.pop();
-                            Self::perform_semantic_action(
-                                &token_index,
-                                &tokens,
-                                action_symbol,
-                                &mut semantic_stack,
-                                &mut ast,
-                                &mut outstanding_dot,
-                            )
+                            let action_symbol = action_symbol.clone();
+                            current_node = state.parsing_stack.pop().unwrap();
+                            self.perform_semantic_action(state, current_node, &action_symbol);
                         }
                     }
                 }
                 SymbolOrToken::Token(_) => panic!("Token appear on the parsing stack"),
             }
         }
-        // let mut ast_output_file = File::create("resource/ast/outderivations").unwrap();
-        // ast_output_file.write_all(format!("{}", ast).as_bytes());
-        // println!("{}", ast);
-        Ok((parsing_tree, ast))
+    }
+
+    /// Look up the parsing table. A token the table has no entry for, e.g. an operator
+    /// missing from it, is a syntax error.
+    fn get_derivation(
+        &self,
+        nonterminal: &NonTerminal,
+        lookahead: &Terminal,
+    ) -> Option<&Derivation> {
+        self.parsing_table
+            .get(&(nonterminal.clone(), lookahead.clone()))
+    }
+
+    /// Parse the expression under `node` by precedence climbing, and push its concept
+    /// onto the semantic stack. This replaces the `L`, `M` and `O` actions.
+    fn parse_expression(
+        &self,
+        state: &mut ParseState,
+        node: NodeId,
+        min_precedence: usize,
+        expect_rel_expr: bool,
+    ) {
+        let nonterminal = match state.parsing_tree.get_node_value(node) {
+            SymbolOrToken::Symbol(symbol) => symbol.clone(),
+            SymbolOrToken::Token(_) => panic!("Parsing an expression under a token"),
+        };
+        state.expressions.push(ExpressionStack {
+            depth: state.parsing_stack.len(),
+            symbols: vec![nonterminal],
+            in_sync: true,
+        });
+        let start = state.token_index;
+        let (mut expr, root_operator) = self.climb(state, node, min_precedence);
+        // any expression may be a condition, its type is checked later. A condition that
+        // is not a comparison and cannot end here is likely to be a mistyped one.
+        if expect_rel_expr
+            && !state.is_aborted()
+            && !matches!(
+                root_operator,
+                Some(Operator {
+                    concept: CompositeConcept::RelExpr
+                        | CompositeConcept::AndExpr
+                        | CompositeConcept::OrExpr,
+                    ..
+                })
+            )
+            && !self.follow_set[&NonTerminal::RelExpr].contains(&state.lookahead())
+        {
+            let relational_terminals: Vec<Terminal> = precedence::OPERATORS
+                .iter()
+                .filter(|operator| matches!(operator.concept, CompositeConcept::RelExpr))
+                .map(|operator| Terminal::ValidTokenType(operator.token_type))
+                .collect();
+            // e.g. `=` written for `==`
+            let repair = self.find_repair(
+                state,
+                &[
+                    Symbol::NonTerminal(NonTerminal::RelOp),
+                    Symbol::NonTerminal(NonTerminal::ArithExpr),
+                ],
+                relational_terminals
+                    .iter()
+                    .map(|terminal| Repair::Replace(terminal.clone()))
+                    .collect(),
+            );
+            let token = state.tokens[state.token_index].clone();
+            state.report_error(
+                SyntaxError::new(
+                    SyntaxErrType::ExpectRelExpr,
+                    token,
+                    relational_terminals,
+                    Some(NonTerminal::RelExpr),
+                )
+                .with_repair(repair.clone()),
+            );
+            if let Some(repair) = repair {
+                state.apply_repair(&repair);
+                (expr, _) = self.climb_from(state, node, min_precedence, (expr, start));
+            }
+        }
+        // what is left of the expression derives the empty string
+        self.derive_expression(state, None);
+        state.expressions.pop();
+        state.semantic_stack.push(expr);
+    }
+
+    /// Apply the derivations of the parsing table to the expression being climbed, until
+    /// `target` is on top of its stack, and pop it; with `None`, until the stack is
+    /// empty. The climber does not need them, but they make up the leftmost derivation
+    /// of the trace and the rest of the expression repairs are tried against.
+    /// Returns false once the climber and the table disagree, e.g. on a chained operator.
+    fn derive_expression(&self, state: &mut ParseState, target: Option<&Symbol>) -> bool {
+        while state.expressions.last().unwrap().in_sync {
+            let lookahead = state.lookahead();
+            let expression = state.expressions.last_mut().unwrap();
+            let derivation = match expression.symbols.pop() {
+                None if target.is_none() => return true,
+                Some(symbol) if target == Some(&symbol) => return true,
+                Some(Symbol::NonTerminal(nonterminal)) => {
+                    self.get_derivation(&nonterminal, &lookahead)
+                }
+                _ => None,
+            };
+            match derivation {
+                Some(derivation) => {
+                    state.trace_derivation(derivation);
+                    state.expressions.last_mut().unwrap().symbols.extend(
+                        derivation
+                            .to
+                            .iter()
+                            .rev()
+                            .filter(|symbol| {
+                                !matches!(
+                                    symbol,
+                                    Symbol::Terminal(Terminal::EPSILON) | Symbol::ActionSymbol(_)
+                                )
+                            })
+                            .cloned(),
+                    );
+                }
+                None => state.expressions.last_mut().unwrap().in_sync = false,
+            }
+        }
+        false
+    }
+
+    /// Return the concept of the expression and the operator at its root
+    fn climb(
+        &self,
+        state: &mut ParseState,
+        node: NodeId,
+        min_precedence: usize,
+    ) -> (NodeId, Option<&'static Operator>) {
+        let start = state.token_index;
+        let lhs = self.parse_operand(state, node);
+        self.climb_from(state, node, min_precedence, (lhs, start))
+    }
+
+    /// Continue climbing with an already parsed left operand, given with the index of
+    /// its first token. The node of an operator covers the tokens of its operands.
+    fn climb_from(
+        &self,
+        state: &mut ParseState,
+        node: NodeId,
+        min_precedence: usize,
+        (mut lhs, start): (NodeId, usize),
+    ) -> (NodeId, Option<&'static Operator>) {
+        let mut root_operator = None;
+        // precedence of the last operator if it cannot be chained
+        let mut unchainable_precedence: Option<usize> = None;
+        while let Some(operator) = state.tokens[state.token_index]
+            .get_valid_token_type()
+            .and_then(|token_type| precedence::get_operator(&token_type))
+        {
+            if operator.precedence < min_precedence || state.is_aborted() {
+                break;
+            }
+            let operator_token = state.tokens[state.token_index].clone();
+            if unchainable_precedence == Some(operator.precedence) {
+                let nonterminal = match state.parsing_tree.get_node_value(node) {
+                    SymbolOrToken::Symbol(Symbol::NonTerminal(nonterminal)) => {
+                        Some(nonterminal.clone())
+                    }
+                    _ => None,
+                };
+                state.report_error(SyntaxError::new(
+                    SyntaxErrType::ChainedOperator,
+                    operator_token.clone(),
+                    vec![],
+                    nonterminal,
+                ));
+            }
+            self.derive_expression(
+                state,
+                Some(&Symbol::Terminal(Terminal::ValidTokenType(
+                    operator.token_type,
+                ))),
+            );
+            state.trace_match();
+            state
+                .parsing_tree
+                .insert_node(Some(node), SymbolOrToken::Token(operator_token.clone()));
+            state.token_index += 1;
+            state.tokens_since_error += 1;
+
+            // operators are left associative or cannot be chained
+            let (rhs, _) = self.climb(state, node, operator.precedence + 1);
+            let operator_span = operator_token.get_span();
+            let operator_node = state.ast.insert_node(
+                None,
+                Concept::AtomicConcept(AtomicConcept {
+                    atomic_concept_type: operator.operator_type.clone(),
+                    value: operator_token.lexeme,
+                }),
+            );
+            state.ast_spans.insert(operator_node, operator_span);
+            let expr = state.ast.insert_node_over(
+                Concept::CompositeConcept(operator.concept.clone()),
+                &[lhs, operator_node, rhs],
+            );
+            state.ast_spans.insert(
+                expr,
+                state.tokens[start]
+                    .get_span()
+                    .merge(&state.tokens[state.token_index - 1].get_span()),
+            );
+            lhs = expr;
+            root_operator = Some(operator);
+            unchainable_precedence = match operator.associativity {
+                Associativity::None => Some(operator.precedence),
+                Associativity::Left => None,
+            };
+        }
+        (lhs, root_operator)
+    }
+
+    /// Parse a `Factor` under `node` with the table-driven loop and return its concept
+    fn parse_operand(&self, state: &mut ParseState, node: NodeId) -> NodeId {
+        let factor = Symbol::NonTerminal(NonTerminal::Factor);
+        // the rest of the expression is not on the parsing stack
+        self.derive_expression(state, Some(&factor));
+        let factor_node = state
+            .parsing_tree
+            .insert_node(Some(node), SymbolOrToken::Symbol(factor));
+        state.parsing_stack.push(factor_node);
+        let semantic_depth = state.semantic_stack.len();
+        self.drive(state, state.parsing_stack.len() - 1);
+        // a factor makes exactly one concept, unless it was broken by a syntax error
+        let operand = semantic_action::pop_item(
+            &mut state.semantic_stack,
+            &mut state.ast,
+            semantic_depth,
+            "operand",
+        );
+        state.semantic_stack.truncate(semantic_depth);
+        operand
     }
 
     fn handle_derivation(
@@ -195,495 +478,441 @@ impl Parser {
         derivation: &Derivation,
         parsing_tree: &mut Tree<SymbolOrToken>,
     ) {
-        println!("{}", derivation);
-        for symbol in derivation.to.iter().rev() {
-            // insert node and push into stack
+        // insert nodes in order, and push them into the stack with the leftmost on top
+        let mut node_ids: Vec<NodeId> = Vec::new();
+        for symbol in derivation.to.iter() {
             match symbol {
-                Symbol::Terminal(terminal) => {
-                    if matches!(terminal, Terminal::EPSILON) {
-                    } else {
-                        let node_id = parsing_tree.insert_node(
-                            Some(current_node),
-                            SymbolOrToken::Symbol(Symbol::Terminal(terminal.clone())),
-                        );
-                        parsing_stack.push(node_id);
-                    }
-                }
-                Symbol::NonTerminal(nonterminal) => {
-                    let node_id = parsing_tree.insert_node(
-                        Some(current_node),
-                        SymbolOrToken::Symbol(Symbol::NonTerminal(nonterminal.clone())),
-                    );
-                    parsing_stack.push(node_id);
-                }
-                Symbol::ActionSymbol(action_symbol) => {
-                    let node_id = parsing_tree.insert_node(
-                        Some(current_node),
-                        SymbolOrToken::Symbol(Symbol::ActionSymbol(action_symbol.clone())),
-                    );
-                    parsing_stack.push(node_id);
-                }
+                Symbol::Terminal(Terminal::EPSILON) => {}
+                symbol => node_ids.push(
+                    parsing_tree
+                        .insert_node(Some(current_node), SymbolOrToken::Symbol(symbol.clone())),
+                ),
             }
         }
+        parsing_stack.extend(node_ids.into_iter().rev());
     }
 
+    /// Perform the semantic action declared for the action symbol at `node` of the
+    /// parsing tree
     fn perform_semantic_action(
-        token_index: &usize,
-        tokens: &Vec<Token>,
+        &self,
+        state: &mut ParseState,
+        node: NodeId,
         action_symbol: &ActionSymbol,
-        semantic_stack: &mut Vec<NodeId>,
-        ast: &mut Tree<Concept>,
-        outstanding_dot: &mut bool,
     ) {
-        // println!("perform action {:?}", action_symbol);
-        match action_symbol {
-            ActionSymbol::A // id, floatLit, intLit
-            | ActionSymbol::K // relOp
-            | ActionSymbol::N // addOp
-            | ActionSymbol::P // multOp
-            | ActionSymbol::A5 // void
-            | ActionSymbol::B6 => { // visibility
-                let concept = Concept::from_terminal_token(tokens[token_index - 1].clone()).unwrap();
-                let concept_node_id = ast.insert_node(None, concept);
-                semantic_stack.push(concept_node_id);
-            }
-            ActionSymbol::B => { // dot
-                let operand1 = semantic_stack.pop().unwrap();
-                let operand2 = semantic_stack.pop().unwrap();
-                let dot_concept = Concept::CompositeConcept(CompositeConcept::Dot);
-                let dot_node_id = ast.insert_node(None, dot_concept);
-                ast.move_node_under_prepend(operand1, Some(dot_node_id));
-                ast.move_node_under_prepend(operand2, Some(dot_node_id));
-                semantic_stack.push(dot_node_id);
-            }
-            ActionSymbol::C => { // indexList
-                let index_list_concept = Concept::CompositeConcept(CompositeConcept::IndexList);
-                let index_list_node_id = ast.insert_node(None, index_list_concept);
-                while !ast.get_node_value(*semantic_stack.last().unwrap()).is_epsilon() {
-                    let index_item_node_id = semantic_stack.pop().unwrap();
-                    ast.move_node_under_prepend(index_item_node_id, Some(index_list_node_id));
-                }
-                // pop epsilon
-                semantic_stack.pop();
-                semantic_stack.push(index_list_node_id);
-            }
-            ActionSymbol::D => { // var
-                let sub_concept1_id = semantic_stack.pop().unwrap();
-                let sub_concept2_id = semantic_stack.pop().unwrap();
-                let var_concept = Concept::CompositeConcept(CompositeConcept::Var);
-                let var_concept_id = ast.insert_node(None, var_concept);
-                ast.move_node_under_prepend(sub_concept1_id, Some(var_concept_id));
-                ast.move_node_under_prepend(sub_concept2_id, Some(var_concept_id));
-                semantic_stack.push(var_concept_id);
+        let production_start = state
+            .parsing_tree
+            .get_parent(node)
+            .and_then(|parent| state.production_starts.get(&parent))
+            .copied()
+            .unwrap_or(state.token_index);
+        let at = ActionTokens {
+            tokens: &state.tokens,
+            production_start,
+            token_index: state.token_index,
+        };
+        self.semantic_actions
+            .get(action_symbol)
+            .unwrap_or_else(|| panic!("No semantic action declared for {:?}", action_symbol))
+            .perform(
+                &at,
+                &mut state.semantic_stack,
+                &mut state.ast,
+                &mut state.ast_spans,
+                &mut state.ast_anchors,
+                &mut state.outstanding_dot,
+            );
+    }
+
+    /// Recover from a syntax error at the top of the parsing stack. A phrase-level repair
+    /// of the unexpected token is applied if one lets parsing go on for long enough;
+    /// otherwise panic-mode recovery skips tokens or pops the stack.
+    /// An error is only reported if enough tokens were matched since the last one,
+    /// and parsing is aborted after `MAX_SYNTAX_ERRORS` errors.
+    fn skip_error(&self, state: &mut ParseState) {
+        if state.error_count >= MAX_SYNTAX_ERRORS {
+            let token = state.tokens[state.token_index].clone();
+            state.report_error(SyntaxError::new(
+                SyntaxErrType::TooManyErrors,
+                token,
+                vec![],
+                None,
+            ));
+            return;
+        }
+        let report = state.tokens_since_error >= MIN_TOKENS_BETWEEN_ERRORS;
+        state.tokens_since_error = 0;
+
+        let top_node = *state.parsing_stack.last().unwrap();
+        let token = state.tokens[state.token_index].clone();
+        let (expected, nonterminal) = match state.parsing_tree.get_node_value(top_node) {
+            SymbolOrToken::Symbol(Symbol::Terminal(top)) => {
+                // terminal on the stack top: report the nonterminal it was derived from
+                let parent_nonterminal = match state.parsing_tree.get_parent(top_node) {
+                    Some(parent) => match state.parsing_tree.get_node_value(parent) {
+                        SymbolOrToken::Symbol(Symbol::NonTerminal(nonterminal)) => {
+                            Some(nonterminal.clone())
+                        }
+                        _ => None,
+                    },
+                    None => None,
+                };
+                (vec![top.clone()], parent_nonterminal)
             }
-            ActionSymbol::E => { // push epsilon
-                let epsilon = Concept::create_epsilon();
-                let epsilon_node_id = ast.insert_node(None, epsilon);
-                semantic_stack.push(epsilon_node_id);
+            SymbolOrToken::Symbol(Symbol::NonTerminal(top)) => {
+                (self.get_expected_terminals(top), Some(top.clone()))
             }
-            ActionSymbol::F => { // create dot if OSD
-                if *outstanding_dot {
-                    let operand1 = semantic_stack.pop().unwrap();
-                    let operand2 = semantic_stack.pop().unwrap();
-                    let dot_concept = Concept::CompositeConcept(CompositeConcept::Dot);
-                    let dot_node_id = ast.insert_node(None, dot_concept);
-                    ast.move_node_under_prepend(operand1, Some(dot_node_id));
-                    ast.move_node_under_prepend(operand2, Some(dot_node_id));
-                    semantic_stack.push(dot_node_id);
-                    *outstanding_dot = false;
+            _ => panic!("Trying to skip a matched token. Should not reach here!"),
+        };
+
+        // try inserting an expected terminal, replacing the token by one, or deleting it
+        let mut candidates: Vec<Repair> = expected
+            .iter()
+            .filter(|terminal| terminal.has_fixed_spelling())
+            .map(|terminal| Repair::Insert(terminal.clone()))
+            .collect();
+        // prefer replacements spelled like the token, e.g. `==` for `=`
+        let mut replacements: Vec<&Terminal> = expected
+            .iter()
+            .filter(|terminal| terminal.has_fixed_spelling())
+            .collect();
+        replacements.sort_by_key(|terminal| !terminal.spelling().starts_with(&token.lexeme));
+        candidates.extend(
+            replacements
+                .into_iter()
+                .map(|terminal| Repair::Replace(terminal.clone())),
+        );
+        candidates.push(Repair::Delete);
+        let repair = self.find_repair(state, &[], candidates);
+        let error = SyntaxError::new(SyntaxErrType::UnexpectedToken, token, expected, nonterminal)
+            .with_repair(repair.clone());
+        if report {
+            state.report_error(error);
+        } else {
+            state.error_count += 1;
+        }
+        if let Some(repair) = repair {
+            state.apply_repair(&repair);
+            return;
+        }
+
+        match state.parsing_tree.get_node_value(top_node) {
+            SymbolOrToken::Symbol(Symbol::NonTerminal(top)) => {
+                let top = top.clone();
+                let first_set = self.first_set.get(&top).unwrap();
+                let sync_set = self.sync_set.get(&top).unwrap();
+                let mut lookahead = state.lookahead();
+                if self.follow_set.get(&top).unwrap().contains(&lookahead) {
+                    self.pop_unparsed(state);
+                } else {
+                    // EOF is in every sync set, so this never goes past the sentinel
+                    while !first_set.contains(&lookahead) && !sync_set.contains(&lookahead) {
+                        state.token_index += 1;
+                        lookahead = state.lookahead();
+                    }
+                    if !first_set.contains(&lookahead) {
+                        self.pop_unparsed(state);
+                    }
                 }
             }
-            ActionSymbol::G => { // set osd to true
-                *outstanding_dot = true;
-            }
-            ActionSymbol::H => { // assign
-                let sub_concept1_id = semantic_stack.pop().unwrap();
-                let sub_concept2_id = semantic_stack.pop().unwrap();
-                let assign_concept = Concept::CompositeConcept(CompositeConcept::Assign);
-                let assign_concept_id = ast.insert_node(None, assign_concept);
-                ast.move_node_under_prepend(sub_concept1_id, Some(assign_concept_id));
-                ast.move_node_under_prepend(sub_concept2_id, Some(assign_concept_id));
-                semantic_stack.push(assign_concept_id);
-            }
-            ActionSymbol::I => {}
-            ActionSymbol::J => { // funcCall
-                let sub_concept1_id = semantic_stack.pop().unwrap();
-                let sub_concept2_id = semantic_stack.pop().unwrap();
-                let func_call_concept = Concept::CompositeConcept(CompositeConcept::FuncCall);
-                let func_call_concept_id = ast.insert_node(None, func_call_concept);
-                ast.move_node_under_prepend(sub_concept1_id, Some(func_call_concept_id));
-                ast.move_node_under_prepend(sub_concept2_id, Some(func_call_concept_id));
-                semantic_stack.push(func_call_concept_id);
-            }
-            ActionSymbol::L => { // relExpr
-                let sub_concept1_id = semantic_stack.pop().unwrap();
-                let sub_concept2_id = semantic_stack.pop().unwrap();
-                let sub_concept3_id = semantic_stack.pop().unwrap();
-                let expr_id = ast.insert_node(None, Concept::CompositeConcept(CompositeConcept::RelExpr));
-                ast.move_node_under_prepend(sub_concept1_id, Some(expr_id));
-                ast.move_node_under_prepend(sub_concept2_id, Some(expr_id));
-                ast.move_node_under_prepend(sub_concept3_id, Some(expr_id));
-                semantic_stack.push(expr_id);
-            }
-            ActionSymbol::M => { // addExpr
-                let sub_concept1_id = semantic_stack.pop().unwrap();
-                let sub_concept2_id = semantic_stack.pop().unwrap();
-                let sub_concept3_id = semantic_stack.pop().unwrap();
-                let expr_id = ast.insert_node(None, Concept::CompositeConcept(CompositeConcept::AddExpr));
-                ast.move_node_under_prepend(sub_concept1_id, Some(expr_id));
-                ast.move_node_under_prepend(sub_concept2_id, Some(expr_id));
-                ast.move_node_under_prepend(sub_concept3_id, Some(expr_id));
-                semantic_stack.push(expr_id);
-            }
-            ActionSymbol::O => { // multExpr
-                let sub_concept1_id = semantic_stack.pop().unwrap();
-                let sub_concept2_id = semantic_stack.pop().unwrap();
-                let sub_concept3_id = semantic_stack.pop().unwrap();
-                let expr_id = ast.insert_node(None, Concept::CompositeConcept(CompositeConcept::MultExpr));
-                ast.move_node_under_prepend(sub_concept1_id, Some(expr_id));
-                ast.move_node_under_prepend(sub_concept2_id, Some(expr_id));
-                ast.move_node_under_prepend(sub_concept3_id, Some(expr_id));
-                semantic_stack.push(expr_id);
+            _ => {
+                // pretend the expected terminal was there
+                state.parsing_stack.pop();
             }
-            ActionSymbol::Q => { // notExpr
-                let sub_concept_id = semantic_stack.pop().unwrap();
-                let expr_id = ast.insert_node(None, Concept::CompositeConcept(CompositeConcept::NotExpr));
-                ast.move_node_under_prepend(sub_concept_id, Some(expr_id));
-                semantic_stack.push(expr_id);
-            }
-            ActionSymbol::R => { // signed expr
-                let sub_concept1_id = semantic_stack.pop().unwrap();
-                let sub_concept2_id = semantic_stack.pop().unwrap();
-                let signed_expr = Concept::CompositeConcept(CompositeConcept::SignedExpr);
-                let signed_expr_id = ast.insert_node(None, signed_expr);
-                ast.move_node_under_prepend(sub_concept1_id, Some(signed_expr_id));
-                ast.move_node_under_prepend(sub_concept2_id, Some(signed_expr_id));
-                semantic_stack.push(signed_expr_id);
-            }
-            ActionSymbol::S => { // sign
-                let concept = Concept::create_sign(tokens[token_index - 1].clone()).unwrap();
-                let concept_node_id = ast.insert_node(None, concept);
-                semantic_stack.push(concept_node_id);
-            }
-            ActionSymbol::T => { // if then else
-                let sub_concept1_id = semantic_stack.pop().unwrap();
-                let sub_concept2_id = semantic_stack.pop().unwrap();
-                let sub_concept3_id = semantic_stack.pop().unwrap();
-                let expr_id = ast.insert_node(None, Concept::CompositeConcept(CompositeConcept::IfThenElse));
-                ast.move_node_under_prepend(sub_concept1_id, Some(expr_id));
-                ast.move_node_under_prepend(sub_concept2_id, Some(expr_id));
-                ast.move_node_under_prepend(sub_concept3_id, Some(expr_id));
-                semantic_stack.push(expr_id);
-            }
-            ActionSymbol::U => { // read
-                let sub_concept_id = semantic_stack.pop().unwrap();
-                let expr_id = ast.insert_node(None, Concept::CompositeConcept(CompositeConcept::Read));
-                ast.move_node_under_prepend(sub_concept_id, Some(expr_id));
-                semantic_stack.push(expr_id);
-            }
-            ActionSymbol::V => { // return 
-                let sub_concept_id = semantic_stack.pop().unwrap();
-                let expr_id = ast.insert_node(None, Concept::CompositeConcept(CompositeConcept::Return));
-                ast.move_node_under_prepend(sub_concept_id, Some(expr_id));
-                semantic_stack.push(expr_id);
-            }
-            ActionSymbol::W => { // while
-                let sub_concept1_id = semantic_stack.pop().unwrap();
-                let sub_concept2_id = semantic_stack.pop().unwrap();
-                let while_concept = Concept::CompositeConcept(CompositeConcept::While);
-                let while_concept_id = ast.insert_node(None, while_concept);
-                ast.move_node_under_prepend(sub_concept1_id, Some(while_concept_id));
-                ast.move_node_under_prepend(sub_concept2_id, Some(while_concept_id));
-                semantic_stack.push(while_concept_id);
-            }
-            ActionSymbol::X => { // statBlock
-                let stmt_block_concept = Concept::CompositeConcept(CompositeConcept::StmtBlock);
-                let stmt_block_concept_id = ast.insert_node(None, stmt_block_concept);
-                while !ast.get_node_value(*semantic_stack.last().unwrap()).is_epsilon() {
-                    let stmt_item_id = semantic_stack.pop().unwrap();
-                    ast.move_node_under_prepend(stmt_item_id, Some(stmt_block_concept_id));
+        }
+    }
+
+    /// Pop the symbol on the top of the parsing stack without deriving it. The concepts
+    /// a nonterminal would have built are replaced by placeholders, which keep the
+    /// concepts it would have taken as children.
+    fn pop_unparsed(&self, state: &mut ParseState) {
+        let node = state.parsing_stack.pop().unwrap();
+        let nonterminal = match state.parsing_tree.get_node_value(node) {
+            SymbolOrToken::Symbol(Symbol::NonTerminal(nonterminal)) => nonterminal.clone(),
+            _ => return,
+        };
+        let stack_use = match self
+            .stack_uses
+            .get(&(nonterminal.clone(), state.outstanding_dot))
+        {
+            Some(stack_use) => *stack_use,
+            None => return,
+        };
+        let expected = format!("{:?}", nonterminal);
+        let taken: Vec<NodeId> = (0..stack_use.pops)
+            .map(|_| {
+                semantic_action::pop_item(&mut state.semantic_stack, &mut state.ast, 0, &expected)
+            })
+            .collect();
+        for i in 0..stack_use.pushes {
+            let error = if i + 1 == stack_use.pushes {
+                let items: Vec<NodeId> = taken.iter().rev().copied().collect();
+                let error = state
+                    .ast
+                    .insert_node_over(Concept::create_error(&expected), &items);
+                if let Some(span) =
+                    semantic_action::children_span(&state.ast, &state.ast_spans, error)
+                {
+                    state.ast_spans.insert(error, span);
                 }
-                // pop epsilon
-                semantic_stack.pop();
-                semantic_stack.push(stmt_block_concept_id);
-            }
-            ActionSymbol::Y => { // write
-                let sub_concept_id = semantic_stack.pop().unwrap();
-                let write_id = ast.insert_node(None, Concept::CompositeConcept(CompositeConcept::Write));
-                ast.move_node_under_prepend(sub_concept_id, Some(write_id));
-                semantic_stack.push(write_id);
-            }
-            ActionSymbol::Z => { // aParams
-                let a_params_concept = Concept::CompositeConcept(CompositeConcept::AParams);
-                let a_params_concept_id = ast.insert_node(None, a_params_concept);
-                while !ast.get_node_value(*semantic_stack.last().unwrap()).is_epsilon() {
-                    let aparam = semantic_stack.pop().unwrap();
-                    ast.move_node_under_prepend(aparam, Some(a_params_concept_id));
+                error
+            } else {
+                state
+                    .ast
+                    .insert_node(None, Concept::create_error(&expected))
+            };
+            state.semantic_stack.push(error);
+        }
+        state.outstanding_dot = stack_use.outstanding_dot;
+    }
+
+    /// Terminals that have an entry in the parsing table row of the nonterminal
+    fn get_expected_terminals(&self, nonterminal: &NonTerminal) -> Vec<Terminal> {
+        let mut terminals: Vec<Terminal> = self
+            .parsing_table
+            .keys()
+            .filter(|(row, _)| row.eq(nonterminal))
+            .map(|(_, terminal)| terminal.clone())
+            .collect();
+        // repairs are tried in this order, ties are won by the first one
+        terminals.sort();
+        terminals
+    }
+
+    /// Pick the repair of the current token after which the parser goes on the furthest,
+    /// if that is at least `REPAIR_MIN_PROGRESS` tokens. `pending` are symbols to be parsed
+    /// before the ones on the parsing stack. Earlier candidates win ties.
+    fn find_repair(
+        &self,
+        state: &ParseState,
+        pending: &[Symbol],
+        candidates: Vec<Repair>,
+    ) -> Option<Repair> {
+        let mut stack = state.pending_symbols();
+        stack.extend(pending.iter().rev().cloned());
+        let lookaheads: Vec<Terminal> = state.tokens[state.token_index..]
+            .iter()
+            .take(REPAIR_WINDOW)
+            .map(get_terminal)
+            .collect();
+
+        let mut best_repair = None;
+        let mut best_progress = REPAIR_MIN_PROGRESS - 1;
+        for candidate in candidates {
+            let mut repaired = lookaheads.clone();
+            // progress is counted in tokens of the original stream
+            let progress = match &candidate {
+                Repair::Insert(terminal) => {
+                    repaired.insert(0, terminal.clone());
+                    self.simulate(stack.clone(), &repaired).saturating_sub(1)
                 }
-                // pop epsilon
-                semantic_stack.pop();
-                semantic_stack.push(a_params_concept_id);
-            }
-            ActionSymbol::A1 => { // arraySizes
-                let array_sizes_concept = Concept::CompositeConcept(CompositeConcept::ArraySizes);
-                let array_sizes_concept_id = ast.insert_node(None, array_sizes_concept);
-                while !ast.get_node_value(*semantic_stack.last().unwrap()).is_epsilon() {
-                    let array_size_id = semantic_stack.pop().unwrap();
-                    ast.move_node_under_prepend(array_size_id, Some(array_sizes_concept_id));
+                Repair::Replace(terminal) => {
+                    repaired[0] = terminal.clone();
+                    self.simulate(stack.clone(), &repaired)
                 }
-                // pop epsilon
-                semantic_stack.pop();
-                semantic_stack.push(array_sizes_concept_id);
-            }
-            ActionSymbol::A2 => { // fParam
-                let sub_concept1_id = semantic_stack.pop().unwrap();
-                let sub_concept2_id = semantic_stack.pop().unwrap();
-                let sub_concept3_id = semantic_stack.pop().unwrap();
-                let f_param_id = ast.insert_node(None, Concept::CompositeConcept(CompositeConcept::FParam));
-                ast.move_node_under_prepend(sub_concept1_id, Some(f_param_id));
-                ast.move_node_under_prepend(sub_concept2_id, Some(f_param_id));
-                ast.move_node_under_prepend(sub_concept3_id, Some(f_param_id));
-                semantic_stack.push(f_param_id);
-            }
-            ActionSymbol::A3 => { // type
-                let sub_concept_id = semantic_stack.pop().unwrap();
-                let type_id = ast.insert_node(None, Concept::CompositeConcept(CompositeConcept::Type));
-                ast.move_node_under_prepend(sub_concept_id, Some(type_id));
-                semantic_stack.push(type_id);
-            }
-            ActionSymbol::A4 => { // fParams
-                let f_params_concept = Concept::CompositeConcept(CompositeConcept::FParams);
-                let f_params_concept_id = ast.insert_node(None, f_params_concept);
-                while !ast.get_node_value(*semantic_stack.last().unwrap()).is_epsilon() {
-                    let f_param_id = semantic_stack.pop().unwrap();
-                    ast.move_node_under_prepend(f_param_id, Some(f_params_concept_id));
+                Repair::Delete => {
+                    if repaired[0].eq(&Terminal::EOF) {
+                        continue;
+                    }
+                    repaired.remove(0);
+                    self.simulate(stack.clone(), &repaired) + 1
                 }
-                // pop epsilon
-                semantic_stack.pop();
-                semantic_stack.push(f_params_concept_id);
-            }
-            ActionSymbol::A6 => { // funcDef
-                let sub_concept1_id = semantic_stack.pop().unwrap();
-                let sub_concept2_id = semantic_stack.pop().unwrap();
-                let sub_concept3_id = semantic_stack.pop().unwrap();
-                let sub_concept4_id = semantic_stack.pop().unwrap();
-                let func_def_id = ast.insert_node(None, Concept::CompositeConcept(CompositeConcept::FuncDef));
-                ast.move_node_under_prepend(sub_concept1_id, Some(func_def_id));
-                ast.move_node_under_prepend(sub_concept2_id, Some(func_def_id));
-                ast.move_node_under_prepend(sub_concept3_id, Some(func_def_id));
-                ast.move_node_under_prepend(sub_concept4_id, Some(func_def_id));
-                semantic_stack.push(func_def_id);
+            };
+            if progress > best_progress {
+                best_progress = progress;
+                best_repair = Some(candidate);
             }
-            ActionSymbol::A7 => { // varDecl
-                let sub_concept1_id = semantic_stack.pop().unwrap();
-                let sub_concept2_id = semantic_stack.pop().unwrap();
-                let sub_concept3_id = semantic_stack.pop().unwrap();
-                let var_decl_id = ast.insert_node(None, Concept::CompositeConcept(CompositeConcept::VarDecl));
-                ast.move_node_under_prepend(sub_concept1_id, Some(var_decl_id));
-                ast.move_node_under_prepend(sub_concept2_id, Some(var_decl_id));
-                ast.move_node_under_prepend(sub_concept3_id, Some(var_decl_id));
-                semantic_stack.push(var_decl_id);
-            }
-            ActionSymbol::A8 => { // funcBody
-                let func_body_id = ast.insert_node(None, Concept::CompositeConcept(CompositeConcept::FuncBody));
-                while !ast.get_node_value(*semantic_stack.last().unwrap()).is_epsilon() {
-                    let item_id = semantic_stack.pop().unwrap();
-                    ast.move_node_under_prepend(item_id, Some(func_body_id));
+        }
+        best_repair
+    }
+
+    /// Run the parsing table on the stack of symbols without building anything,
+    /// and count the lookaheads matched before the next syntax error
+    fn simulate(&self, mut stack: Vec<Symbol>, lookaheads: &[Terminal]) -> usize {
+        let mut matched = 0;
+        while matched < lookaheads.len() {
+            match stack.pop() {
+                None => {
+                    return if lookaheads[matched].eq(&Terminal::EOF) {
+                        lookaheads.len()
+                    } else {
+                        matched
+                    }
                 }
-                // pop epsilon
-                semantic_stack.pop();
-                semantic_stack.push(func_body_id);
-            }
-            ActionSymbol::A9 => { // funcDecl
-                let sub_concept1_id = semantic_stack.pop().unwrap();
-                let sub_concept2_id = semantic_stack.pop().unwrap();
-                let sub_concept3_id = semantic_stack.pop().unwrap();
-                let func_decl_id = ast.insert_node(None, Concept::CompositeConcept(CompositeConcept::FuncDecl));
-                ast.move_node_under_prepend(sub_concept1_id, Some(func_decl_id));
-                ast.move_node_under_prepend(sub_concept2_id, Some(func_decl_id));
-                ast.move_node_under_prepend(sub_concept3_id, Some(func_decl_id));
-                semantic_stack.push(func_decl_id);
-            }
-            ActionSymbol::B1 => { // funcDefList
-                let func_def_list_id = ast.insert_node(None, Concept::CompositeConcept(CompositeConcept::FuncDefList));
-                while !ast.get_node_value(*semantic_stack.last().unwrap()).is_epsilon() {
-                    let func_def_id = semantic_stack.pop().unwrap();
-                    ast.move_node_under_prepend(func_def_id, Some(func_def_list_id));
+                Some(Symbol::Terminal(terminal)) => {
+                    if terminal.eq(&lookaheads[matched]) {
+                        matched += 1;
+                    } else {
+                        return matched;
+                    }
                 }
-                // pop epsilon
-                semantic_stack.pop();
-                semantic_stack.push(func_def_list_id);
-            }
-            ActionSymbol::B2 => { // implDef
-                let sub_concept1_id = semantic_stack.pop().unwrap();
-                let sub_concept2_id = semantic_stack.pop().unwrap();
-                let impl_def_id = ast.insert_node(None, Concept::CompositeConcept(CompositeConcept::ImplDef));
-                ast.move_node_under_prepend(sub_concept1_id, Some(impl_def_id));
-                ast.move_node_under_prepend(sub_concept2_id, Some(impl_def_id));
-                semantic_stack.push(impl_def_id);
-            }
-            ActionSymbol::B3 => { // structDecl
-                let sub_concept1_id = semantic_stack.pop().unwrap();
-                let sub_concept2_id = semantic_stack.pop().unwrap();
-                let sub_concept3_id = semantic_stack.pop().unwrap();
-                let struct_decl_id = ast.insert_node(None, Concept::CompositeConcept(CompositeConcept::StructDecl));
-                ast.move_node_under_prepend(sub_concept1_id, Some(struct_decl_id));
-                ast.move_node_under_prepend(sub_concept2_id, Some(struct_decl_id));
-                ast.move_node_under_prepend(sub_concept3_id, Some(struct_decl_id));
-                semantic_stack.push(struct_decl_id);
-            }
-            ActionSymbol::B4 => { // inheritsList
-                let inherits_list_id = ast.insert_node(None, Concept::CompositeConcept(CompositeConcept::InheritsList));
-                while !ast.get_node_value(*semantic_stack.last().unwrap()).is_epsilon() {
-                    let inherits_item_id = semantic_stack.pop().unwrap();
-                    ast.move_node_under_prepend(inherits_item_id, Some(inherits_list_id));
+                Some(Symbol::NonTerminal(nonterminal)) => {
+                    match self.get_derivation(&nonterminal, &lookaheads[matched]) {
+                        None => return matched,
+                        Some(derivation) => {
+                            for symbol in derivation.to.iter().rev() {
+                                if !matches!(
+                                    symbol,
+                                    Symbol::Terminal(Terminal::EPSILON) | Symbol::ActionSymbol(_)
+                                ) {
+                                    stack.push(symbol.clone());
+                                }
+                            }
+                        }
+                    }
                 }
-                // pop epsilon
-                semantic_stack.pop();
-                semantic_stack.push(inherits_list_id);
+                Some(Symbol::ActionSymbol(_)) => {}
             }
-            ActionSymbol::B5 => { // structMemberDeclList
-                let struct_mem_decl_list_id = ast.insert_node(None, Concept::CompositeConcept(CompositeConcept::StructMemberDeclList));
-                while !ast.get_node_value(*semantic_stack.last().unwrap()).is_epsilon() {
-                    let struct_mem_decl_id = semantic_stack.pop().unwrap();
-                    ast.move_node_under_prepend(struct_mem_decl_id, Some(struct_mem_decl_list_id));
+        }
+        matched
+    }
+}
+
+/// Mutable state of one run of the parser
+struct ParseState {
+    tokens: Vec<Token>,
+    token_index: usize,
+    parsing_tree: Tree<SymbolOrToken>,
+    parsing_stack: Vec<NodeId>,
+    ast: Tree<Concept>,
+    /// source range of each AST node
+    ast_spans: HashMap<NodeId, Span>,
+    /// tokens covered by the nodes taking a leaf or a list, see `SemanticAction::perform`
+    ast_anchors: HashMap<NodeId, usize>,
+    /// index of the first token of each nonterminal of the parsing tree derived so far
+    production_starts: HashMap<NodeId, usize>,
+    semantic_stack: Vec<NodeId>,
+    outstanding_dot: bool,
+    trace: Option<Trace>,
+    errors: Vec<SyntaxError>,
+    error_count: usize,
+    tokens_since_error: usize,
+    /// expressions being climbed, innermost last
+    expressions: Vec<ExpressionStack>,
+}
+
+/// The part of an expression the climber has not parsed yet, as the parsing stack of the
+/// table-driven loop would hold it
+struct ExpressionStack {
+    /// depth of the parsing stack the expression is parsed at
+    depth: usize,
+    /// bottom first
+    symbols: Vec<Symbol>,
+    /// whether the symbols were derived by the table, or only assumed
+    in_sync: bool,
+}
+
+impl ParseState {
+    /// The terminal of the current token
+    fn lookahead(&self) -> Terminal {
+        get_terminal(&self.tokens[self.token_index])
+    }
+
+    /// Symbols still to be parsed, bottom first, including the rest of the expressions
+    /// being climbed
+    fn pending_symbols(&self) -> Vec<Symbol> {
+        let mut symbols: Vec<Symbol> = Vec::new();
+        for depth in 0..=self.parsing_stack.len() {
+            for expression in self.expressions.iter() {
+                if expression.depth == depth {
+                    symbols.extend(expression.symbols.iter().cloned());
                 }
-                // pop epsilon
-                semantic_stack.pop();
-                semantic_stack.push(struct_mem_decl_list_id);
             }
-            ActionSymbol::B7 => { // structMemberDecl
-                let sub_concept1_id = semantic_stack.pop().unwrap();
-                let sub_concept2_id = semantic_stack.pop().unwrap();
-                let struct_mem_decl_id = ast.insert_node(None, Concept::CompositeConcept(CompositeConcept::StructMemberDecl));
-                ast.move_node_under_prepend(sub_concept1_id, Some(struct_mem_decl_id));
-                ast.move_node_under_prepend(sub_concept2_id, Some(struct_mem_decl_id));
-                semantic_stack.push(struct_mem_decl_id);
-            }
-            ActionSymbol::B8 => { // prog
-                let prog_id = ast.insert_node(None, Concept::CompositeConcept(CompositeConcept::Prog));
-                while !ast.get_node_value(*semantic_stack.last().unwrap()).is_epsilon() {
-                    let item_id = semantic_stack.pop().unwrap();
-                    ast.move_node_under_prepend(item_id, Some(prog_id));
+            if let Some(node) = self.parsing_stack.get(depth) {
+                match self.parsing_tree.get_node_value(*node) {
+                    SymbolOrToken::Symbol(symbol) => symbols.push(symbol.clone()),
+                    SymbolOrToken::Token(_) => panic!("Token appear on the parsing stack"),
                 }
-                // pop epsilon
-                semantic_stack.pop();
-                semantic_stack.push(prog_id);
-            }
-            ActionSymbol::B9 => { //emptyArraySize
-                let concept = Concept::AtomicConcept(AtomicConcept {
-                    atomic_concept_type: AtomicConceptType::EmptyArraySize,
-                    value: "".to_string()
-                });
-                let concept_node_id = ast.insert_node(None, concept);
-                semantic_stack.push(concept_node_id);
             }
         }
+        symbols
     }
 
-    fn skip_error(
-        &self,
-        tokens: &Vec<Token>,
-        token_index: &mut usize,
-        stack: &mut Vec<NodeId>,
-        tree: &Tree<SymbolOrToken>,
-        error_file: &mut File,
-    ) {
-        error_file.write_all(
-            format!(
-                "Syntax error at line {}: unexpected token {}\n",
-                tokens[*token_index].location.0,
-                tokens[*token_index].get_valid_token_type().unwrap()
-            )
-            .as_ref(),
-        );
-        match tree.get_node_value(*stack.last().unwrap()) {
-            SymbolOrToken::Symbol(Symbol::Terminal(top)) => {
-                // terminal on the stack top
-                error_file.write_all(format!("Expect token {}\n", top).as_ref());
-                stack.pop();
-            }
-            SymbolOrToken::Symbol(Symbol::NonTerminal(top)) => {
-                let mut lookahead_token_type = tokens[*token_index].get_valid_token_type().unwrap();
-                if self
-                    .follow_set
-                    .get(top)
-                    .unwrap()
-                    .contains(&Terminal::ValidTokenType(lookahead_token_type))
-                {
-                    error_file.write_all(format!("Expect nonterminal {}\n", top).as_ref());
-                    stack.pop();
-                } else {
-                    while (!self
-                        .first_set
-                        .get(top)
-                        .unwrap()
-                        .contains(&Terminal::ValidTokenType(lookahead_token_type)))
-                        || (self
-                            .first_set
-                            .get(top)
-                            .unwrap()
-                            .contains(&Terminal::EPSILON)
-                            && !self
-                                .follow_set
-                                .get(top)
-                                .unwrap()
-                                .contains(&Terminal::ValidTokenType(lookahead_token_type)))
-                    {
-                        error_file
-                            .write_all(format!("Skip token: {}\n", lookahead_token_type).as_ref());
-                        *token_index += 1;
-                        lookahead_token_type = tokens[*token_index].get_valid_token_type().unwrap();
-                    }
-                }
+    fn trace_derivation(&mut self, derivation: &Derivation) {
+        if self.trace.is_none() {
+            return;
+        }
+        let pending = self.pending_symbols();
+        if let Some(trace) = &mut self.trace {
+            trace.derive(derivation, &self.tokens[self.token_index], &pending);
+        }
+    }
+
+    fn trace_match(&mut self) {
+        if let Some(trace) = &mut self.trace {
+            trace.match_token(&self.tokens[self.token_index]);
+        }
+    }
+
+    /// Apply a repair to the current token. Made-up tokens take its location.
+    fn apply_repair(&mut self, repair: &Repair) {
+        let location = self.tokens[self.token_index].location;
+        let make_token = |terminal: &Terminal| match terminal {
+            Terminal::ValidTokenType(valid_token_type) => Token {
+                token_type: TokenType::ValidTokenType(*valid_token_type),
+                lexeme: valid_token_type.spelling().to_string(),
+                location,
+            },
+            _ => panic!("Cannot make up a token for {}", terminal),
+        };
+        match repair {
+            Repair::Insert(terminal) => self.tokens.insert(self.token_index, make_token(terminal)),
+            Repair::Replace(terminal) => self.tokens[self.token_index] = make_token(terminal),
+            Repair::Delete => {
+                self.tokens.remove(self.token_index);
             }
-            _ => panic!("Trying to skip a matched token. Should not reach here!"),
         }
     }
 
-    fn write_derivation(
-        &self,
-        derivation_file: &mut File,
-        derivation: &Derivation,
-    ) -> IOResult<()> {
-        derivation_file.write_all(format!("{}\n", derivation).as_ref());
-        // println!("{}", derivation);
-        Ok(())
+    /// Parsing gave up after too many errors; only the pending actions are still performed
+    fn is_aborted(&self) -> bool {
+        self.error_count > MAX_SYNTAX_ERRORS
     }
 
-    fn write_match(&self, derivation_file: &mut File, lookahead: &ValidTokenType) -> IOResult<()> {
-        derivation_file.write_all(format!("match {}\n", lookahead).as_ref());
-        // println!("match {}", lookahead);
-        Ok(())
+    fn report_error(&mut self, error: SyntaxError) {
+        self.error_count += 1;
+        self.errors.push(error);
     }
 }
 
-#[derive(Debug)]
-pub struct SyntaxError {}
+/// Drop comments and invalid tokens, which are already reported by the lexer,
+/// and append the EOF sentinel
+pub(crate) fn prepare_tokens(raw_tokens: &[Token]) -> Vec<Token> {
+    let mut tokens: Vec<Token> = raw_tokens
+        .iter()
+        .filter(|token| {
+            !matches!(
+                token.token_type,
+                TokenType::ValidTokenType(ValidTokenType::BlockCmt)
+                    | TokenType::ValidTokenType(ValidTokenType::InlineCmt)
+                    | TokenType::InvalidTokenType(_)
+            )
+        })
+        .cloned()
+        .collect();
+    // EOF sentinel: the token index never goes past it
+    tokens.push(Token {
+        token_type: TokenType::ValidTokenType(ValidTokenType::Eof),
+        lexeme: "".to_string(),
+        location: raw_tokens.last().map_or((1, 0), |token| token.location),
+    });
+    tokens
+}
 
-impl Display for SyntaxError {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{:?}", self)
+/// The terminal of a token. The EOF sentinel maps to `$`
+pub(crate) fn get_terminal(token: &Token) -> Terminal {
+    match token.get_valid_token_type() {
+        Some(ValidTokenType::Eof) | None => Terminal::EOF,
+        Some(valid_token_type) => Terminal::ValidTokenType(valid_token_type),
     }
 }
 
-impl Error for SyntaxError {}
-
 #[derive(PartialEq)]
 pub enum SymbolOrToken {
     Symbol(Symbol),
     Token(Token),
 }
 
-impl SymbolOrToken {
-    fn get_token(&self) -> Token {
-        match self {
-            SymbolOrToken::Symbol(_) => panic!("Called SymbolOrToken::get_token on a Symbol"),
-            SymbolOrToken::Token(token) => token.clone(),
-        }
-    }
-}
-
 impl Display for SymbolOrToken {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(
@@ -696,3 +925,368 @@ impl Display for SymbolOrToken {
         )
     }
 }
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::lexical::lexer::Lexer;
+    use crate::syntactic::trace::TraceFormat;
+    use std::cell::RefCell;
+    use std::io::Write;
+    use std::path::Path;
+    use std::rc::Rc;
+    use std::sync::mpsc;
+    use std::thread;
+    use std::time::Duration;
+
+    /// A parse taking longer than this is taken to loop forever
+    const PARSE_TIMEOUT: Duration = Duration::from_secs(20);
+    const MUTATIONS_PER_SAMPLE: usize = 30;
+
+    pub(crate) fn sample_tokens(dir: &Path, samples: &mut Vec<(String, Vec<Token>)>) {
+        for entry in std::fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                sample_tokens(&path, samples);
+            } else if path.extension().is_some_and(|extension| extension == "src") {
+                let mut lexer = Lexer::new();
+                lexer.read_source(&std::fs::read_to_string(&path).unwrap());
+                samples.push((path.display().to_string(), lexer.get_tokens()));
+            }
+        }
+    }
+
+    /// xorshift, so that a failing input can be found again
+    pub(crate) struct Random(pub(crate) u64);
+
+    impl Random {
+        fn below(&mut self, bound: usize) -> usize {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            (self.0 % bound as u64) as usize
+        }
+    }
+
+    /// Delete, duplicate or swap a few tokens
+    pub(crate) fn mutate(tokens: &[Token], random: &mut Random) -> Vec<Token> {
+        let mut tokens = tokens.to_vec();
+        for _ in 0..1 + random.below(4) {
+            if tokens.is_empty() {
+                break;
+            }
+            let i = random.below(tokens.len());
+            let j = random.below(tokens.len());
+            match random.below(3) {
+                0 => {
+                    tokens.remove(i);
+                }
+                1 => tokens.insert(i, tokens[j].clone()),
+                _ => tokens.swap(i, j),
+            }
+        }
+        tokens
+    }
+
+    #[test]
+    fn recovers_from_truncated_and_mutated_samples() {
+        let mut samples = vec![];
+        sample_tokens(Path::new("resource"), &mut samples);
+        assert!(!samples.is_empty());
+        let mut random = Random(0x2545_f491_4f6c_dd1d);
+        let mut inputs: Vec<(String, Vec<Token>)> = vec![];
+        for (path, tokens) in samples.iter() {
+            for length in 0..tokens.len() {
+                inputs.push((
+                    format!("{} truncated to {} tokens", path, length),
+                    tokens[..length].to_vec(),
+                ));
+            }
+            for i in 0..MUTATIONS_PER_SAMPLE {
+                inputs.push((
+                    format!("{} mutation {}", path, i),
+                    mutate(tokens, &mut random),
+                ));
+            }
+        }
+
+        // the parser runs on its own thread, so that a parse that does not terminate
+        // fails the test instead of hanging it
+        let (input_sender, input_receiver) = mpsc::channel::<Vec<Token>>();
+        let (result_sender, result_receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let mut parser = Parser::new();
+            for tokens in input_receiver {
+                let errors = match parser.parse(tokens) {
+                    Ok((_, _, _, errors)) | Err(errors) => errors,
+                };
+                if result_sender.send(errors).is_err() {
+                    return;
+                }
+            }
+        });
+        for (description, tokens) in inputs {
+            input_sender.send(tokens).unwrap();
+            let errors = match result_receiver.recv_timeout(PARSE_TIMEOUT) {
+                Ok(errors) => errors,
+                Err(mpsc::RecvTimeoutError::Timeout) => {
+                    panic!("parsing {} does not terminate", description)
+                }
+                Err(mpsc::RecvTimeoutError::Disconnected) => {
+                    panic!("parser panicked on {}", description)
+                }
+            };
+            // the error that aborts parsing is the only one past the cap
+            assert!(
+                errors.len() <= MAX_SYNTAX_ERRORS + 1,
+                "{} syntax errors in {}",
+                errors.len(),
+                description
+            );
+        }
+    }
+
+    pub(crate) fn tokens_of(source: &str) -> Vec<Token> {
+        let mut lexer = Lexer::new();
+        lexer.read_source(source);
+        lexer.get_tokens()
+    }
+
+    /// Trace output kept in memory
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Rc<RefCell<Vec<u8>>>);
+
+    impl SharedBuffer {
+        fn take(&self) -> String {
+            String::from_utf8(self.0.borrow_mut().split_off(0)).unwrap()
+        }
+    }
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn traces_the_derivation_of_climbed_expressions() {
+        let source = "func main() -> void {\n  let x: integer;\n  x = 1 + 2 * 3;\n}\n";
+        let buffer = SharedBuffer::default();
+        let mut parser = Parser::new();
+        parser.set_trace(Some(Trace::new(
+            TraceFormat::Productions,
+            Box::new(buffer.clone()),
+        )));
+        parser.parse(tokens_of(source)).unwrap();
+        let productions = buffer.take();
+        let expression: Vec<&str> = productions
+            .lines()
+            .skip_while(|line| *line != "AssignOp -> =")
+            .skip(1)
+            .take_while(|line| !line.starts_with("ReptFuncBody1"))
+            .collect();
+        assert_eq!(
+            expression,
+            [
+                "Expr -> AndExpr RightRecExpr",
+                "AndExpr -> CompareExpr RightRecAndExpr",
+                "CompareExpr -> ArithExpr Expr2",
+                "ArithExpr -> Term RightRecArithExpr",
+                "Term -> Factor RightRecTerm",
+                "Factor -> Integer",
+                "RightRecTerm -> ε",
+                "RightRecArithExpr -> AddOp Term RightRecArithExpr",
+                "AddOp -> +",
+                "Term -> Factor RightRecTerm",
+                "Factor -> Integer",
+                "RightRecTerm -> MultOp Factor RightRecTerm",
+                "MultOp -> *",
+                "Factor -> Integer",
+                "RightRecTerm -> ε",
+                "RightRecArithExpr -> ε",
+                "Expr2 -> ε",
+                "RightRecAndExpr -> ε",
+                "RightRecExpr -> ε",
+            ]
+        );
+    }
+
+    #[test]
+    fn sentential_forms_start_over_on_each_parse() {
+        let source = "func main() -> void {\n  write(1 < 2 & 3 < 4);\n}\n";
+        let buffer = SharedBuffer::default();
+        let mut parser = Parser::new();
+        parser.set_trace(Some(Trace::new(
+            TraceFormat::SententialForms,
+            Box::new(buffer.clone()),
+        )));
+        parser.parse(tokens_of(source)).unwrap();
+        let first = buffer.take();
+        parser.parse(tokens_of(source)).unwrap();
+        assert_eq!(first, buffer.take());
+        assert_eq!(first.lines().next(), Some("Start"));
+        assert_eq!(
+            first.lines().last(),
+            Some("=> func main ( ) -> void { write ( 1 < 2 & 3 < 4 ) ; }")
+        );
+    }
+
+    /// The first expression of the operators of `concept`, with its operations in parentheses
+    fn grouping_of(source: &str, parser: &mut Parser, concept: CompositeConcept) -> String {
+        fn group(ast: &Tree<Concept>, node: NodeId) -> String {
+            match ast.get_node_value(node) {
+                Concept::AtomicConcept(atomic_concept) => atomic_concept.get_value(),
+                Concept::CompositeConcept(_) => format!(
+                    "({})",
+                    ast.children(node)
+                        .map(|child| group(ast, child))
+                        .collect::<Vec<String>>()
+                        .join(" ")
+                ),
+            }
+        }
+        let (_, ast, _, errors) = parser.parse(tokens_of(source)).unwrap();
+        assert!(errors.is_empty(), "{:?}", errors);
+        let expr = ast
+            .pre_order(ast.get_root())
+            .find(|node| {
+                ast.get_node_value(*node)
+                    .eq(&Concept::CompositeConcept(concept.clone()))
+            })
+            .unwrap();
+        group(&ast, expr)
+    }
+
+    #[test]
+    fn groups_operators_of_a_level_to_the_left() {
+        let mut parser = Parser::new();
+        assert_eq!(
+            grouping_of(
+                "func main() -> void {\n  write(1 - 2 - 3);\n}\n",
+                &mut parser,
+                CompositeConcept::AddExpr
+            ),
+            "((1 - 2) - 3)"
+        );
+        assert_eq!(
+            grouping_of(
+                "func main() -> void {\n  write(1 - 2 * 3 + 4);\n}\n",
+                &mut parser,
+                CompositeConcept::AddExpr
+            ),
+            "((1 - (2 * 3)) + 4)"
+        );
+    }
+
+    #[test]
+    fn reports_chained_relational_operators() {
+        let source = "func main() -> void {\n  write(1 < 2 < 3);\n}\n";
+        let errors = match Parser::new().parse(tokens_of(source)) {
+            Ok((_, _, _, errors)) | Err(errors) => errors,
+        };
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        assert_eq!(errors[0].error_type, SyntaxErrType::ChainedOperator);
+        assert_eq!(errors[0].token.lexeme, "<");
+        assert_eq!(errors[0].token.location, (2, 14));
+    }
+
+    #[test]
+    fn parses_operators_the_parsing_table_has_no_column_for() {
+        let source = "func main() -> void {\n  let a: integer;\n  write(a / 2 * a / f(a));\n}\n";
+        let mut parser = Parser::new();
+        let expected = grouping_of(source, &mut parser, CompositeConcept::MultExpr);
+        let div = Terminal::ValidTokenType(ValidTokenType::Div);
+        parser
+            .parsing_table
+            .retain(|(_, terminal), _| !terminal.eq(&div));
+        precedence::complete_parsing_table(&mut parser.parsing_table);
+        assert_eq!(
+            grouping_of(source, &mut parser, CompositeConcept::MultExpr),
+            expected
+        );
+        assert_eq!(expected, "((((a ()) / 2) * (a ())) / (f ((a ()))))");
+    }
+
+    fn errors_of(source: &str) -> Vec<SyntaxError> {
+        match Parser::new().parse(tokens_of(source)) {
+            Ok((_, _, _, errors)) | Err(errors) => errors,
+        }
+    }
+
+    #[test]
+    fn reports_the_token_and_what_the_table_expected() {
+        // a missing `;`
+        let errors = errors_of("func main() -> void {\n  write(1)\n}\n");
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        assert_eq!(errors[0].error_type, SyntaxErrType::UnexpectedToken);
+        assert_eq!(errors[0].token.lexeme, "}");
+        assert_eq!(errors[0].token.location, (3, 0));
+        assert_eq!(
+            errors[0].expected,
+            [Terminal::ValidTokenType(ValidTokenType::Semi)]
+        );
+        assert_eq!(errors[0].nonterminal, Some(NonTerminal::Statement));
+
+        // a missing `)`
+        let errors = errors_of("func main( -> void {\n}\n");
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        assert_eq!(errors[0].token.lexeme, "->");
+        assert_eq!(errors[0].token.location, (1, 11));
+        assert_eq!(
+            errors[0].expected,
+            [
+                Terminal::ValidTokenType(ValidTokenType::Id),
+                Terminal::ValidTokenType(ValidTokenType::ClosePar)
+            ]
+        );
+        assert_eq!(errors[0].nonterminal, Some(NonTerminal::FParams));
+    }
+
+    #[test]
+    fn fails_only_when_no_program_is_derived() {
+        let mut parser = Parser::new();
+        let errors = parser
+            .parse(tokens_of("func main() -> void {\n  write(1)\n}\n"))
+            .unwrap()
+            .3;
+        assert_eq!(errors.len(), 1);
+        assert!(parser.parse(tokens_of("func")).is_ok());
+        let errors = parser.parse(tokens_of("}")).err().unwrap();
+        assert_eq!(errors[0].nonterminal, Some(NonTerminal::Start));
+        assert!(errors[0]
+            .expected
+            .contains(&Terminal::ValidTokenType(ValidTokenType::KwFunc)));
+    }
+
+    #[test]
+    fn suggests_the_smallest_repair() {
+        let repairs = |source: &str| -> Vec<Option<Repair>> {
+            errors_of(source)
+                .into_iter()
+                .map(|error| error.repair)
+                .collect()
+        };
+        assert_eq!(
+            repairs("func main() -> void {\n  let x: integer;\n  x = 1 + 2\n  if (x == 3) then { } else ;\n}\n"),
+            [Some(Repair::Insert(Terminal::ValidTokenType(
+                ValidTokenType::Semi
+            )))]
+        );
+        assert_eq!(
+            repairs("func main() -> void {\n  let x: integer;\n  if (x = 3) then { } else ;\n}\n"),
+            [Some(Repair::Replace(Terminal::ValidTokenType(
+                ValidTokenType::Eq
+            )))]
+        );
+        assert_eq!(
+            repairs("func main() -> void {\n  foo(1, 2;\n}\n"),
+            [Some(Repair::Insert(Terminal::ValidTokenType(
+                ValidTokenType::ClosePar
+            )))]
+        );
+    }
+}
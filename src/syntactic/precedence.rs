@@ -0,0 +1,180 @@
+use crate::lexical::token::ValidTokenType;
+use crate::semantic::concept::{AtomicConceptType, CompositeConcept};
+use crate::syntactic::derivation::Derivation;
+use crate::syntactic::symbol::{NonTerminal, Symbol, Terminal};
+use std::collections::HashMap;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Associativity {
+    Left,
+    /// the operator cannot be chained, e.g. `a < b < c`
+    None,
+}
+
+/// A binary operator known to the expression parser
+pub struct Operator {
+    pub token_type: ValidTokenType,
+    pub precedence: usize,
+    pub associativity: Associativity,
+    /// the composite concept built from `lhs op rhs`
+    pub concept: CompositeConcept,
+    /// the atomic concept type of the operator node
+    pub operator_type: AtomicConceptType,
+}
+
+/// Binary operators of the language. Higher precedence binds tighter.
+/// Adding an operator needs a new entry here and a token from the lexer; the LL(1) parsing
+/// table is completed for it when it is loaded.
+pub const OPERATORS: &[Operator] = &[
+    // logical, evaluated with short circuit
+    Operator {
+        token_type: ValidTokenType::Or,
+        precedence: 1,
+        associativity: Associativity::Left,
+        concept: CompositeConcept::OrExpr,
+        operator_type: AtomicConceptType::LogicOp,
+    },
+    Operator {
+        token_type: ValidTokenType::And,
+        precedence: 2,
+        associativity: Associativity::Left,
+        concept: CompositeConcept::AndExpr,
+        operator_type: AtomicConceptType::LogicOp,
+    },
+    // relational
+    Operator {
+        token_type: ValidTokenType::Eq,
+        precedence: 3,
+        associativity: Associativity::None,
+        concept: CompositeConcept::RelExpr,
+        operator_type: AtomicConceptType::RelOp,
+    },
+    Operator {
+        token_type: ValidTokenType::NotEq,
+        precedence: 3,
+        associativity: Associativity::None,
+        concept: CompositeConcept::RelExpr,
+        operator_type: AtomicConceptType::RelOp,
+    },
+    Operator {
+        token_type: ValidTokenType::Lt,
+        precedence: 3,
+        associativity: Associativity::None,
+        concept: CompositeConcept::RelExpr,
+        operator_type: AtomicConceptType::RelOp,
+    },
+    Operator {
+        token_type: ValidTokenType::Gt,
+        precedence: 3,
+        associativity: Associativity::None,
+        concept: CompositeConcept::RelExpr,
+        operator_type: AtomicConceptType::RelOp,
+    },
+    Operator {
+        token_type: ValidTokenType::Leq,
+        precedence: 3,
+        associativity: Associativity::None,
+        concept: CompositeConcept::RelExpr,
+        operator_type: AtomicConceptType::RelOp,
+    },
+    Operator {
+        token_type: ValidTokenType::Geq,
+        precedence: 3,
+        associativity: Associativity::None,
+        concept: CompositeConcept::RelExpr,
+        operator_type: AtomicConceptType::RelOp,
+    },
+    // additive
+    Operator {
+        token_type: ValidTokenType::Plus,
+        precedence: 4,
+        associativity: Associativity::Left,
+        concept: CompositeConcept::AddExpr,
+        operator_type: AtomicConceptType::AddOp,
+    },
+    Operator {
+        token_type: ValidTokenType::Minus,
+        precedence: 4,
+        associativity: Associativity::Left,
+        concept: CompositeConcept::AddExpr,
+        operator_type: AtomicConceptType::AddOp,
+    },
+    // multiplicative
+    Operator {
+        token_type: ValidTokenType::Mult,
+        precedence: 5,
+        associativity: Associativity::Left,
+        concept: CompositeConcept::MultExpr,
+        operator_type: AtomicConceptType::MultiOp,
+    },
+    Operator {
+        token_type: ValidTokenType::Div,
+        precedence: 5,
+        associativity: Associativity::Left,
+        concept: CompositeConcept::MultExpr,
+        operator_type: AtomicConceptType::MultiOp,
+    },
+];
+
+/// Lowest precedence allowed in an `ARITHEXPR`: logical and relational operators are excluded
+pub const ARITH_MIN_PRECEDENCE: usize = 4;
+
+/// The operator table entry of a token type, if it is a binary operator
+pub fn get_operator(token_type: &ValidTokenType) -> Option<&'static Operator> {
+    OPERATORS
+        .iter()
+        .find(|operator| operator.token_type.eq(token_type))
+}
+
+/// Expression nonterminals parsed by precedence climbing instead of the parsing table,
+/// with the minimum precedence of the operators they accept
+pub fn min_precedence_of(nonterminal: &NonTerminal) -> Option<usize> {
+    match nonterminal {
+        NonTerminal::Expr | NonTerminal::RelExpr => Some(0),
+        NonTerminal::AndExpr => Some(2),
+        NonTerminal::CompareExpr => Some(3),
+        NonTerminal::ArithExpr => Some(ARITH_MIN_PRECEDENCE),
+        _ => None,
+    }
+}
+
+/// Give the operators the parsing table has no column for the entries that every other
+/// operator shares, i.e. the ones ending an operand. The climber parses the operators
+/// themselves, so that a new operator, of an existing precedence level or a new one, does
+/// not need the table to be edited.
+pub fn complete_parsing_table(parsing_table: &mut HashMap<(NonTerminal, Terminal), Derivation>) {
+    let (known, missing): (Vec<Terminal>, Vec<Terminal>) = OPERATORS
+        .iter()
+        .map(|operator| Terminal::ValidTokenType(operator.token_type))
+        .partition(|operator| {
+            parsing_table
+                .keys()
+                .any(|(_, terminal)| terminal.eq(operator))
+        });
+    let Some((first, others)) = known.split_first() else {
+        return;
+    };
+    let shared: Vec<(NonTerminal, Vec<Symbol>)> = parsing_table
+        .iter()
+        .filter(|((nonterminal, terminal), derivation)| {
+            terminal.eq(first)
+                && others.iter().all(|other| {
+                    parsing_table
+                        .get(&(nonterminal.clone(), other.clone()))
+                        .is_some_and(|other_derivation| other_derivation.to == derivation.to)
+                })
+        })
+        .map(|((nonterminal, _), derivation)| (nonterminal.clone(), derivation.to.clone()))
+        .collect();
+    for operator in missing {
+        for (nonterminal, to) in shared.iter() {
+            parsing_table.insert(
+                (nonterminal.clone(), operator.clone()),
+                Derivation {
+                    from: nonterminal.clone(),
+                    to: to.clone(),
+                },
+            );
+        }
+    }
+}
@@ -0,0 +1,558 @@
+use crate::lexical::span::Span;
+use crate::lexical::token::{Token, ValidTokenType};
+use crate::semantic::concept::{AtomicConcept, AtomicConceptType, CompositeConcept, Concept};
+use crate::syntactic::derivation::Derivation;
+use crate::syntactic::symbol::{ActionSymbol, NonTerminal, Symbol, Terminal};
+use crate::syntactic::tree::{NodeId, Tree};
+use std::collections::HashMap;
+use std::str::FromStr;
+
+/// What an action symbol does to the semantic stack, as declared in
+/// `resource/ast/Semantic Actions.csv`
+#[derive(Debug, Clone, PartialEq)]
+pub enum SemanticAction {
+    /// push a leaf made from the token just matched
+    Leaf,
+    /// push a sign leaf made from the `+` or `-` just matched
+    Sign,
+    /// push a leaf of the atomic concept type with no value
+    Atom(AtomicConceptType),
+    /// push the epsilon marker starting a list
+    Epsilon,
+    /// make a node from the top `n` items
+    Node(CompositeConcept, usize),
+    /// make a node from the items above the epsilon marker, and pop the marker
+    List(CompositeConcept),
+    /// like `Node`, but only if a dot is outstanding; it is then closed
+    DotNode(CompositeConcept, usize),
+    /// mark a dot as outstanding
+    OpenDot,
+    None,
+}
+
+impl SemanticAction {
+    /// Parse a row of the declaration file: kind, concept and count
+    pub fn from_cells(kind: &str, concept: &str, count: &str) -> Result<Self, String> {
+        let composite_concept = || {
+            CompositeConcept::from_str(concept)
+                .map_err(|_| format!("Unknown composite concept `{}`", concept))
+        };
+        let count = || {
+            count
+                .parse::<usize>()
+                .map_err(|_| format!("Invalid item count `{}`", count))
+        };
+        match kind {
+            "leaf" => Ok(SemanticAction::Leaf),
+            "sign" => Ok(SemanticAction::Sign),
+            "atom" => AtomicConceptType::from_str(concept)
+                .map(SemanticAction::Atom)
+                .map_err(|_| format!("Unknown atomic concept type `{}`", concept)),
+            "epsilon" => Ok(SemanticAction::Epsilon),
+            "node" => Ok(SemanticAction::Node(composite_concept()?, count()?)),
+            "list" => Ok(SemanticAction::List(composite_concept()?)),
+            "dot node" => Ok(SemanticAction::DotNode(composite_concept()?, count()?)),
+            "open dot" => Ok(SemanticAction::OpenDot),
+            "none" => Ok(SemanticAction::None),
+            other => Err(format!("Unknown semantic action kind `{}`", other)),
+        }
+    }
+
+    /// Perform the action. After a syntax error the stack may be short of items, or the
+    /// last token may not be the one the action expects; an `Error` placeholder then stands
+    /// for the missing concept. The source range of each concept made is recorded in
+    /// `spans`: a leaf covers its token, a node the tokens of the production performing it
+    /// with its items, and a list the tokens from its marker. A concept with no tokens,
+    /// such as an empty list, covers the token matched just before it.
+    pub fn perform(
+        &self,
+        at: &ActionTokens,
+        semantic_stack: &mut Vec<NodeId>,
+        ast: &mut Tree<Concept>,
+        spans: &mut HashMap<NodeId, Span>,
+        anchors: &mut HashMap<NodeId, usize>,
+        outstanding_dot: &mut bool,
+    ) {
+        let last_token = at.last_token();
+        // first token covered, besides the items
+        let start = match self {
+            SemanticAction::Leaf => {
+                let concept = Concept::from_terminal_token(last_token.clone())
+                    .unwrap_or_else(|_| Concept::create_error("leaf"));
+                semantic_stack.push(ast.insert_node(None, concept));
+                None
+            }
+            SemanticAction::Sign => {
+                let concept = Concept::create_sign(last_token.clone())
+                    .unwrap_or_else(|_| Concept::create_error("sign"));
+                semantic_stack.push(ast.insert_node(None, concept));
+                None
+            }
+            SemanticAction::Atom(atomic_concept_type) => {
+                let concept = Concept::AtomicConcept(AtomicConcept {
+                    atomic_concept_type: atomic_concept_type.clone(),
+                    value: "".to_string(),
+                });
+                semantic_stack.push(ast.insert_node(None, concept));
+                None
+            }
+            SemanticAction::Epsilon => {
+                // the marker is not a concept and has no span
+                let marker = ast.insert_node(None, Concept::create_epsilon());
+                semantic_stack.push(marker);
+                anchors.insert(marker, at.token_index);
+                return;
+            }
+            SemanticAction::Node(composite_concept, count) => {
+                Self::make_node(composite_concept, *count, semantic_stack, ast);
+                Some(at.production_start())
+            }
+            SemanticAction::List(composite_concept) => {
+                let marker = semantic_stack
+                    .iter()
+                    .rposition(|item_id| ast.get_node_value(*item_id).is_epsilon());
+                match marker {
+                    Some(marker) => {
+                        let items = semantic_stack.split_off(marker + 1);
+                        let marker = semantic_stack.pop().unwrap();
+                        semantic_stack.push(ast.insert_node_over(
+                            Concept::CompositeConcept(composite_concept.clone()),
+                            &items,
+                        ));
+                        anchors.remove(&marker)
+                    }
+                    // the marker was skipped by error recovery, and the items with it
+                    None => {
+                        semantic_stack.push(ast.insert_node(
+                            None,
+                            Concept::create_error(&format!("{:?}", composite_concept)),
+                        ));
+                        None
+                    }
+                }
+            }
+            SemanticAction::DotNode(composite_concept, count) => {
+                if !*outstanding_dot {
+                    return;
+                }
+                Self::make_node(composite_concept, *count, semantic_stack, ast);
+                *outstanding_dot = false;
+                Some(at.production_start())
+            }
+            SemanticAction::OpenDot => {
+                *outstanding_dot = true;
+                return;
+            }
+            SemanticAction::None => return,
+        };
+        let node_id = *semantic_stack.last().unwrap();
+        // the anchors of the items, such as `let` before the name of a variable, are
+        // covered by a node
+        let item_anchors: Vec<Span> = match self {
+            SemanticAction::Leaf | SemanticAction::Sign | SemanticAction::Atom(_) => {
+                if let Some(anchor) = at.leaf_anchor() {
+                    anchors.insert(node_id, anchor);
+                }
+                vec![]
+            }
+            SemanticAction::Node(..) | SemanticAction::DotNode(..) => ast
+                .get_children(node_id)
+                .iter()
+                .filter_map(|child| anchors.get(child))
+                .map(|anchor| at.tokens[*anchor].get_span())
+                .collect(),
+            _ => vec![],
+        };
+        let span = start
+            .and_then(|start| at.span_from(start))
+            .into_iter()
+            .chain(children_span(ast, spans, node_id))
+            .chain(item_anchors)
+            .reduce(|span, other| span.merge(&other))
+            .unwrap_or_else(|| last_token.get_span());
+        spans.insert(node_id, span);
+    }
+
+    fn make_node(
+        composite_concept: &CompositeConcept,
+        count: usize,
+        semantic_stack: &mut Vec<NodeId>,
+        ast: &mut Tree<Concept>,
+    ) {
+        let expected = format!("{:?}", composite_concept);
+        let mut items: Vec<NodeId> = (0..count)
+            .map(|_| pop_item(semantic_stack, ast, 0, &expected))
+            .collect();
+        items.reverse();
+        let node_id =
+            ast.insert_node_over(Concept::CompositeConcept(composite_concept.clone()), &items);
+        semantic_stack.push(node_id);
+    }
+}
+
+/// Where in the tokens an action is performed: after `tokens[..token_index]` were matched,
+/// in a production whose first token is `tokens[production_start]`
+pub struct ActionTokens<'a> {
+    pub tokens: &'a [Token],
+    pub production_start: usize,
+    pub token_index: usize,
+}
+
+impl ActionTokens<'_> {
+    fn last_token(&self) -> &Token {
+        &self.tokens[self.token_index.saturating_sub(1)]
+    }
+
+    /// The first token of the production, past a comma separating it from the item before
+    fn production_start(&self) -> usize {
+        match self.tokens.get(self.production_start) {
+            Some(token)
+                if self.production_start < self.token_index
+                    && token.get_valid_token_type() == Some(ValidTokenType::Comma) =>
+            {
+                self.production_start + 1
+            }
+            _ => self.production_start,
+        }
+    }
+
+    /// The span of the tokens matched from `start`, `None` if there are none
+    fn span_from(&self, start: usize) -> Option<Span> {
+        (start < self.token_index).then(|| {
+            self.tokens[start]
+                .get_span()
+                .merge(&self.last_token().get_span())
+        })
+    }
+
+    /// For the leaf of the last token, the first token of the production if only keywords
+    /// and punctuation come before it there, as `func` before the name of a function
+    fn leaf_anchor(&self) -> Option<usize> {
+        let leaf = self.token_index.checked_sub(1)?;
+        let start = self.production_start();
+        let only_keywords = self.tokens[start.min(leaf)..leaf].iter().all(|token| {
+            token
+                .get_valid_token_type()
+                .is_some_and(|valid_token_type| {
+                    Terminal::ValidTokenType(valid_token_type).has_fixed_spelling()
+                })
+        });
+        (start < leaf && only_keywords).then_some(start)
+    }
+}
+
+/// The smallest span covering the children of the node that have one
+pub(crate) fn children_span(
+    ast: &Tree<Concept>,
+    spans: &HashMap<NodeId, Span>,
+    node_id: NodeId,
+) -> Option<Span> {
+    ast.get_children(node_id)
+        .iter()
+        .filter_map(|child| spans.get(child))
+        .copied()
+        .reduce(|span, child_span| span.merge(&child_span))
+}
+
+/// Pop an item from the semantic stack, not going below `bottom` nor past a list marker.
+/// An `Error` placeholder mentioning `expected` is returned if there is no such item.
+pub(crate) fn pop_item(
+    semantic_stack: &mut Vec<NodeId>,
+    ast: &mut Tree<Concept>,
+    bottom: usize,
+    expected: &str,
+) -> NodeId {
+    match semantic_stack.last() {
+        Some(item_id)
+            if semantic_stack.len() > bottom && !ast.get_node_value(*item_id).is_epsilon() =>
+        {
+            semantic_stack.pop().unwrap()
+        }
+        _ => ast.insert_node(None, Concept::create_error(expected)),
+    }
+}
+
+/// What deriving a nonterminal does to the semantic stack
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct StackUse {
+    /// items taken from the stack
+    pub pops: usize,
+    /// items left on the stack
+    pub pushes: usize,
+    /// whether a dot is outstanding afterwards
+    pub outstanding_dot: bool,
+}
+
+/// Items taken and left on the stack. `None` if the numbers depend on the input,
+/// as for a list tail.
+type Segment = Option<(usize, usize)>;
+
+/// Effect of deriving a nonterminal on the semantic stack
+#[derive(Debug, Clone, PartialEq)]
+struct StackEffect {
+    /// lists opened before the derivation and closed by it
+    closes: usize,
+    /// items below any marker, then the items after each list marker left open
+    segments: Vec<Segment>,
+    outstanding_dot: bool,
+}
+
+impl StackEffect {
+    fn new(outstanding_dot: bool) -> Self {
+        Self {
+            closes: 0,
+            segments: vec![Some((0, 0))],
+            outstanding_dot,
+        }
+    }
+
+    /// Take `pops` items, then leave `pushes` items
+    fn apply(&mut self, segment: Segment) {
+        let is_base = self.segments.len() == 1;
+        let last = self.segments.last_mut().unwrap();
+        *last = match (*last, segment) {
+            (Some((last_pops, last_pushes)), Some((pops, pushes))) => {
+                if pops <= last_pushes {
+                    Some((last_pops, last_pushes - pops + pushes))
+                } else if is_base {
+                    Some((last_pops + pops - last_pushes, pushes))
+                } else {
+                    // would take the list marker
+                    None
+                }
+            }
+            _ => None,
+        };
+    }
+
+    fn open(&mut self) {
+        self.segments.push(Some((0, 0)));
+    }
+
+    fn close(&mut self) {
+        if self.segments.len() > 1 {
+            self.segments.pop();
+        } else {
+            // everything since the outer marker becomes one list
+            self.closes += 1;
+            self.segments[0] = Some((0, 0));
+        }
+        self.apply(Some((0, 1)));
+    }
+
+    /// Apply another effect after this one
+    fn then(&mut self, other: &StackEffect) {
+        for _ in 0..other.closes {
+            self.close();
+        }
+        self.apply(other.segments[0]);
+        for segment in other.segments.iter().skip(1) {
+            self.open();
+            self.apply(*segment);
+        }
+        self.outstanding_dot = other.outstanding_dot;
+    }
+
+    /// The effect of either derivation, `None` if they differ in more than item counts
+    fn merge(&self, other: &StackEffect) -> Option<StackEffect> {
+        if self.closes != other.closes
+            || self.segments.len() != other.segments.len()
+            || self.outstanding_dot != other.outstanding_dot
+        {
+            return None;
+        }
+        Some(StackEffect {
+            closes: self.closes,
+            segments: self
+                .segments
+                .iter()
+                .zip(other.segments.iter())
+                .map(|(a, b)| match (a, b) {
+                    // taking an item and leaving it is the same as not touching it
+                    (Some((a_pops, a_pushes)), Some((b_pops, b_pushes)))
+                        if *a_pushes as isize - *a_pops as isize
+                            == *b_pushes as isize - *b_pops as isize =>
+                    {
+                        let pops = (*a_pops).max(*b_pops);
+                        Some((pops, pops + a_pushes - a_pops))
+                    }
+                    _ => None,
+                })
+                .collect(),
+            outstanding_dot: self.outstanding_dot,
+        })
+    }
+}
+
+/// How deriving each nonterminal uses the semantic stack, given whether a dot is outstanding
+/// before, for the nonterminals that always use it the same way. Popping such a nonterminal
+/// without deriving it leaves the concepts it would have built missing.
+pub(crate) fn get_stack_uses(
+    parsing_table: &HashMap<(NonTerminal, Terminal), Derivation>,
+    semantic_actions: &HashMap<ActionSymbol, SemanticAction>,
+) -> HashMap<(NonTerminal, bool), StackUse> {
+    // `None` if the effect cannot be described by a `StackEffect`
+    let mut effects: HashMap<(NonTerminal, bool), Option<StackEffect>> = HashMap::new();
+    let mut changed = true;
+    while changed {
+        changed = false;
+        let mut new_effects: HashMap<(NonTerminal, bool), Option<StackEffect>> = HashMap::new();
+        for derivation in parsing_table.values() {
+            for outstanding_dot in [false, true] {
+                let effect = match derivation_effect(
+                    derivation,
+                    outstanding_dot,
+                    &effects,
+                    semantic_actions,
+                ) {
+                    Some(effect) => effect,
+                    // depends on a nonterminal not known yet
+                    None => continue,
+                };
+                let key = (derivation.from.clone(), outstanding_dot);
+                let merged = match new_effects.get(&key) {
+                    Some(known) => merge(known, &effect),
+                    None => effect,
+                };
+                new_effects.insert(key, merged);
+            }
+        }
+        for (key, effect) in new_effects {
+            // merge with the effect of the last round, so effects only get less precise
+            // and this terminates
+            let effect = match effects.get(&key) {
+                Some(known) => merge(known, &effect),
+                None => effect,
+            };
+            if effects.get(&key) != Some(&effect) {
+                effects.insert(key, effect);
+                changed = true;
+            }
+        }
+    }
+    effects
+        .into_iter()
+        .filter_map(|(key, effect)| {
+            let effect = effect?;
+            match effect.segments[..] {
+                [Some((pops, pushes))] if effect.closes == 0 && (pops, pushes) != (0, 0) => {
+                    Some((
+                        key,
+                        StackUse {
+                            pops,
+                            pushes,
+                            outstanding_dot: effect.outstanding_dot,
+                        },
+                    ))
+                }
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+fn merge(a: &Option<StackEffect>, b: &Option<StackEffect>) -> Option<StackEffect> {
+    a.as_ref().zip(b.as_ref()).and_then(|(a, b)| a.merge(b))
+}
+
+/// Effect of deriving the right-hand side, `None` if a nonterminal in it is not known yet
+fn derivation_effect(
+    derivation: &Derivation,
+    outstanding_dot: bool,
+    effects: &HashMap<(NonTerminal, bool), Option<StackEffect>>,
+    semantic_actions: &HashMap<ActionSymbol, SemanticAction>,
+) -> Option<Option<StackEffect>> {
+    let mut effect = StackEffect::new(outstanding_dot);
+    for symbol in derivation.to.iter() {
+        match symbol {
+            Symbol::Terminal(_) => {}
+            Symbol::NonTerminal(nonterminal) => {
+                match effects.get(&(nonterminal.clone(), effect.outstanding_dot))? {
+                    Some(nonterminal_effect) => effect.then(nonterminal_effect),
+                    None => return Some(None),
+                }
+            }
+            Symbol::ActionSymbol(action_symbol) => match &semantic_actions[action_symbol] {
+                SemanticAction::Leaf | SemanticAction::Sign | SemanticAction::Atom(_) => {
+                    effect.apply(Some((0, 1)))
+                }
+                SemanticAction::Epsilon => effect.open(),
+                SemanticAction::Node(_, count) => effect.apply(Some((*count, 1))),
+                SemanticAction::List(_) => effect.close(),
+                SemanticAction::DotNode(_, count) => {
+                    if effect.outstanding_dot {
+                        effect.apply(Some((*count, 1)));
+                        effect.outstanding_dot = false;
+                    }
+                }
+                SemanticAction::OpenDot => effect.outstanding_dot = true,
+                SemanticAction::None => {}
+            },
+        }
+    }
+    Some(Some(effect))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexical::lexer::Lexer;
+
+    fn leaf(ast: &mut Tree<Concept>, value: &str) -> NodeId {
+        ast.insert_node(
+            None,
+            Concept::AtomicConcept(AtomicConcept {
+                atomic_concept_type: AtomicConceptType::Id,
+                value: value.to_string(),
+            }),
+        )
+    }
+
+    fn perform_list(semantic_stack: &mut Vec<NodeId>, ast: &mut Tree<Concept>) {
+        let mut lexer = Lexer::new();
+        lexer.read_source("x");
+        let tokens = lexer.get_tokens();
+        let at = ActionTokens {
+            tokens: &tokens,
+            production_start: 0,
+            token_index: 1,
+        };
+        SemanticAction::List(CompositeConcept::AParams).perform(
+            &at,
+            semantic_stack,
+            ast,
+            &mut HashMap::new(),
+            &mut HashMap::new(),
+            &mut false,
+        );
+    }
+
+    #[test]
+    fn list_takes_the_items_above_its_marker() {
+        let mut ast = Tree::new();
+        let below = leaf(&mut ast, "below");
+        let marker = ast.insert_node(None, Concept::create_epsilon());
+        let first = leaf(&mut ast, "first");
+        let second = leaf(&mut ast, "second");
+        let mut semantic_stack = vec![below, marker, first, second];
+        perform_list(&mut semantic_stack, &mut ast);
+
+        assert_eq!(semantic_stack.len(), 2);
+        assert_eq!(semantic_stack[0], below);
+        assert_eq!(ast.get_children(semantic_stack[1]), vec![first, second]);
+        assert_eq!(ast.get_parent(below), None);
+    }
+
+    #[test]
+    fn list_without_marker_leaves_the_stack_alone() {
+        let mut ast = Tree::new();
+        let below = leaf(&mut ast, "below");
+        let mut semantic_stack = vec![below];
+        perform_list(&mut semantic_stack, &mut ast);
+
+        assert_eq!(semantic_stack.len(), 2);
+        assert_eq!(semantic_stack[0], below);
+        assert_eq!(ast.get_parent(below), None);
+        assert!(ast.get_node_value(semantic_stack[1]).is_error());
+    }
+}
@@ -4,7 +4,7 @@ use crate::syntactic::symbol::Terminal::*;
 use std::fmt::{Display, Formatter};
 use std::str::FromStr;
 
-#[derive(PartialEq, Debug)]
+#[derive(Clone, PartialEq, Debug)]
 pub enum Symbol {
     NonTerminal(NonTerminal),
     Terminal(Terminal),
@@ -21,18 +21,52 @@ impl Display for Symbol {
     }
 }
 
-#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+#[allow(clippy::upper_case_acronyms)]
 pub enum Terminal {
     ValidTokenType(ValidTokenType),
     EPSILON,
     EOF,
+    /// the `error` token of the LALR(1) grammar, standing for the tokens skipped
+    /// when recovering from a syntax error
+    ERROR,
+}
+
+impl Terminal {
+    /// Whether a token of this terminal always has the same lexeme,
+    /// so that it can be made up by error repair
+    pub fn has_fixed_spelling(&self) -> bool {
+        match self {
+            Terminal::ValidTokenType(valid_token_type) => !matches!(
+                valid_token_type,
+                ValidTokenType::Id
+                    | ValidTokenType::Integer
+                    | ValidTokenType::Float
+                    | ValidTokenType::Str
+                    | ValidTokenType::InlineCmt
+                    | ValidTokenType::BlockCmt
+                    | ValidTokenType::Eof
+            ),
+            Terminal::EPSILON | Terminal::EOF | Terminal::ERROR => false,
+        }
+    }
+
+    /// How the terminal is written in the source code
+    pub fn spelling(&self) -> &'static str {
+        match self {
+            Terminal::ValidTokenType(valid_token_type) => valid_token_type.spelling(),
+            Terminal::EPSILON => "nothing",
+            Terminal::EOF => "end of file",
+            Terminal::ERROR => "error",
+        }
+    }
 }
 
 impl Display for Terminal {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
             Terminal::ValidTokenType(valid_token_type) => {
-                write!(f, "{}", valid_token_type.to_string())
+                write!(f, "{}", valid_token_type)
             }
             other => write!(f, "{:?}", other),
         }
@@ -43,18 +77,23 @@ impl Display for Terminal {
 pub enum NonTerminal {
     Start,
     AddOp,
+    AndExpr,
     AParams,
     AParamsTail,
     ArithExpr,
     ArraySize,
     ArraySize2,
     AssignOp,
+    CompareExpr,
+    ConstDecl,
     Expr,
     Expr2,
+    ElseBody,
     Factor,
     Factor2,
     FParams,
     FParamsTail,
+    ForInit,
     FuncBody,
     FuncDecl,
     FuncDef,
@@ -63,8 +102,11 @@ pub enum NonTerminal {
     IdNest2,
     ImplDef,
     Index,
+    Initializer,
     MemberDecl,
     MultOp,
+    OptElse,
+    OptInit,
     OptStructDecl2,
     Prog,
     RelExpr,
@@ -78,6 +120,7 @@ pub enum NonTerminal {
     ReptIdNest1,
     ReptImplDef3,
     ReptIndices0,
+    ReptInitList,
     ReptOptStructDecl22,
     ReptProg0,
     ReptStatBlock1,
@@ -86,7 +129,9 @@ pub enum NonTerminal {
     ReptVariable,
     ReptVarOrFuncCall,
     ReturnType,
+    RightRecAndExpr,
     RightRecArithExpr,
+    RightRecExpr,
     RightRecTerm,
     Sign,
     StatBlock,
@@ -100,6 +145,7 @@ pub enum NonTerminal {
     Type,
     VarDecl,
     VarDeclOrStat,
+    VarDeclTail,
     Variable,
     Variable2,
     VarIdNest,
@@ -168,24 +214,36 @@ impl Symbol {
                 "intlit" => Integer,
                 "equal" => Assign,
                 "or" => Or,
+                "for" => KwFor,
+                "break" => KwBreak,
+                "continue" => KwContinue,
+                "bool" => KwBool,
+                "true" => KwTrue,
+                "false" => KwFalse,
+                "const" => KwConst,
                 bad_string => panic!("Unexpected terminal symbol string {}", bad_string),
             }))
         } else if symbol_string.len() > 2 {
             Symbol::NonTerminal(match symbol_string {
                 "START" => Start,
                 "ADDOP" => AddOp,
+                "ANDEXPR" => AndExpr,
                 "APARAMS" => AParams,
                 "APARAMSTAIL" => AParamsTail,
                 "ARITHEXPR" => ArithExpr,
                 "ARRAYSIZE" => ArraySize,
                 "ARRAYSIZE2" => ArraySize2,
                 "ASSIGNOP" => AssignOp,
+                "COMPAREXPR" => CompareExpr,
+                "CONSTDECL" => ConstDecl,
                 "EXPR" => Expr,
                 "EXPR2" => Expr2,
+                "ELSEBODY" => ElseBody,
                 "FACTOR" => Factor,
                 "FACTOR2" => Factor2,
                 "FPARAMS" => FParams,
                 "FPARAMSTAIL" => FParamsTail,
+                "FORINIT" => ForInit,
                 "FUNCBODY" => FuncBody,
                 "FUNCDECL" => FuncDecl,
                 "FUNCDEF" => FuncDef,
@@ -194,8 +252,11 @@ impl Symbol {
                 "IDNEST2" => IdNest2,
                 "IMPLDEF" => ImplDef,
                 "INDICE" => Index,
+                "INITIALIZER" => Initializer,
                 "MEMBERDECL" => MemberDecl,
                 "MULTOP" => MultOp,
+                "OPTELSE" => OptElse,
+                "OPTINIT" => OptInit,
                 "OPTSTRUCTDECL2" => OptStructDecl2,
                 "PROG" => Prog,
                 "RELEXPR" => RelExpr,
@@ -208,6 +269,7 @@ impl Symbol {
                 "REPTIDNEST1" => ReptIdNest1,
                 "REPTIMPLDEF3" => ReptImplDef3,
                 "REPTINDICES0" => ReptIndices0,
+                "REPTINITLIST" => ReptInitList,
                 "REPTOPTSTRUCTDECL22" => ReptOptStructDecl22,
                 "REPTPROG0" => ReptProg0,
                 "REPTSTATBLOCK1" => ReptStatBlock1,
@@ -216,7 +278,9 @@ impl Symbol {
                 "REPTVARIABLE" => ReptVariable,
                 "REPTVARORFUNCCALL" => ReptVarOrFuncCall,
                 "RETURNTYPE" => ReturnType,
+                "RIGHTRECANDEXPR" => RightRecAndExpr,
                 "RIGHTRECARITHEXPR" => RightRecArithExpr,
+                "RIGHTRECEXPR" => RightRecExpr,
                 "RIGHTRECTERM" => RightRecTerm,
                 "SIGN" => Sign,
                 "STATBLOCK" => StatBlock,
@@ -230,6 +294,7 @@ impl Symbol {
                 "TYPE" => Type,
                 "VARDECL" => VarDecl,
                 "VARDECLORSTAT" => VarDeclOrStat,
+                "VARDECLTAIL" => VarDeclTail,
                 "VARIABLE" => Variable,
                 "VARIABLE2" => Variable2,
                 "VARIDNEST" => VarIdNest,
@@ -291,6 +356,16 @@ pub enum ActionSymbol {
     B7,
     B8,
     B9,
+    C1,
+    C2,
+    C3,
+    C4,
+    C5,
+    C6,
+    C7,
+    C8,
+    C9,
+    D1,
 }
 
 impl FromStr for ActionSymbol {
@@ -342,6 +417,16 @@ impl FromStr for ActionSymbol {
             "B7" => Ok(ActionSymbol::B7),
             "B8" => Ok(ActionSymbol::B8),
             "B9" => Ok(ActionSymbol::B9),
+            "C1" => Ok(ActionSymbol::C1),
+            "C2" => Ok(ActionSymbol::C2),
+            "C3" => Ok(ActionSymbol::C3),
+            "C4" => Ok(ActionSymbol::C4),
+            "C5" => Ok(ActionSymbol::C5),
+            "C6" => Ok(ActionSymbol::C6),
+            "C7" => Ok(ActionSymbol::C7),
+            "C8" => Ok(ActionSymbol::C8),
+            "C9" => Ok(ActionSymbol::C9),
+            "D1" => Ok(ActionSymbol::D1),
             _ => Err(()),
         }
     }
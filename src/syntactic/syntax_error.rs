@@ -0,0 +1,136 @@
+use crate::lexical::token::{Token, TokenType, ValidTokenType};
+use crate::syntactic::symbol::{NonTerminal, Terminal};
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::io::Write;
+
+#[derive(Debug, Clone)]
+pub struct SyntaxError {
+    pub(crate) error_type: SyntaxErrType,
+    /// the offending token. At the end of input this is the EOF sentinel
+    pub(crate) token: Token,
+    /// terminals that would have been accepted instead
+    pub(crate) expected: Vec<Terminal>,
+    /// the nonterminal being parsed when the error occurred
+    pub(crate) nonterminal: Option<NonTerminal>,
+    /// the smallest change to the token that lets parsing go on, if any
+    pub(crate) repair: Option<Repair>,
+}
+
+impl SyntaxError {
+    pub fn new(
+        error_type: SyntaxErrType,
+        token: Token,
+        mut expected: Vec<Terminal>,
+        nonterminal: Option<NonTerminal>,
+    ) -> Self {
+        expected.sort();
+        expected.dedup();
+        Self {
+            error_type,
+            token,
+            expected,
+            nonterminal,
+            repair: None,
+        }
+    }
+
+    pub fn with_repair(mut self, repair: Option<Repair>) -> Self {
+        self.repair = repair;
+        self
+    }
+}
+
+impl Display for SyntaxError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Syntax error at line {}, column {}: {} {}",
+            self.token.location.0,
+            self.token.location.1,
+            match self.error_type {
+                SyntaxErrType::UnexpectedToken => "unexpected token",
+                SyntaxErrType::ChainedOperator => "operator cannot be chained without parentheses",
+                SyntaxErrType::ExpectRelExpr => "expect a relational expression before",
+                SyntaxErrType::TrailingToken => "unexpected token after the end of program",
+                SyntaxErrType::TooManyErrors => "too many errors, parsing aborted at",
+            },
+            match self.token.get_valid_token_type() {
+                Some(ValidTokenType::Eof) => ValidTokenType::Eof.spelling().to_string(),
+                _ => format!("`{}`", self.token.lexeme),
+            }
+        )?;
+        if let Some(nonterminal) = &self.nonterminal {
+            write!(f, " while parsing {}", nonterminal)?;
+        }
+        match self.expected.len() {
+            0 => {}
+            1 => write!(f, ", expected `{}`", self.expected[0].spelling())?,
+            _ => write!(
+                f,
+                ", expected one of {}",
+                self.expected
+                    .iter()
+                    .map(|terminal| format!("`{}`", terminal.spelling()))
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            )?,
+        }
+        match &self.repair {
+            None => Ok(()),
+            Some(Repair::Insert(terminal)) => {
+                write!(f, "; help: insert `{}` here", terminal.spelling())
+            }
+            Some(Repair::Delete) => write!(f, "; help: remove `{}`", self.token.lexeme),
+            Some(Repair::Replace(terminal)) => write!(
+                f,
+                "; help: replace `{}` with `{}`",
+                self.token.lexeme,
+                terminal.spelling()
+            ),
+        }
+    }
+}
+
+impl Error for SyntaxError {}
+
+/// Write the invalid tokens skipped by the parser, then the syntax errors, one per line
+pub fn write_syntax_errors(
+    out: &mut impl Write,
+    raw_tokens: &[Token],
+    errors: &[SyntaxError],
+) -> std::io::Result<()> {
+    for token in raw_tokens.iter() {
+        if let TokenType::InvalidTokenType(_) = token.token_type {
+            writeln!(
+                out,
+                "Syntax error at line {}: skip invalid token {}",
+                token.location.0, token.lexeme
+            )?;
+        }
+    }
+    for error in errors.iter() {
+        writeln!(out, "{}", error)?;
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SyntaxErrType {
+    UnexpectedToken,
+    ChainedOperator,
+    ExpectRelExpr,
+    TrailingToken,
+    TooManyErrors,
+}
+
+/// A fix-it applied to the token stream at the error
+#[derive(Debug, Clone, PartialEq)]
+pub enum Repair {
+    /// insert the terminal before the unexpected token
+    Insert(Terminal),
+    /// remove the unexpected token
+    Delete,
+    /// use the terminal in place of the unexpected token
+    Replace(Terminal),
+}
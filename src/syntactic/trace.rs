@@ -0,0 +1,144 @@
+use crate::lexical::token::Token;
+use crate::syntactic::derivation::Derivation;
+use crate::syntactic::symbol::{Symbol, Terminal};
+use crate::syntactic::util::json_string;
+use std::io::Write;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TraceFormat {
+    /// one leftmost sentential form per derivation step, e.g. `=> func main ( FParams ) ...`
+    SententialForms,
+    /// one production per line, in the order they are applied
+    Productions,
+    /// one JSON object per line for every derivation and match
+    Json,
+}
+
+impl FromStr for TraceFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sentential" => Ok(TraceFormat::SententialForms),
+            "productions" => Ok(TraceFormat::Productions),
+            "json" => Ok(TraceFormat::Json),
+            other => Err(format!(
+                "unknown trace format `{}`, expected one of `sentential`, `productions`, `json`",
+                other
+            )),
+        }
+    }
+}
+
+/// Derivation trace of one run of the parser.
+/// The parser only builds trace output when a `Trace` is given to it.
+pub struct Trace {
+    format: TraceFormat,
+    out: Box<dyn Write>,
+    /// lexemes matched so far, the prefix of every sentential form
+    matched: Vec<String>,
+    started: bool,
+}
+
+impl Trace {
+    pub fn new(format: TraceFormat, out: Box<dyn Write>) -> Self {
+        Self {
+            format,
+            out,
+            matched: Vec::new(),
+            started: false,
+        }
+    }
+
+    /// Forget the previous run of the parser. The output goes on in the same writer.
+    pub(crate) fn restart(&mut self) {
+        self.matched.clear();
+        self.started = false;
+    }
+
+    /// Record a derivation applied at `token`. `pending` are the symbols left on the
+    /// parsing stack below the derived nonterminal, bottom first.
+    pub(crate) fn derive(&mut self, derivation: &Derivation, token: &Token, pending: &[Symbol]) {
+        let line = match self.format {
+            TraceFormat::SententialForms => {
+                let mut line = String::new();
+                if !self.started {
+                    line += &format!("{:?}\n", derivation.from);
+                }
+                let form: Vec<String> = self
+                    .matched
+                    .iter()
+                    .cloned()
+                    .chain(derivation.to.iter().filter_map(symbol_name))
+                    .chain(pending.iter().rev().filter_map(symbol_name))
+                    .collect();
+                line + "=> " + &form.join(" ")
+            }
+            TraceFormat::Productions => {
+                let rhs: Vec<String> = derivation.to.iter().filter_map(symbol_name).collect();
+                format!(
+                    "{:?} -> {}",
+                    derivation.from,
+                    if rhs.is_empty() {
+                        "ε".to_string()
+                    } else {
+                        rhs.join(" ")
+                    }
+                )
+            }
+            TraceFormat::Json => {
+                let rhs: Vec<String> = derivation
+                    .to
+                    .iter()
+                    .filter_map(symbol_name)
+                    .map(|name| json_string(&name))
+                    .collect();
+                format!(
+                    "{{\"event\":\"derive\",\"lhs\":{},\"rhs\":[{}],\"line\":{},\"column\":{}}}",
+                    json_string(&format!("{:?}", derivation.from)),
+                    rhs.join(","),
+                    token.location.0,
+                    token.location.1
+                )
+            }
+        };
+        self.started = true;
+        self.write_line(&line);
+    }
+
+    /// Record a token matched against a terminal
+    pub(crate) fn match_token(&mut self, token: &Token) {
+        self.matched.push(token.lexeme.clone());
+        if let TraceFormat::Json = self.format {
+            let line = format!(
+                "{{\"event\":\"match\",\"terminal\":{},\"lexeme\":{},\"line\":{},\"column\":{}}}",
+                json_string(&token.token_type.to_string()),
+                json_string(&token.lexeme),
+                token.location.0,
+                token.location.1
+            );
+            self.write_line(&line);
+        }
+    }
+
+    pub(crate) fn flush(&mut self) {
+        let _ = self.out.flush();
+    }
+
+    fn write_line(&mut self, line: &str) {
+        let _ = self.out.write_all(format!("{}\n", line).as_ref());
+    }
+}
+
+/// Name of a grammar symbol in trace output. Action symbols and epsilon are left out.
+pub(crate) fn symbol_name(symbol: &Symbol) -> Option<String> {
+    match symbol {
+        Symbol::NonTerminal(nonterminal) => Some(format!("{:?}", nonterminal)),
+        Symbol::Terminal(Terminal::EPSILON) | Symbol::ActionSymbol(_) => None,
+        Symbol::Terminal(terminal) if terminal.has_fixed_spelling() => {
+            Some(terminal.spelling().to_string())
+        }
+        Symbol::Terminal(terminal) => Some(terminal.to_string()),
+    }
+}
@@ -1,3 +1,4 @@
+use std::collections::{HashSet, VecDeque};
 use std::fmt::{Display, Formatter};
 
 struct Node<T>
@@ -7,6 +8,8 @@ where
     value: T,
     parent: Option<NodeId>,
     children: Vec<NodeId>,
+    /// the node was dropped with `remove_subtree`; its id is not reused
+    removed: bool,
 }
 
 impl<T> Node<T>
@@ -18,6 +21,7 @@ where
             value,
             parent: None,
             children: vec![],
+            removed: false,
         }
     }
 
@@ -40,6 +44,34 @@ where
     T: PartialEq + Display,
 {
     arena: Arena<T>,
+    root: Option<NodeId>,
+}
+
+/// A change that would not leave a tree
+#[derive(Debug, PartialEq)]
+pub enum TreeError {
+    /// the new parent is the node itself or one of its descendants
+    Cycle,
+    /// a node is put in its own place
+    SameNode,
+}
+
+impl Display for TreeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TreeError::Cycle => write!(f, "a node cannot be moved under its own subtree"),
+            TreeError::SameNode => write!(f, "a node cannot replace itself"),
+        }
+    }
+}
+
+impl<T> Default for Tree<T>
+where
+    T: PartialEq + Display,
+{
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl<T> Tree<T>
@@ -49,72 +81,300 @@ where
     pub fn new() -> Self {
         Self {
             arena: Arena::new(),
+            root: None,
         }
     }
 
+    /// Insert a node as the last child of parent. The first node inserted without a
+    /// parent becomes the root.
     pub fn insert_node(&mut self, parent: Option<NodeId>, value: T) -> NodeId {
-        self.arena.nodes.push(Node {
-            value,
-            parent,
-            children: vec![],
-        });
-        self.arena.nodes.len() - 1
+        let mut node = Node::new(value);
+        node.parent = parent;
+        self.arena.nodes.push(node);
+        let node_id = self.arena.nodes.len() - 1;
+        self.arena.size += 1;
+        match parent {
+            Some(parent_id) => self.arena.nodes[parent_id].children.push(node_id),
+            None => {
+                self.root.get_or_insert(node_id);
+            }
+        }
+        node_id
+    }
+
+    /// Insert a node above the given nodes, which are detached from their parents to
+    /// become its children, in order. It is how a tree is built from the leaves up.
+    pub fn insert_node_over(&mut self, value: T, children: &[NodeId]) -> NodeId {
+        let node_id = self.insert_node(None, value);
+        for child in children {
+            self.detach(*child);
+            self.arena.nodes[*child].parent = Some(node_id);
+            self.arena.nodes[node_id].children.push(*child);
+            if self.root == Some(*child) {
+                self.root = Some(node_id);
+            }
+        }
+        node_id
     }
 
     /// move a node child under another node parent.
     /// Child is prepended to the children list of parent
-    pub fn move_node_under_prepend(&mut self, child: NodeId, parent: Option<NodeId>) {
-        // remove from old parent
-        if let Some(old_parent_id) = self.arena.nodes[child as usize].parent {
-            let pos = self.arena.nodes[old_parent_id]
-                .children
-                .iter()
-                .position(|id| *id == old_parent_id)
-                .unwrap();
-            self.arena.nodes[old_parent_id].children.remove(pos);
-        }
-        // add pointer from child to parent
-        self.arena.nodes[child as usize].parent = parent;
-        // add pointer from parent to child
-        if parent.is_some() {
-            self.arena.nodes[parent.unwrap() as usize]
-                .children
-                .insert(0, child)
+    pub fn move_node_under_prepend(
+        &mut self,
+        child: NodeId,
+        parent: Option<NodeId>,
+    ) -> Result<(), TreeError> {
+        match parent {
+            Some(parent_id) => self.insert_child_at(parent_id, 0, child),
+            None => {
+                self.detach(child);
+                Ok(())
+            }
         }
     }
 
+    /// Make `child` the child of `parent` at `index`, shifting the later children.
+    /// The child is first detached from its current parent. The parent cannot be in
+    /// the subtree of the child. If the child was the root, the root of its new tree
+    /// takes its place.
+    pub fn insert_child_at(
+        &mut self,
+        parent: NodeId,
+        index: usize,
+        child: NodeId,
+    ) -> Result<(), TreeError> {
+        if parent == child || self.ancestors(parent).any(|id| id == child) {
+            return Err(TreeError::Cycle);
+        }
+        self.detach(child);
+        self.arena.nodes[child].parent = Some(parent);
+        self.arena.nodes[parent].children.insert(index, child);
+        if self.root == Some(child) {
+            self.root = Some(self.ancestors(parent).last().unwrap_or(parent));
+        }
+        Ok(())
+    }
+
+    /// Take a node out of the children of its parent. It stays in the arena as the root
+    /// of its own subtree, which can be moved back with `insert_child_at`.
+    pub fn detach(&mut self, node_id: NodeId) {
+        if let Some(parent_id) = self.arena.nodes[node_id].parent.take() {
+            let children = &mut self.arena.nodes[parent_id].children;
+            let pos = children.iter().position(|id| *id == node_id).unwrap();
+            children.remove(pos);
+        }
+    }
+
+    /// Detach a node and drop it with all its descendants. The tree has no root
+    /// anymore if it was the root.
+    pub fn remove_subtree(&mut self, node_id: NodeId) {
+        self.detach(node_id);
+        if self.root == Some(node_id) {
+            self.root = None;
+        }
+        let mut stack = vec![node_id];
+        while let Some(id) = stack.pop() {
+            if !self.arena.nodes[id].removed {
+                self.arena.nodes[id].removed = true;
+                self.arena.size -= 1;
+            }
+            stack.extend(self.arena.nodes[id].children.iter());
+        }
+    }
+
+    /// Put the subtree of `new` in the place of `old`, which is detached and kept in the
+    /// arena so that it can be reused, as a child of `new` for instance. `new` becomes
+    /// the root if `old` was the root. `new` cannot be `old` or one of its ancestors.
+    pub fn replace_node(&mut self, old: NodeId, new: NodeId) -> Result<(), TreeError> {
+        if new == old {
+            return Err(TreeError::SameNode);
+        }
+        if self.ancestors(old).any(|id| id == new) {
+            return Err(TreeError::Cycle);
+        }
+        self.detach(new);
+        if self.root == Some(old) {
+            self.root = Some(new);
+        }
+        if let Some(parent_id) = self.arena.nodes[old].parent.take() {
+            let children = &mut self.arena.nodes[parent_id].children;
+            let pos = children.iter().position(|id| *id == old).unwrap();
+            children[pos] = new;
+            self.arena.nodes[new].parent = Some(parent_id);
+        }
+        Ok(())
+    }
+
     pub fn get_node_value(&self, node_id: NodeId) -> &T {
-        self.arena.nodes[node_id as usize].get_value()
+        self.arena.nodes[node_id].get_value()
     }
 
+    /// Number of nodes, not counting the removed ones
     pub fn size(&self) -> usize {
-        self.arena.nodes.len()
+        self.arena.size
     }
 
+    /// The root of the tree. Panics if the tree has none, as when it is empty.
     pub fn get_root(&self) -> NodeId {
-        let mut id = self.arena.nodes.len() - 1;
-        while self.arena.nodes[id].parent.is_some() {
-            id = self.arena.nodes[id].parent.unwrap();
-        }
-        id as NodeId
+        self.root.expect("the tree has no root")
+    }
+
+    pub fn has_root(&self) -> bool {
+        self.root.is_some()
+    }
+
+    /// Make a node the root, such as the program node once the AST is built, as nodes
+    /// made from the leaves up are not under it at first
+    pub fn set_root(&mut self, node_id: NodeId) {
+        self.root = Some(node_id);
+    }
+
+    pub fn get_parent(&self, node_id: NodeId) -> Option<NodeId> {
+        self.arena.nodes[node_id].parent
     }
 
     pub fn get_children(&self, parent_id: NodeId) -> Vec<NodeId> {
         self.arena.nodes[parent_id].children.clone()
     }
 
+    /// Position of the node among the children of its parent
+    pub fn get_child_index(&self, node_id: NodeId) -> Option<usize> {
+        let parent_id = self.arena.nodes[node_id].parent?;
+        self.arena.nodes[parent_id]
+            .children
+            .iter()
+            .position(|id| *id == node_id)
+    }
+
+    /// The other children of the parent of the node, in order
+    pub fn get_siblings(&self, node_id: NodeId) -> Vec<NodeId> {
+        match self.arena.nodes[node_id].parent {
+            Some(parent_id) => self.arena.nodes[parent_id]
+                .children
+                .iter()
+                .copied()
+                .filter(|id| *id != node_id)
+                .collect(),
+            None => vec![],
+        }
+    }
+
+    pub fn get_prev_sibling(&self, node_id: NodeId) -> Option<NodeId> {
+        let index = self.get_child_index(node_id)?;
+        let parent_id = self.arena.nodes[node_id].parent?;
+        index
+            .checked_sub(1)
+            .map(|prev| self.arena.nodes[parent_id].children[prev])
+    }
+
+    pub fn get_next_sibling(&self, node_id: NodeId) -> Option<NodeId> {
+        let index = self.get_child_index(node_id)?;
+        let parent_id = self.arena.nodes[node_id].parent?;
+        self.arena.nodes[parent_id].children.get(index + 1).copied()
+    }
+
+    /// The children of the node, without copying them
+    pub fn children(&self, node_id: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+        self.arena.nodes[node_id].children.iter().copied()
+    }
+
+    /// The node and its descendants, each node before its children
+    pub fn pre_order(&self, from: NodeId) -> PreOrder<'_, T> {
+        PreOrder {
+            tree: self,
+            stack: vec![from],
+        }
+    }
+
+    /// The node and its descendants, each node after its children
+    pub fn post_order(&self, from: NodeId) -> PostOrder<'_, T> {
+        PostOrder {
+            tree: self,
+            stack: vec![(from, 0)],
+        }
+    }
+
+    /// The node and its descendants, level by level from the node
+    pub fn level_order(&self, from: NodeId) -> LevelOrder<'_, T> {
+        LevelOrder {
+            tree: self,
+            queue: VecDeque::from([from]),
+        }
+    }
+
+    /// The parent of the node, its parent, and so on up to the root
+    pub fn ancestors(&self, node_id: NodeId) -> Ancestors<'_, T> {
+        Ancestors {
+            tree: self,
+            current: self.arena.nodes[node_id].parent,
+        }
+    }
+
+    /// The nodes under the node, in pre-order
+    pub fn descendants(&self, node_id: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+        self.pre_order(node_id).skip(1)
+    }
+
+    /// Number of edges from the root of its tree to the node
+    pub fn depth(&self, node_id: NodeId) -> usize {
+        self.ancestors(node_id).count()
+    }
+
+    /// The nodes of the subtree whose value satisfies the predicate, in pre-order
+    pub fn find_all<P>(&self, from: NodeId, predicate: P) -> Vec<NodeId>
+    where
+        P: Fn(&T) -> bool,
+    {
+        self.pre_order(from)
+            .filter(|id| predicate(self.get_node_value(*id)))
+            .collect()
+    }
+
+    /// Graphviz DOT form of the subtree of `from`, each node labelled by `label`. The
+    /// `highlighted` nodes, those with a diagnostic for instance, are filled in red.
+    pub fn to_dot<F>(&self, from: NodeId, label: F, highlighted: &HashSet<NodeId>) -> String
+    where
+        F: Fn(&T) -> String,
+    {
+        let mut result = String::from("digraph tree {\n    node [shape=box];\n");
+        for node_id in self.pre_order(from) {
+            let text = escape_dot(&label(self.get_node_value(node_id)));
+            let style = if highlighted.contains(&node_id) {
+                ", style=filled, fillcolor=\"#ff9999\""
+            } else {
+                ""
+            };
+            result.push_str(&format!(
+                "    n{} [label=\"{}\"{}];\n",
+                node_id, text, style
+            ));
+            for child in self.children(node_id) {
+                result.push_str(&format!("    n{} -> n{};\n", node_id, child));
+            }
+        }
+        result + "}\n"
+    }
+
     fn to_string_from_node(&self, from: &NodeId, depth: usize) -> String {
         let mut result = String::from("");
-        result.push_str(&*"| ".repeat(depth));
-        result.push_str(&self.arena.nodes[*from as usize].to_string());
-        result.push_str(&*"\n");
-        for child in self.arena.nodes[*from as usize].children.iter() {
-            result.push_str(&*self.to_string_from_node(child, depth + 1))
+        result.push_str(&"| ".repeat(depth));
+        result.push_str(&self.arena.nodes[*from].to_string());
+        result.push('\n');
+        for child in self.arena.nodes[*from].children.iter() {
+            result.push_str(&self.to_string_from_node(child, depth + 1))
         }
         result
     }
 }
 
+/// Escape a label to be put between the double quotes of a DOT attribute
+pub fn escape_dot(label: &str) -> String {
+    label
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
 impl<T> Display for Tree<T>
 where
     T: PartialEq + Display,
@@ -124,11 +384,112 @@ where
     }
 }
 
+pub struct PreOrder<'a, T>
+where
+    T: PartialEq + Display,
+{
+    tree: &'a Tree<T>,
+    stack: Vec<NodeId>,
+}
+
+impl<'a, T> Iterator for PreOrder<'a, T>
+where
+    T: PartialEq + Display,
+{
+    type Item = NodeId;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node_id = self.stack.pop()?;
+        // pushed in reverse so that the first child comes out first
+        self.stack
+            .extend(self.tree.arena.nodes[node_id].children.iter().rev());
+        Some(node_id)
+    }
+}
+
+pub struct PostOrder<'a, T>
+where
+    T: PartialEq + Display,
+{
+    tree: &'a Tree<T>,
+    /// nodes on the path from the start, with the index of their next child to visit
+    stack: Vec<(NodeId, usize)>,
+}
+
+impl<'a, T> Iterator for PostOrder<'a, T>
+where
+    T: PartialEq + Display,
+{
+    type Item = NodeId;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (node_id, next_child) = self.stack.last_mut()?;
+            let node_id = *node_id;
+            match self.tree.arena.nodes[node_id].children.get(*next_child) {
+                Some(child) => {
+                    *next_child += 1;
+                    self.stack.push((*child, 0));
+                }
+                None => {
+                    self.stack.pop();
+                    return Some(node_id);
+                }
+            }
+        }
+    }
+}
+
+pub struct LevelOrder<'a, T>
+where
+    T: PartialEq + Display,
+{
+    tree: &'a Tree<T>,
+    queue: VecDeque<NodeId>,
+}
+
+impl<'a, T> Iterator for LevelOrder<'a, T>
+where
+    T: PartialEq + Display,
+{
+    type Item = NodeId;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node_id = self.queue.pop_front()?;
+        self.queue
+            .extend(self.tree.arena.nodes[node_id].children.iter());
+        Some(node_id)
+    }
+}
+
+pub struct Ancestors<'a, T>
+where
+    T: PartialEq + Display,
+{
+    tree: &'a Tree<T>,
+    current: Option<NodeId>,
+}
+
+impl<'a, T> Iterator for Ancestors<'a, T>
+where
+    T: PartialEq + Display,
+{
+    type Item = NodeId;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node_id = self.current?;
+        self.current = self.tree.arena.nodes[node_id].parent;
+        Some(node_id)
+    }
+}
+
 struct Arena<T>
 where
     T: PartialEq + Display,
 {
     nodes: Vec<Node<T>>,
+    /// number of nodes not removed
+    size: usize,
 }
 
 impl<T> Arena<T>
@@ -136,8 +497,228 @@ where
     T: PartialEq + Display,
 {
     fn new() -> Self {
-        Self { nodes: vec![] }
+        Self {
+            nodes: vec![],
+            size: 0,
+        }
     }
 }
 
 pub type NodeId = usize;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::semantic::concept::{AtomicConcept, AtomicConceptType, CompositeConcept, Concept};
+
+    /// `a` with children `b` and `c`, and `d` under `b`
+    fn sample() -> (Tree<String>, [NodeId; 4]) {
+        let mut tree = Tree::new();
+        let a = tree.insert_node(None, "a".to_string());
+        let b = tree.insert_node(Some(a), "b".to_string());
+        let c = tree.insert_node(Some(a), "c".to_string());
+        let d = tree.insert_node(Some(b), "d".to_string());
+        (tree, [a, b, c, d])
+    }
+
+    #[test]
+    fn move_node_under_prepend_takes_the_node_from_its_parent() {
+        let (mut tree, [a, b, c, d]) = sample();
+        tree.move_node_under_prepend(d, Some(c)).unwrap();
+        assert_eq!(tree.get_children(b), vec![]);
+        assert_eq!(tree.get_children(c), vec![d]);
+        assert_eq!(tree.get_parent(d), Some(c));
+        tree.move_node_under_prepend(c, Some(b)).unwrap();
+        assert_eq!(tree.get_children(a), vec![b]);
+        assert_eq!(tree.get_children(b), vec![c]);
+    }
+
+    #[test]
+    fn detach_keeps_the_subtree_in_the_arena() {
+        let (mut tree, [a, b, c, d]) = sample();
+        tree.detach(b);
+        assert_eq!(tree.get_children(a), vec![c]);
+        assert_eq!(tree.get_parent(b), None);
+        assert_eq!(tree.get_children(b), vec![d]);
+        assert_eq!(tree.size(), 4);
+        assert_eq!(tree.get_root(), a);
+    }
+
+    #[test]
+    fn insert_child_at_places_the_child_and_rejects_cycles() {
+        let (mut tree, [a, b, c, d]) = sample();
+        tree.insert_child_at(a, 1, d).unwrap();
+        assert_eq!(tree.get_children(a), vec![b, d, c]);
+        assert_eq!(tree.get_children(b), vec![]);
+
+        assert_eq!(tree.insert_child_at(d, 0, a), Err(TreeError::Cycle));
+        assert_eq!(tree.insert_child_at(b, 0, b), Err(TreeError::Cycle));
+        tree.insert_child_at(d, 0, b).unwrap();
+        assert_eq!(tree.insert_child_at(b, 0, d), Err(TreeError::Cycle));
+        assert_eq!(tree.get_children(a), vec![d, c]);
+        assert_eq!(tree.get_children(d), vec![b]);
+    }
+
+    #[test]
+    fn replace_node_puts_the_new_subtree_in_place() {
+        let (mut tree, [a, b, c, d]) = sample();
+        let e = tree.insert_node(None, "e".to_string());
+        tree.replace_node(b, e).unwrap();
+        assert_eq!(tree.get_children(a), vec![e, c]);
+        assert_eq!(tree.get_parent(e), Some(a));
+        assert_eq!(tree.get_parent(b), None);
+        assert_eq!(tree.get_children(b), vec![d]);
+
+        // the old node can be reused under the new one
+        tree.insert_child_at(e, 0, b).unwrap();
+        assert_eq!(tree.get_children(e), vec![b]);
+        assert_eq!(tree.get_root(), a);
+    }
+
+    #[test]
+    fn replace_node_rejects_the_node_itself_and_its_ancestors() {
+        let (mut tree, [a, b, c, d]) = sample();
+        assert_eq!(tree.replace_node(b, b), Err(TreeError::SameNode));
+        assert_eq!(tree.replace_node(d, b), Err(TreeError::Cycle));
+        assert_eq!(tree.replace_node(d, a), Err(TreeError::Cycle));
+        assert_eq!(tree.get_children(a), vec![b, c]);
+        assert_eq!(tree.get_children(b), vec![d]);
+        assert_eq!(tree.get_parent(b), Some(a));
+
+        // a descendant can take the place of its ancestor
+        tree.replace_node(b, d).unwrap();
+        assert_eq!(tree.get_children(a), vec![d, c]);
+        assert_eq!(tree.get_children(b), vec![]);
+    }
+
+    #[test]
+    fn remove_subtree_drops_the_node_and_its_descendants() {
+        let (mut tree, [a, b, c, _]) = sample();
+        tree.remove_subtree(b);
+        assert_eq!(tree.get_children(a), vec![c]);
+        assert_eq!(tree.size(), 2);
+        tree.remove_subtree(a);
+        assert_eq!(tree.size(), 0);
+        assert!(!tree.has_root());
+    }
+
+    #[test]
+    fn sibling_accessors() {
+        let (mut tree, [a, b, c, d]) = sample();
+        let e = tree.insert_node(Some(a), "e".to_string());
+        assert_eq!(tree.get_child_index(c), Some(1));
+        assert_eq!(tree.get_child_index(a), None);
+        assert_eq!(tree.get_siblings(c), vec![b, e]);
+        assert_eq!(tree.get_siblings(d), vec![]);
+        assert_eq!(tree.get_prev_sibling(c), Some(b));
+        assert_eq!(tree.get_prev_sibling(b), None);
+        assert_eq!(tree.get_next_sibling(c), Some(e));
+        assert_eq!(tree.get_next_sibling(e), None);
+    }
+
+    #[test]
+    fn root_follows_the_tree_built_from_the_leaves_up() {
+        let mut tree = Tree::new();
+        let x = tree.insert_node(None, "x".to_string());
+        let y = tree.insert_node(None, "y".to_string());
+        let sum = tree.insert_node_over("+".to_string(), &[x, y]);
+        assert_eq!(tree.get_root(), sum);
+        assert_eq!(tree.get_children(sum), vec![x, y]);
+
+        // a node inserted later is not the root until it holds the tree
+        let z = tree.insert_node(None, "z".to_string());
+        assert_eq!(tree.get_root(), sum);
+        let product = tree.insert_node(None, "*".to_string());
+        tree.insert_child_at(product, 0, sum).unwrap();
+        tree.insert_child_at(product, 1, z).unwrap();
+        assert_eq!(tree.get_root(), product);
+        assert_eq!(tree.to_string(), "*\n| +\n| | x\n| | y\n| z\n");
+    }
+
+    #[test]
+    fn traversal_orders() {
+        let (mut tree, [a, b, c, d]) = sample();
+        let e = tree.insert_node(Some(c), "e".to_string());
+        assert_eq!(tree.pre_order(a).collect::<Vec<_>>(), vec![a, b, d, c, e]);
+        assert_eq!(tree.post_order(a).collect::<Vec<_>>(), vec![d, b, e, c, a]);
+        assert_eq!(tree.level_order(a).collect::<Vec<_>>(), vec![a, b, c, d, e]);
+        assert_eq!(tree.pre_order(c).collect::<Vec<_>>(), vec![c, e]);
+        assert_eq!(tree.post_order(d).collect::<Vec<_>>(), vec![d]);
+        assert_eq!(tree.children(a).collect::<Vec<_>>(), vec![b, c]);
+    }
+
+    #[test]
+    fn ancestors_descendants_and_depth() {
+        let (tree, [a, b, c, d]) = sample();
+        assert_eq!(tree.ancestors(d).collect::<Vec<_>>(), vec![b, a]);
+        assert_eq!(tree.ancestors(a).count(), 0);
+        assert_eq!(tree.descendants(a).collect::<Vec<_>>(), vec![b, d, c]);
+        assert_eq!(tree.descendants(c).count(), 0);
+        assert_eq!(tree.depth(a), 0);
+        assert_eq!(tree.depth(d), 2);
+    }
+
+    #[test]
+    fn find_all_under_a_node() {
+        let (mut tree, [a, b, _, d]) = sample();
+        let other_d = tree.insert_node(Some(a), "d".to_string());
+        assert_eq!(tree.find_all(a, |value| value == "d"), vec![d, other_d]);
+        assert_eq!(tree.find_all(b, |value| value == "d"), vec![d]);
+        assert_eq!(tree.find_all(a, |value| value == "z"), vec![]);
+    }
+
+    #[test]
+    fn size_counts_the_nodes_left() {
+        let (mut tree, [_, b, c, _]) = sample();
+        assert_eq!(tree.size(), 4);
+        tree.insert_node(Some(c), "e".to_string());
+        assert_eq!(tree.size(), 5);
+        tree.remove_subtree(b);
+        assert_eq!(tree.size(), 3);
+        tree.remove_subtree(b);
+        assert_eq!(tree.size(), 3);
+    }
+
+    #[test]
+    fn to_dot_labels_and_highlights_the_subtree() {
+        let mut tree = Tree::new();
+        let program = tree.insert_node(None, Concept::CompositeConcept(CompositeConcept::Prog));
+        let id = tree.insert_node(
+            Some(program),
+            Concept::AtomicConcept(AtomicConcept {
+                atomic_concept_type: AtomicConceptType::Id,
+                value: "counter".to_string(),
+            }),
+        );
+        let dot = tree.to_dot(program, Concept::label, &HashSet::from([id]));
+        assert_eq!(
+            dot,
+            format!(
+                "digraph tree {{\n    node [shape=box];\n    n{0} [label=\"Prog\"];\n    n{0} -> n{1};\n    n{1} [label=\"Id: counter\", style=filled, fillcolor=\"#ff9999\"];\n}}\n",
+                program, id
+            )
+        );
+    }
+
+    #[test]
+    fn to_dot_exports_the_subtree_of_any_node() {
+        let (tree, [_, b, _, d]) = sample();
+        assert_eq!(
+            tree.to_dot(b, String::clone, &HashSet::new()),
+            format!(
+                "digraph tree {{\n    node [shape=box];\n    n{0} [label=\"b\"];\n    n{0} -> n{1};\n    n{1} [label=\"d\"];\n}}\n",
+                b, d
+            )
+        );
+    }
+
+    #[test]
+    fn to_dot_escapes_the_labels() {
+        assert_eq!(escape_dot("a \"b\"\\n\nc"), "a \\\"b\\\"\\\\n\\nc");
+        let mut tree = Tree::new();
+        let root = tree.insert_node(None, "say \"hi\"\n".to_string());
+        assert!(tree
+            .to_dot(root, String::clone, &HashSet::new())
+            .contains("[label=\"say \\\"hi\\\"\\n\"];"));
+    }
+}
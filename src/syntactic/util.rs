@@ -1,10 +1,12 @@
 use crate::syntactic::derivation::Derivation;
-use crate::syntactic::symbol::{NonTerminal, Symbol, Terminal};
+use crate::syntactic::semantic_action::SemanticAction;
+use crate::syntactic::symbol::{ActionSymbol, NonTerminal, Symbol, Terminal};
 use regex::Regex;
 use std::collections::HashMap;
 use std::fs::File;
 use std::io::{self, BufRead};
 use std::path::Path;
+use std::str::FromStr;
 
 pub fn read_parsing_table() -> HashMap<(NonTerminal, Terminal), Derivation> {
     // let mut table: HashMap<(NonTerminal, ValidTokenType), Derivation> = HashMap::new();
@@ -15,7 +17,7 @@ pub fn read_parsing_table() -> HashMap<(NonTerminal, Terminal), Derivation> {
         // 1. build terminals and non_terminals
         for (i, line_result) in lines.enumerate() {
             if let Ok(line) = line_result {
-                let cells = split_string(&*line, r",");
+                let cells = split_string(&line, r",");
                 if i == 0 {
                     // first line: build terminal vector starting from the third column
                     for (j, cell) in cells.iter().enumerate() {
@@ -41,10 +43,10 @@ pub fn read_parsing_table() -> HashMap<(NonTerminal, Terminal), Derivation> {
     if let Ok(lines) = read_lines("resource/ast/LL(1) Parsing Table with Actions.csv") {
         for (i, line_result) in lines.enumerate() {
             if let Ok(line) = line_result {
-                let cells = split_string(&*line, r",");
+                let cells = split_string(&line, r",");
                 if i > 0 {
                     for (j, cell) in cells.iter().enumerate() {
-                        if j > 0 && !cell.eq("") {
+                        if j > 0 && !cell.is_empty() {
                             table.insert(
                                 (non_terminals[i - 1].clone(), terminals[j - 1].clone()),
                                 Derivation::new(cell),
@@ -58,22 +60,22 @@ pub fn read_parsing_table() -> HashMap<(NonTerminal, Terminal), Derivation> {
     table
 }
 
-pub fn read_first_follow_set_and_endable() -> (
-    HashMap<NonTerminal, Vec<Terminal>>,
-    HashMap<NonTerminal, Vec<Terminal>>,
-    HashMap<NonTerminal, bool>,
-) {
+/// Terminals of each nonterminal, as in the first and follow sets
+type TerminalSets = HashMap<NonTerminal, Vec<Terminal>>;
+
+pub fn read_first_follow_set_and_endable(
+) -> (TerminalSets, TerminalSets, HashMap<NonTerminal, bool>) {
     let mut first_set = HashMap::new();
     let mut follow_set = HashMap::new();
     let mut endable = HashMap::new();
+    let space = Regex::new(r" ").unwrap();
     if let Ok(lines) = read_lines("resource/syntax/fst_flw.csv") {
         for (i, line_result) in lines.enumerate() {
             if let Ok(line) = line_result {
                 if i > 0 {
-                    let cells = split_string(&*line, r",");
+                    let cells = split_string(&line, r",");
                     if let Symbol::NonTerminal(key) = Symbol::from_string(&cells[0]) {
-                        let mut first_terminals: Vec<Terminal> = Regex::new(r" ")
-                            .unwrap()
+                        let mut first_terminals: Vec<Terminal> = space
                             .split(&cells[1])
                             .map(|x| match Symbol::from_string(x.trim()) {
                                 Symbol::Terminal(terminal) => terminal,
@@ -91,8 +93,7 @@ pub fn read_first_follow_set_and_endable() -> (
                         first_set.insert(key.clone(), first_terminals);
 
                         // build follow set
-                        let follow_terminals: Vec<Terminal> = Regex::new(r" ")
-                            .unwrap()
+                        let follow_terminals: Vec<Terminal> = space
                             .split(&cells[2])
                             .filter(|x| !(*x).eq("∅"))
                             .map(|x| match Symbol::from_string(x.trim()) {
@@ -120,6 +121,57 @@ pub fn read_first_follow_set_and_endable() -> (
     }
     (first_set, follow_set, endable)
 }
+/// Semantic action of each action symbol, declared alongside the parsing table with actions
+pub fn read_semantic_actions() -> HashMap<ActionSymbol, SemanticAction> {
+    let mut semantic_actions = HashMap::new();
+    if let Ok(lines) = read_lines("resource/ast/Semantic Actions.csv") {
+        for (i, line_result) in lines.enumerate() {
+            if let Ok(line) = line_result {
+                if i > 0 {
+                    let cells = split_string(&line, r",");
+                    let action_symbol = ActionSymbol::from_str(&cells[0])
+                        .unwrap_or_else(|_| panic!("Unknown action symbol {}", cells[0]));
+                    let semantic_action = SemanticAction::from_cells(&cells[1], &cells[2], &cells[3])
+                        .unwrap_or_else(|e| panic!("{} for action symbol {}", e, cells[0]));
+                    semantic_actions.insert(action_symbol, semantic_action);
+                }
+            }
+        }
+    }
+    semantic_actions
+}
+
+/// Text of the LALR(1) grammar with actions
+pub fn read_lalr_grammar() -> String {
+    std::fs::read_to_string("resource/ast/LALR(1) Grammar.txt").unwrap_or_default()
+}
+
+/// Synchronizing set of each nonterminal for panic-mode recovery: its follow set,
+/// the end of input, and the tokens starting a top-level definition
+pub fn build_sync_set(
+    first_set: &HashMap<NonTerminal, Vec<Terminal>>,
+    follow_set: &HashMap<NonTerminal, Vec<Terminal>>,
+) -> HashMap<NonTerminal, Vec<Terminal>> {
+    let mut top_level_terminals = first_set
+        .get(&NonTerminal::StructOrImplOrFunc)
+        .cloned()
+        .unwrap_or_default();
+    top_level_terminals.retain(|terminal| !matches!(terminal, Terminal::EPSILON));
+    top_level_terminals.push(Terminal::EOF);
+
+    let mut sync_set = HashMap::new();
+    for (non_terminal, follow_terminals) in follow_set {
+        let mut sync_terminals = follow_terminals.clone();
+        for terminal in top_level_terminals.iter() {
+            if !sync_terminals.contains(terminal) {
+                sync_terminals.push(terminal.clone());
+            }
+        }
+        sync_set.insert(non_terminal.clone(), sync_terminals);
+    }
+    sync_set
+}
+
 fn split_string(text: &str, regex: &str) -> Vec<String> {
     Regex::new(regex)
         .unwrap()
@@ -135,3 +187,20 @@ where
     let file = File::open(file_name)?;
     Ok(io::BufReader::new(file).lines())
 }
+
+/// Quote and escape a string as a JSON string literal
+pub fn json_string(s: &str) -> String {
+    let mut escaped = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => escaped += "\\\"",
+            '\\' => escaped += "\\\\",
+            '\n' => escaped += "\\n",
+            '\r' => escaped += "\\r",
+            '\t' => escaped += "\\t",
+            c if (c as u32) < 0x20 => escaped += &format!("\\u{:04x}", c as u32),
+            c => escaped.push(c),
+        }
+    }
+    escaped + "\""
+}